@@ -0,0 +1,363 @@
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::color;
+use crate::commands::{gen_id, wildcard_match};
+use crate::connection::{persistent_sender, Response};
+use crate::output::{error_json, fail, ErrorCode};
+
+const USAGE: &str = "assert <text|visible|url|title> [selector] [expected] [--contains|--regex] [--timeout <ms>]";
+
+/// How long to wait between attempts while `--timeout` hasn't run out
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What an assertion checks
+#[derive(Debug, PartialEq)]
+pub enum Subject {
+    Text(String),
+    Visible(String),
+    Url,
+    Title,
+}
+
+/// How `actual` is compared with `expected`
+#[derive(Debug)]
+pub enum Matcher {
+    Exact(String),
+    Contains(String),
+    Regex(Regex),
+    /// `*` wildcards (URLs)
+    Glob(String),
+}
+
+impl Matcher {
+    pub fn matches(&self, actual: &str) -> bool {
+        match self {
+            Matcher::Exact(expected) => actual == expected,
+            Matcher::Contains(expected) => actual.contains(expected.as_str()),
+            Matcher::Regex(re) => re.is_match(actual),
+            Matcher::Glob(pattern) => wildcard_match(pattern, actual),
+        }
+    }
+
+    fn expected(&self) -> &str {
+        match self {
+            Matcher::Exact(s) | Matcher::Contains(s) | Matcher::Glob(s) => s,
+            Matcher::Regex(re) => re.as_str(),
+        }
+    }
+
+    fn mode(&self) -> &'static str {
+        match self {
+            Matcher::Exact(_) => "equals",
+            Matcher::Contains(_) => "contains",
+            Matcher::Regex(_) => "matches",
+            Matcher::Glob(_) => "matches",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Assertion {
+    pub subject: Subject,
+    /// `None` for `visible`, which only needs the element to be shown
+    pub matcher: Option<Matcher>,
+    pub timeout: Duration,
+}
+
+/// Result of the last attempt
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub pass: bool,
+    pub actual: Value,
+    /// Query error (e.g. element not found), if the daemon reported one
+    pub error: Option<String>,
+}
+
+pub fn parse_assert(rest: &[String]) -> Result<Assertion, String> {
+    let mut positionals: Vec<&str> = Vec::new();
+    let mut contains = false;
+    let mut regex = false;
+    let mut timeout = Duration::ZERO;
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i].as_str() {
+            "--contains" => contains = true,
+            "--regex" => regex = true,
+            "--timeout" => {
+                let ms = rest
+                    .get(i + 1)
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .ok_or_else(|| format!("--timeout expects milliseconds\nUsage: {}", USAGE))?;
+                timeout = Duration::from_millis(ms);
+                i += 1;
+            }
+            arg => positionals.push(arg),
+        }
+        i += 1;
+    }
+    if contains && regex {
+        return Err("--contains and --regex cannot be combined".to_string());
+    }
+
+    let missing = |what: &str| format!("assert {}: missing arguments\nUsage: {}", what, USAGE);
+    let (subject, expected) = match positionals.as_slice() {
+        ["text", selector, expected] => (Subject::Text(selector.to_string()), Some(*expected)),
+        ["text", ..] => return Err(missing("text")),
+        ["visible", selector] => (Subject::Visible(selector.to_string()), None),
+        ["visible", ..] => return Err(missing("visible")),
+        ["url", pattern] => (Subject::Url, Some(*pattern)),
+        ["url", ..] => return Err(missing("url")),
+        ["title", expected] => (Subject::Title, Some(*expected)),
+        ["title", ..] => return Err(missing("title")),
+        [kind, ..] => {
+            return Err(format!("Unknown assertion: {}\nValid options: text, visible, url, title", kind))
+        }
+        [] => return Err(format!("Missing assertion\nUsage: {}", USAGE)),
+    };
+
+    let matcher = match expected {
+        None => None,
+        Some(e) if regex => Some(Matcher::Regex(Regex::new(e).map_err(|err| format!("Invalid --regex: {}", err))?)),
+        Some(e) if contains => Some(Matcher::Contains(e.to_string())),
+        Some(e) if subject == Subject::Url && e.contains('*') => Some(Matcher::Glob(e.to_string())),
+        Some(e) => Some(Matcher::Exact(e.to_string())),
+    };
+    Ok(Assertion { subject, matcher, timeout })
+}
+
+impl Assertion {
+    fn name(&self) -> &'static str {
+        match self.subject {
+            Subject::Text(_) => "text",
+            Subject::Visible(_) => "visible",
+            Subject::Url => "url",
+            Subject::Title => "title",
+        }
+    }
+
+    /// The query command whose answer is checked
+    pub fn query(&self) -> Value {
+        let id = gen_id();
+        match &self.subject {
+            Subject::Text(selector) => json!({ "id": id, "action": "gettext", "selector": selector }),
+            Subject::Visible(selector) => json!({ "id": id, "action": "isvisible", "selector": selector }),
+            Subject::Url => json!({ "id": id, "action": "url" }),
+            Subject::Title => json!({ "id": id, "action": "title" }),
+        }
+    }
+
+    /// Compare a query response with the expectation
+    pub fn evaluate(&self, resp: &Response) -> Outcome {
+        if !resp.success {
+            return Outcome {
+                pass: false,
+                actual: Value::Null,
+                error: Some(resp.error.clone().unwrap_or_else(|| "Unknown error".to_string())),
+            };
+        }
+        // Each query answers with a field named after the assertion
+        let actual = resp.data.as_ref().and_then(|d| d.get(self.name())).cloned().unwrap_or(Value::Null);
+        let pass = match (&self.matcher, &actual) {
+            (None, Value::Bool(visible)) => *visible,
+            (Some(matcher), Value::String(s)) => matcher.matches(s),
+            _ => false,
+        };
+        Outcome { pass, actual, error: None }
+    }
+
+    fn expected(&self) -> Value {
+        match &self.matcher {
+            Some(m) => json!(m.expected()),
+            None => json!(true),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let target = match &self.subject {
+            Subject::Text(selector) => format!("text of {}", selector),
+            Subject::Visible(selector) => return format!("{} is visible", selector),
+            Subject::Url => "url".to_string(),
+            Subject::Title => "title".to_string(),
+        };
+        match &self.matcher {
+            Some(m) => format!("{} {} {:?}", target, m.mode(), m.expected()),
+            None => target,
+        }
+    }
+}
+
+/// Query until the assertion passes or `timeout` has been spent waiting.
+/// With no timeout there is exactly one attempt.
+pub fn check<F, S>(assertion: &Assertion, send: &mut F, mut sleep: S) -> Outcome
+where
+    F: FnMut(Value) -> Result<Response, String>,
+    S: FnMut(Duration),
+{
+    let start = Instant::now();
+    let mut waited = Duration::ZERO;
+    loop {
+        let outcome = match send(assertion.query()) {
+            Ok(resp) => assertion.evaluate(&resp),
+            Err(e) => Outcome { pass: false, actual: Value::Null, error: Some(e) },
+        };
+        if outcome.pass || waited >= assertion.timeout || start.elapsed() >= assertion.timeout {
+            return outcome;
+        }
+        let pause = POLL_INTERVAL.min(assertion.timeout - waited);
+        sleep(pause);
+        waited += pause;
+    }
+}
+
+pub fn run_assert(clean: &[String], session: &str, json_mode: bool) {
+    let assertion = match parse_assert(&clean[1..]) {
+        Ok(a) => a,
+        Err(e) => fail(ErrorCode::ParseError, &e, json_mode),
+    };
+    let mut send = persistent_sender(session);
+    let outcome = check(&assertion, &mut send, thread::sleep);
+
+    let message = match (&outcome.error, outcome.pass) {
+        (_, true) => assertion.describe(),
+        (Some(e), false) => format!("{}: {}", assertion.describe(), e),
+        (None, false) => format!("{}: got {}", assertion.describe(), outcome.actual),
+    };
+    if json_mode {
+        let data = json!({
+            "assertion": assertion.name(),
+            "pass": outcome.pass,
+            "actual": outcome.actual,
+            "expected": assertion.expected(),
+        });
+        if outcome.pass {
+            println!("{}", json!({ "success": true, "data": data }));
+        } else {
+            let mut value: Value = serde_json::from_str(&error_json(ErrorCode::CommandFailed, &message)).unwrap_or_default();
+            value["data"] = data;
+            println!("{}", value);
+        }
+    } else if outcome.pass {
+        println!("{} {}", color::success_indicator(), message);
+    } else {
+        eprintln!("{} {}", color::error_indicator(), message);
+    }
+    if !outcome.pass {
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Assertion, String> {
+        let args: Vec<String> = s.split_whitespace().map(String::from).collect();
+        parse_assert(&args)
+    }
+
+    fn data(value: Value) -> Response {
+        Response { success: true, data: Some(value), error: None }
+    }
+
+    #[test]
+    fn test_text_exact() {
+        let a = parse("text #title Welcome").unwrap();
+        assert_eq!(a.subject, Subject::Text("#title".to_string()));
+        assert!(a.evaluate(&data(json!({ "text": "Welcome" }))).pass);
+        assert!(!a.evaluate(&data(json!({ "text": "Welcome back" }))).pass);
+    }
+
+    #[test]
+    fn test_text_contains() {
+        let a = parse("text #title Welcome --contains").unwrap();
+        assert!(a.evaluate(&data(json!({ "text": "Welcome back" }))).pass);
+        assert!(!a.evaluate(&data(json!({ "text": "Goodbye" }))).pass);
+    }
+
+    #[test]
+    fn test_regex() {
+        let a = parse("title ^Order\\s#\\d+$ --regex").unwrap();
+        assert!(a.evaluate(&data(json!({ "title": "Order #42" }))).pass);
+        assert!(!a.evaluate(&data(json!({ "title": "Order #x" }))).pass);
+        assert!(parse("title ( --regex").unwrap_err().contains("--regex"));
+    }
+
+    #[test]
+    fn test_url_glob_and_visible() {
+        let a = parse("url https://example.com/*/settings").unwrap();
+        assert!(a.evaluate(&data(json!({ "url": "https://example.com/u/settings" }))).pass);
+        assert!(!a.evaluate(&data(json!({ "url": "https://example.com/settings" }))).pass);
+
+        let v = parse("visible #modal").unwrap();
+        assert!(v.evaluate(&data(json!({ "visible": true }))).pass);
+        assert!(!v.evaluate(&data(json!({ "visible": false }))).pass);
+        assert_eq!(v.query()["action"], "isvisible");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("text #a").is_err());
+        assert!(parse("visible").is_err());
+        assert!(parse("color #a red").unwrap_err().contains("Unknown assertion"));
+        assert!(parse("text #a b --contains --regex").is_err());
+        assert!(parse("title x --timeout soon").is_err());
+        assert_eq!(parse("title x --timeout 1500").unwrap().timeout, Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_failed_query_is_not_a_pass() {
+        let a = parse("text #missing x").unwrap();
+        let outcome = a.evaluate(&Response { success: false, data: None, error: Some("Element not found".to_string()) });
+        assert!(!outcome.pass);
+        assert_eq!(outcome.error.as_deref(), Some("Element not found"));
+    }
+
+    #[test]
+    fn test_retry_until_pass() {
+        let a = parse("text #status Ready --timeout 2000").unwrap();
+        let mut responses = vec!["Loading", "Loading", "Ready"].into_iter();
+        let mut calls = 0;
+        let mut send = |_cmd: Value| {
+            calls += 1;
+            Ok(data(json!({ "text": responses.next().unwrap_or("Ready") })))
+        };
+        let mut slept = Vec::new();
+        let outcome = check(&a, &mut send, |d| slept.push(d));
+        assert!(outcome.pass);
+        assert_eq!(calls, 3);
+        assert_eq!(slept, vec![POLL_INTERVAL, POLL_INTERVAL]);
+    }
+
+    #[test]
+    fn test_retry_gives_up_after_timeout() {
+        let a = parse("text #status Ready --timeout 600").unwrap();
+        let mut calls = 0;
+        let mut send = |_cmd: Value| {
+            calls += 1;
+            Ok(data(json!({ "text": "Loading" })))
+        };
+        let outcome = check(&a, &mut send, |_| {});
+        assert!(!outcome.pass);
+        assert_eq!(outcome.actual, json!("Loading"));
+        // 0ms, 250ms, 500ms, 600ms
+        assert_eq!(calls, 4);
+    }
+
+    #[test]
+    fn test_no_timeout_single_attempt() {
+        let a = parse("title Home").unwrap();
+        let mut calls = 0;
+        let mut send = |_cmd: Value| {
+            calls += 1;
+            Err("Failed to connect: refused".to_string())
+        };
+        let outcome = check(&a, &mut send, |_| panic!("should not sleep"));
+        assert!(!outcome.pass);
+        assert_eq!(calls, 1);
+    }
+}
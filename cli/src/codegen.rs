@@ -0,0 +1,298 @@
+use std::fs;
+
+use serde_json::{json, Value};
+
+use crate::color;
+use crate::commands::is_ref;
+use crate::flags::Flags;
+use crate::output::{fail, ErrorCode};
+use crate::repl::{parse_line, tokenize};
+use crate::script::parse_script;
+
+const USAGE: &str = "script export <file> [--format playwright-ts]";
+
+/// Formats `script export` can write
+const FORMATS: &[&str] = &["playwright-ts"];
+
+/// `script export <file>`: convert a `run` script (e.g. one written by
+/// `--record-script`) to a Playwright test, on stdout or to `--output`
+pub fn run_script_command(clean: &[String], flags: &Flags) {
+    let json_mode = flags.json;
+    match clean.get(1).map(|s| s.as_str()) {
+        Some("export") => {}
+        Some(other) => fail(
+            ErrorCode::ParseError,
+            &format!("Unknown script subcommand: {}\nUsage: {}", other, USAGE),
+            json_mode,
+        ),
+        None => fail(ErrorCode::ParseError, &format!("Missing subcommand\nUsage: {}", USAGE), json_mode),
+    }
+
+    let mut path: Option<&str> = None;
+    let mut format = "playwright-ts";
+    let mut iter = clean[2..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match iter.next() {
+                    Some(f) => f.as_str(),
+                    None => fail(ErrorCode::ParseError, &format!("Missing value for --format\nUsage: {}", USAGE), json_mode),
+                };
+            }
+            _ if path.is_none() => path = Some(arg.as_str()),
+            _ => fail(ErrorCode::ParseError, &format!("Unexpected argument: {}\nUsage: {}", arg, USAGE), json_mode),
+        }
+    }
+    let Some(path) = path else {
+        fail(ErrorCode::ParseError, &format!("Missing script file\nUsage: {}", USAGE), json_mode);
+    };
+    if !FORMATS.contains(&format) {
+        fail(
+            ErrorCode::ParseError,
+            &format!("Unknown format '{}' (expected one of: {})", format, FORMATS.join(", ")),
+            json_mode,
+        );
+    }
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => fail(ErrorCode::CommandFailed, &format!("Failed to read {}: {}", path, e), json_mode),
+    };
+
+    let code = to_playwright_ts(&content);
+    match &flags.output {
+        Some(out) => {
+            if let Err(e) = fs::write(out, &code) {
+                fail(ErrorCode::CommandFailed, &format!("Failed to write {}: {}", out, e), json_mode);
+            }
+            if json_mode {
+                println!("{}", json!({ "success": true, "data": { "path": out, "format": format } }));
+            } else {
+                println!("{} Exported {} to {}", color::success_indicator(), path, out);
+            }
+        }
+        None if json_mode => println!("{}", json!({ "success": true, "data": { "code": code, "format": format } })),
+        None => print!("{}", code),
+    }
+}
+
+/// A Playwright test running the script's commands in order. Commands with
+/// no Playwright equivalent are kept as comments.
+pub fn to_playwright_ts(script: &str) -> String {
+    let mut out = String::from("import { test } from '@playwright/test';\n\ntest('recorded flow', async ({ page }) => {\n");
+    for line in parse_script(script) {
+        let code = tokenize(&line.text)
+            .and_then(|tokens| parse_line(&[], tokens))
+            .ok()
+            .and_then(|(cmd, _)| playwright_statement(&cmd));
+        match code {
+            Some(code) => out.push_str(&format!("  {}\n", code)),
+            None => out.push_str(&format!("  // not converted: {}\n", line.text)),
+        }
+    }
+    out.push_str("});\n");
+    out
+}
+
+/// One Playwright statement for a parsed command, if it has an equivalent
+pub fn playwright_statement(cmd: &Value) -> Option<String> {
+    let action = cmd.get("action")?.as_str()?;
+    let str_field = |key: &str| cmd.get(key).and_then(|v| v.as_str());
+    let timeout = || options(&[("timeout", cmd.get("timeout").and_then(|v| v.as_u64()).map(|ms| ms.to_string()))]);
+
+    match action {
+        "navigate" => {
+            only(cmd, &["url", "waitUntil", "referer"])?;
+            let opts = options(&[("waitUntil", str_field("waitUntil").map(js_string)), ("referer", str_field("referer").map(js_string))]);
+            Some(format!("await page.goto({}{});", js_string(str_field("url")?), opts.map(|o| format!(", {}", o)).unwrap_or_default()))
+        }
+        "back" | "forward" if cmd.get("steps").and_then(|v| v.as_u64()) == Some(1) => {
+            only(cmd, &["steps"])?;
+            Some(format!("await page.{}();", if action == "back" { "goBack" } else { "goForward" }))
+        }
+        "reload" => {
+            only(cmd, &[])?;
+            Some("await page.reload();".to_string())
+        }
+        "click" | "dblclick" | "hover" | "check" | "uncheck" | "focus" => {
+            only(cmd, &["selector"])?;
+            Some(format!("await {}.{}();", locator(str_field("selector")?)?, action))
+        }
+        "fill" => {
+            only(cmd, &["selector", "value"])?;
+            Some(format!("await {}.fill({});", locator(str_field("selector")?)?, js_value(str_field("value")?)))
+        }
+        "type" => {
+            only(cmd, &["selector", "text"])?;
+            Some(format!("await {}.pressSequentially({});", locator(str_field("selector")?)?, js_value(str_field("text")?)))
+        }
+        "press" => {
+            only(cmd, &["key"])?;
+            Some(format!("await page.keyboard.press({});", js_string(str_field("key")?)))
+        }
+        "wait" => {
+            only(cmd, &["selector", "timeout"])?;
+            match str_field("selector") {
+                Some(sel) => Some(format!("await {}.waitFor({});", locator(sel)?, timeout().unwrap_or_default())),
+                None => Some(format!("await page.waitForTimeout({});", cmd.get("timeout")?.as_u64()?)),
+            }
+        }
+        "waitforurl" | "waitforloadstate" => {
+            let (key, method) = if action == "waitforurl" { ("url", "waitForURL") } else { ("state", "waitForLoadState") };
+            only(cmd, &[key, "timeout"])?;
+            let opts = timeout().map(|o| format!(", {}", o)).unwrap_or_default();
+            Some(format!("await page.{}({}{});", method, js_string(str_field(key)?), opts))
+        }
+        "waitforfunction" => {
+            only(cmd, &["expression", "timeout"])?;
+            let opts = timeout().map(|o| format!(", undefined, {}", o)).unwrap_or_default();
+            Some(format!("await page.waitForFunction({}{});", js_string(str_field("expression")?), opts))
+        }
+        "getbyrole" | "getbytext" | "getbylabel" | "getbyplaceholder" | "getbyalttext" | "getbytitle" | "getbytestid" => {
+            only(cmd, &["role", "text", "label", "placeholder", "testId", "subaction", "value", "name", "exact", "nth"])?;
+            let (method, target) = match action {
+                "getbyrole" => ("getByRole", str_field("role")),
+                "getbytext" => ("getByText", str_field("text")),
+                "getbylabel" => ("getByLabel", str_field("label")),
+                "getbyplaceholder" => ("getByPlaceholder", str_field("placeholder")),
+                "getbyalttext" => ("getByAltText", str_field("text")),
+                "getbytitle" => ("getByTitle", str_field("text")),
+                _ => ("getByTestId", str_field("testId")),
+            };
+            let exact = (cmd.get("exact").and_then(|v| v.as_bool()) == Some(true)).then(|| "true".to_string());
+            let opts = options(&[("name", str_field("name").map(js_string)), ("exact", exact)]);
+            let mut locator = format!("page.{}({}{})", method, js_string(target?), opts.map(|o| format!(", {}", o)).unwrap_or_default());
+            if let Some(n) = cmd.get("nth").and_then(|v| v.as_i64()) {
+                locator.push_str(&format!(".nth({})", n));
+            }
+            match str_field("subaction")? {
+                sub @ ("click" | "dblclick" | "hover" | "check" | "uncheck" | "focus") => Some(format!("await {}.{}();", locator, sub)),
+                "fill" => Some(format!("await {}.fill({});", locator, js_value(str_field("value")?))),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `None` unless every field besides `id` and `action` is in `known`;
+/// null and false fields count as absent
+fn only(cmd: &Value, known: &[&str]) -> Option<()> {
+    let fields = cmd.as_object()?;
+    fields
+        .iter()
+        .filter(|(key, value)| !matches!(key.as_str(), "id" | "action") && !value.is_null() && value.as_bool() != Some(false))
+        .all(|(key, _)| known.contains(&key.as_str()))
+        .then_some(())
+}
+
+/// `{ key: value, ... }` from the fields that are set
+fn options(fields: &[(&str, Option<String>)]) -> Option<String> {
+    let set: Vec<String> = fields
+        .iter()
+        .filter_map(|(key, value)| value.as_ref().map(|v| format!("{}: {}", key, v)))
+        .collect();
+    (!set.is_empty()).then(|| format!("{{ {} }}", set.join(", ")))
+}
+
+/// `page.locator(...)`; element refs only mean something in a live session
+fn locator(selector: &str) -> Option<String> {
+    (!is_ref(selector)).then(|| format!("page.locator({})", js_string(selector)))
+}
+
+/// A single-quoted JavaScript string literal
+fn js_string(s: &str) -> String {
+    let mut out = String::from("'");
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// A typed value; a whole `${NAME}` placeholder (from `--redact`) reads the
+/// environment variable instead
+fn js_value(s: &str) -> String {
+    match s.strip_prefix("${").and_then(|rest| rest.strip_suffix('}')) {
+        Some(name) if is_identifier(name) => format!("process.env.{} ?? ''", name),
+        _ => js_string(s),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(line: &str) -> Option<String> {
+        let (cmd, _) = parse_line(&[], tokenize(line).unwrap()).unwrap();
+        playwright_statement(&cmd)
+    }
+
+    #[test]
+    fn test_playwright_mapping() {
+        let cases = [
+            ("open example.com", "await page.goto('https://example.com');"),
+            (
+                "open example.com/app --wait-until networkidle",
+                "await page.goto('https://example.com/app', { waitUntil: 'networkidle' });",
+            ),
+            ("click #submit", "await page.locator('#submit').click();"),
+            ("dblclick .row", "await page.locator('.row').dblclick();"),
+            ("fill #email \"a@b.co\"", "await page.locator('#email').fill('a@b.co');"),
+            ("fill #password ${PASSWORD}", "await page.locator('#password').fill(process.env.PASSWORD ?? '');"),
+            ("fill '#q' \"it's here\"", "await page.locator('#q').fill('it\\'s here');"),
+            ("press Enter", "await page.keyboard.press('Enter');"),
+            ("wait #results", "await page.locator('#results').waitFor();"),
+            ("wait #results --timeout 5000", "await page.locator('#results').waitFor({ timeout: 5000 });"),
+            ("wait 1500", "await page.waitForTimeout(1500);"),
+            ("wait --url **/dashboard", "await page.waitForURL('**/dashboard');"),
+            ("wait --load networkidle --timeout 9000", "await page.waitForLoadState('networkidle', { timeout: 9000 });"),
+            ("wait --text Welcome", "await page.locator('text=Welcome').waitFor();"),
+            ("find role button click --name Submit", "await page.getByRole('button', { name: 'Submit' }).click();"),
+            ("find label Email fill a@b.co", "await page.getByLabel('Email').fill('a@b.co');"),
+            ("find text Next click --exact --nth 1", "await page.getByText('Next', { exact: true }).nth(1).click();"),
+            ("find testid login", "await page.getByTestId('login').click();"),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(statement(line).as_deref(), Some(expected), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_playwright_unmappable() {
+        for line in ["snapshot -i", "click @e2", "fill @e3 hi", "find role button --locate", "find text Go click --within #nav", "scroll down"] {
+            assert_eq!(statement(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_to_playwright_ts() {
+        let code = to_playwright_ts("# login\nopen example.com\nsnapshot -i\nclick #go\n");
+        assert_eq!(
+            code,
+            "import { test } from '@playwright/test';\n\n\
+             test('recorded flow', async ({ page }) => {\n  \
+             await page.goto('https://example.com');\n  \
+             // not converted: snapshot -i\n  \
+             await page.locator('#go').click();\n\
+             });\n"
+        );
+    }
+
+    #[test]
+    fn test_js_string_escapes() {
+        assert_eq!(js_string("a\\b'c\nd"), "'a\\\\b\\'c\\nd'");
+        assert_eq!(js_value("${1X}"), "'${1X}'");
+        assert_eq!(js_value("pre ${X}"), "'pre ${X}'");
+    }
+}
@@ -1,13 +1,60 @@
-//! Color output utilities respecting NO_COLOR environment variable.
+//! Color output utilities respecting `--color`, NO_COLOR and CLICOLOR_FORCE.
 //! When NO_COLOR is set, all color formatting is disabled per https://no-color.org/
+//! unless `--color always` or CLICOLOR_FORCE asks for it.
 
 use std::env;
+use std::io::IsTerminal;
 use std::sync::OnceLock;
 
-/// Returns true if color output is enabled (NO_COLOR is NOT set)
+/// `--color`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    /// Color when stdout is a terminal, subject to NO_COLOR / CLICOLOR_FORCE
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn parse(value: &str) -> Result<ColorMode, String> {
+        match value {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            other => Err(format!("--color: unknown mode '{}' (expected auto, always or never)", other)),
+        }
+    }
+}
+
+static MODE: OnceLock<ColorMode> = OnceLock::new();
+
+/// Set the `--color` mode. Call before anything is printed: the first
+/// [`is_enabled`] fixes the decision for the rest of the process.
+pub fn set_mode(mode: ColorMode) {
+    let _ = MODE.set(mode);
+}
+
+/// Whether to color, given `--color`, the environment and whether stdout
+/// is a terminal. The flag wins, then CLICOLOR_FORCE, then NO_COLOR.
+pub fn decide(mode: ColorMode, no_color: bool, clicolor_force: bool, is_tty: bool) -> bool {
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => clicolor_force || (!no_color && is_tty),
+    }
+}
+
+/// Returns true if color output is enabled
 pub fn is_enabled() -> bool {
     static COLORS_ENABLED: OnceLock<bool> = OnceLock::new();
-    *COLORS_ENABLED.get_or_init(|| env::var("NO_COLOR").is_err())
+    *COLORS_ENABLED.get_or_init(|| {
+        decide(
+            MODE.get().copied().unwrap_or(ColorMode::Auto),
+            env::var_os("NO_COLOR").is_some(),
+            env::var("CLICOLOR_FORCE").is_ok_and(|v| !v.is_empty() && v != "0"),
+            std::io::stdout().is_terminal(),
+        )
+    })
 }
 
 /// Format text in red (errors)
@@ -37,15 +84,6 @@ pub fn yellow(text: &str) -> String {
     }
 }
 
-/// Format text in cyan (info)
-pub fn cyan(text: &str) -> String {
-    if is_enabled() {
-        format!("\x1b[36m{}\x1b[0m", text)
-    } else {
-        text.to_string()
-    }
-}
-
 /// Format text in bold
 pub fn bold(text: &str) -> String {
     if is_enabled() {
@@ -117,3 +155,37 @@ pub fn console_level_prefix(level: &str) -> String {
         format!("{}[{}]\x1b[0m", color, level)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(ColorMode::parse("auto").unwrap(), ColorMode::Auto);
+        assert_eq!(ColorMode::parse("always").unwrap(), ColorMode::Always);
+        assert_eq!(ColorMode::parse("never").unwrap(), ColorMode::Never);
+        assert!(ColorMode::parse("yes").unwrap_err().contains("'yes'"));
+    }
+
+    #[test]
+    fn test_decision_matrix() {
+        use ColorMode::*;
+        // (mode, NO_COLOR, CLICOLOR_FORCE, stdout is a tty) → color
+        let cases = [
+            (Auto, false, false, true, true),
+            (Auto, false, false, false, false),
+            (Auto, true, false, true, false),
+            (Auto, true, false, false, false),
+            (Auto, false, true, false, true),
+            (Auto, true, true, false, true),
+            (Always, false, false, false, true),
+            (Always, true, false, false, true),
+            (Never, false, false, true, false),
+            (Never, false, true, true, false),
+        ];
+        for (mode, no_color, force, tty, expected) in cases {
+            assert_eq!(decide(mode, no_color, force, tty), expected, "{:?} NO_COLOR={} FORCE={} tty={}", mode, no_color, force, tty);
+        }
+    }
+}
@@ -1,5 +1,13 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
 use serde_json::{json, Value};
 
+use crate::devices::{closest_device, DEVICES};
 use crate::flags::Flags;
 
 /// Error type for command parsing with contextual information
@@ -45,18 +53,45 @@ impl ParseError {
     }
 }
 
+/// Request id: `r<random>-<counter>`. The counter makes ids unique within
+/// this process; the random prefix (64 bits, base36) keeps two processes
+/// talking to one daemon from reusing each other's ids.
 pub fn gen_id() -> String {
-    format!(
-        "r{}",
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_micros()
-            % 1000000
-    )
+    static PREFIX: OnceLock<String> = OnceLock::new();
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let prefix = PREFIX.get_or_init(|| {
+        // RandomState is keyed from the OS random source
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            hasher.write_u128(now.as_nanos());
+        }
+        base36(hasher.finish())
+    });
+    format!("r{}-{}", prefix, base36(COUNTER.fetch_add(1, Ordering::Relaxed)))
+}
+
+fn base36(mut n: u64) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut out = Vec::new();
+    loop {
+        out.push(DIGITS[(n % 36) as usize]);
+        n /= 36;
+        if n == 0 {
+            break;
+        }
+    }
+    out.reverse();
+    String::from_utf8(out).unwrap_or_default()
 }
 
 pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError> {
+    parse_command_with_stdin(args, flags, std::io::stdin())
+}
+
+/// `parse_command`, reading `-` values (and `clipboard write --stdin`) from
+/// `stdin`. Nothing is read unless the command asks for it.
+pub fn parse_command_with_stdin(args: &[String], flags: &Flags, mut stdin: impl Read) -> Result<Value, ParseError> {
     if args.is_empty() {
         return Err(ParseError::MissingArguments {
             context: "".to_string(),
@@ -70,88 +105,107 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
     match cmd {
         // === Navigation ===
-        "open" | "goto" | "navigate" => {
-            let url = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: cmd.to_string(),
-                usage: "open <url>",
-            })?;
-            let url = if url.starts_with("http") || url.starts_with("about:") || url.starts_with("data:") || url.starts_with("file:") {
-                url.to_string()
+        "open" | "goto" | "navigate" => parse_open(cmd, &rest, &id, flags),
+        "back" | "forward" => {
+            let steps = parse_step_count(cmd, rest.first().copied(), "back|forward [n]")?;
+            Ok(json!({ "id": id, "action": cmd, "steps": steps }))
+        }
+        "reload" => {
+            if rest.contains(&"--hard") {
+                Ok(json!({ "id": id, "action": "reload", "bypassCache": true }))
             } else {
-                format!("https://{}", url)
-            };
-            let mut nav_cmd = json!({ "id": id, "action": "navigate", "url": url });
-            // If --headers flag is set, include headers (scoped to this origin)
-            if let Some(ref headers_json) = flags.headers {
-                if let Ok(headers) = serde_json::from_str::<serde_json::Value>(headers_json) {
-                    nav_cmd["headers"] = headers;
+                Ok(json!({ "id": id, "action": "reload" }))
+            }
+        }
+        "history" => {
+            const VALID: &[&str] = &["back", "forward"];
+            match rest.first().copied() {
+                None => Ok(json!({ "id": id, "action": "history" })),
+                Some(dir @ ("back" | "forward")) => {
+                    let steps = parse_step_count(&format!("history {}", dir), rest.get(1).copied(), "history <back|forward> [n]")?;
+                    let delta = if dir == "back" { -steps } else { steps };
+                    Ok(json!({ "id": id, "action": "history_go", "delta": delta }))
                 }
+                Some(sub) => Err(ParseError::UnknownSubcommand {
+                    subcommand: sub.to_string(),
+                    valid_options: VALID,
+                }),
             }
-            Ok(nav_cmd)
         }
-        "back" => Ok(json!({ "id": id, "action": "back" })),
-        "forward" => Ok(json!({ "id": id, "action": "forward" })),
-        "reload" => Ok(json!({ "id": id, "action": "reload" })),
 
         // === Core Actions ===
         "click" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "click".to_string(),
                 usage: "click <selector>",
             })?;
             Ok(json!({ "id": id, "action": "click", "selector": sel }))
         }
         "dblclick" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "dblclick".to_string(),
                 usage: "dblclick <selector>",
             })?;
             Ok(json!({ "id": id, "action": "dblclick", "selector": sel }))
         }
         "fill" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "fill".to_string(),
                 usage: "fill <selector> <text>",
             })?;
-            Ok(json!({ "id": id, "action": "fill", "selector": sel, "value": rest[1..].join(" ") }))
+            let mut value = rest[1..].join(" ");
+            if value == "-" {
+                value = read_stdin("fill", "fill <selector> <text|->", &mut stdin)?;
+            }
+            Ok(json!({ "id": id, "action": "fill", "selector": sel, "value": value }))
         }
         "type" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "type".to_string(),
-                usage: "type <selector> <text>",
+                usage: "type <selector> <text> [--clear]",
             })?;
-            Ok(json!({ "id": id, "action": "type", "selector": sel, "text": rest[1..].join(" ") }))
+            let is_clear = |s: &&str| matches!(*s, "--clear" | "--clear-first");
+            let clear = rest[1..].iter().any(is_clear);
+            let mut text = rest[1..].iter().filter(|s| !is_clear(s)).copied().collect::<Vec<_>>().join(" ");
+            if text == "-" {
+                text = read_stdin("type", "type <selector> <text|-> [--clear]", &mut stdin)?;
+            }
+            let mut cmd = json!({ "id": id, "action": "type", "selector": sel, "text": text });
+            if clear {
+                cmd["clear"] = json!(true);
+            }
+            Ok(cmd)
         }
         "hover" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "hover".to_string(),
                 usage: "hover <selector>",
             })?;
             Ok(json!({ "id": id, "action": "hover", "selector": sel }))
         }
         "focus" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "focus".to_string(),
                 usage: "focus <selector>",
             })?;
             Ok(json!({ "id": id, "action": "focus", "selector": sel }))
         }
         "check" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "check".to_string(),
                 usage: "check <selector>",
             })?;
             Ok(json!({ "id": id, "action": "check", "selector": sel }))
         }
         "uncheck" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "uncheck".to_string(),
                 usage: "uncheck <selector>",
             })?;
             Ok(json!({ "id": id, "action": "uncheck", "selector": sel }))
         }
         "select" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "select".to_string(),
                 usage: "select <selector> <value>",
             })?;
@@ -161,42 +215,26 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
             })?;
             Ok(json!({ "id": id, "action": "select", "selector": sel, "value": val }))
         }
-        "drag" => {
-            let src = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "drag".to_string(),
-                usage: "drag <source> <target>",
-            })?;
-            let tgt = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "drag".to_string(),
-                usage: "drag <source> <target>",
-            })?;
-            Ok(json!({ "id": id, "action": "drag", "source": src, "target": tgt }))
-        }
-        "upload" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "upload".to_string(),
-                usage: "upload <selector> <files...>",
-            })?;
-            Ok(json!({ "id": id, "action": "upload", "selector": sel, "files": &rest[1..] }))
-        }
+        "drag" => parse_drag(&rest, &id),
+        "upload" => parse_upload(&rest, &id),
 
         // === Keyboard ===
         "press" | "key" => {
-            let key = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let key = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "press".to_string(),
                 usage: "press <key>",
             })?;
             Ok(json!({ "id": id, "action": "press", "key": key }))
         }
         "keydown" => {
-            let key = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let key = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "keydown".to_string(),
                 usage: "keydown <key>",
             })?;
             Ok(json!({ "id": id, "action": "keydown", "key": key }))
         }
         "keyup" => {
-            let key = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let key = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "keyup".to_string(),
                 usage: "keyup <key>",
             })?;
@@ -205,12 +243,12 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Scroll ===
         "scroll" => {
-            let dir = rest.get(0).unwrap_or(&"down");
+            let dir = rest.first().unwrap_or(&"down");
             let amount = rest.get(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(300);
             Ok(json!({ "id": id, "action": "scroll", "direction": dir, "amount": amount }))
         }
         "scrollintoview" | "scrollinto" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "scrollintoview".to_string(),
                 usage: "scrollintoview <selector>",
             })?;
@@ -219,13 +257,58 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Wait ===
         "wait" => {
+            // --timeout <ms> caps any of the conditions below. It has no short
+            // form: -t is --text.
+            let mut timeout: Option<u64> = None;
+            if let Some(idx) = rest.iter().position(|&s| s == "--timeout") {
+                let ms = rest.get(idx + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: "wait --timeout".to_string(),
+                    usage: "wait <selector|--url|--load|--fn|--text> --timeout <ms>",
+                })?;
+                timeout = Some(ms.parse::<u64>().ok().filter(|ms| *ms > 0).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("wait: invalid --timeout '{}' (expected milliseconds, more than 0)", ms),
+                    usage: "wait <selector|--url|--load|--fn|--text> --timeout <ms>",
+                })?);
+            }
+            let rest: Vec<&str> = match rest.iter().position(|&s| s == "--timeout") {
+                Some(idx) => rest[..idx].iter().chain(rest.iter().skip(idx + 2)).copied().collect(),
+                None => rest.to_vec(),
+            };
+            let with_timeout = |mut cmd: Value| {
+                if let Some(ms) = timeout {
+                    cmd["timeout"] = json!(ms);
+                }
+                Ok(cmd)
+            };
+
+            // Several selectors: wait --any "#ok" "#error"
+            let any = rest.contains(&"--any");
+            let all = rest.contains(&"--all");
+            if any || all {
+                if any && all {
+                    return Err(ParseError::MissingArguments {
+                        context: "wait: --any and --all can't be combined".to_string(),
+                        usage: "wait --any|--all <selector>... [--timeout <ms>]",
+                    });
+                }
+                let selectors: Vec<&str> = rest.iter().copied().filter(|s| *s != "--any" && *s != "--all").collect();
+                if selectors.is_empty() {
+                    return Err(ParseError::MissingArguments {
+                        context: format!("wait {}: no selectors given", if any { "--any" } else { "--all" }),
+                        usage: "wait --any|--all <selector>... [--timeout <ms>]",
+                    });
+                }
+                let mode = if any { "any" } else { "all" };
+                return with_timeout(json!({ "id": id, "action": "wait_multi", "selectors": selectors, "mode": mode }));
+            }
+
             // Check for --url flag: wait --url "**/dashboard"
             if let Some(idx) = rest.iter().position(|&s| s == "--url" || s == "-u") {
                 let url = rest.get(idx + 1).ok_or_else(|| ParseError::MissingArguments {
                     context: "wait --url".to_string(),
                     usage: "wait --url <pattern>",
                 })?;
-                return Ok(json!({ "id": id, "action": "waitforurl", "url": url }));
+                return with_timeout(json!({ "id": id, "action": "waitforurl", "url": url }));
             }
             
             // Check for --load flag: wait --load networkidle
@@ -234,7 +317,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     context: "wait --load".to_string(),
                     usage: "wait --load <state>",
                 })?;
-                return Ok(json!({ "id": id, "action": "waitforloadstate", "state": state }));
+                return with_timeout(json!({ "id": id, "action": "waitforloadstate", "state": state }));
             }
             
             // Check for --fn flag: wait --fn "window.ready === true"
@@ -243,7 +326,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     context: "wait --fn".to_string(),
                     usage: "wait --fn <expression>",
                 })?;
-                return Ok(json!({ "id": id, "action": "waitforfunction", "expression": expr }));
+                return with_timeout(json!({ "id": id, "action": "waitforfunction", "expression": expr }));
             }
             
             // Check for --text flag: wait --text "Welcome"
@@ -253,15 +336,21 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     usage: "wait --text <text>",
                 })?;
                 // Use getByText locator to wait for text to appear
-                return Ok(json!({ "id": id, "action": "wait", "selector": format!("text={}", text) }));
+                return with_timeout(json!({ "id": id, "action": "wait", "selector": format!("text={}", text) }));
             }
             
             // Default: selector or timeout
-            if let Some(arg) = rest.get(0) {
-                if arg.parse::<u64>().is_ok() {
-                    Ok(json!({ "id": id, "action": "wait", "timeout": arg.parse::<u64>().unwrap() }))
+            if let Some(arg) = rest.first() {
+                if let Ok(ms) = arg.parse::<u64>() {
+                    if timeout.is_some() {
+                        return Err(ParseError::MissingArguments {
+                            context: "wait: --timeout needs a selector or condition to wait for".to_string(),
+                            usage: "wait <selector|--url|--load|--fn|--text> --timeout <ms>",
+                        });
+                    }
+                    Ok(json!({ "id": id, "action": "wait", "timeout": ms }))
                 } else {
-                    Ok(json!({ "id": id, "action": "wait", "selector": arg }))
+                    with_timeout(json!({ "id": id, "action": "wait", "selector": arg }))
                 }
             } else {
                 Err(ParseError::MissingArguments {
@@ -273,20 +362,51 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Screenshot/PDF ===
         "screenshot" => {
-            let mut cmd = json!({ "id": id, "action": "screenshot", "fullPage": flags.full });
-            if let Some(path) = rest.get(0) {
+            // -f before the command word is the global --full; after it, it's ours
+            let full = flags.full || rest.iter().any(|a| *a == "--full" || *a == "-f");
+            let mut cmd = json!({ "id": id, "action": "screenshot", "fullPage": full });
+            if let Some(path) = rest.iter().find(|a| !a.starts_with('-')) {
                 cmd["path"] = json!(path);
             }
             Ok(cmd)
         }
         "pdf" => {
-            let path = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let path = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "pdf".to_string(),
                 usage: "pdf <path>",
             })?;
             Ok(json!({ "id": id, "action": "pdf", "path": path }))
         }
 
+        // === Save page (archival copy) ===
+        "save" => {
+            const USAGE: &str = "save <path.mhtml|path.html>";
+            let path = rest.first().ok_or_else(|| ParseError::MissingArguments {
+                context: "save".to_string(),
+                usage: USAGE,
+            })?;
+            let ext = Path::new(path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_ascii_lowercase());
+            // The daemon may run in another directory; resolve against ours
+            let resolved = std::env::current_dir()
+                .map(|cwd| cwd.join(path))
+                .unwrap_or_else(|_| Path::new(path).to_path_buf())
+                .display()
+                .to_string();
+            match ext.as_deref() {
+                Some("mhtml") | Some("mht") => Ok(json!({ "id": id, "action": "savemhtml", "path": resolved })),
+                Some("html") | Some("htm") => {
+                    Ok(json!({ "id": id, "action": "savehtml", "path": resolved, "inline": true }))
+                }
+                _ => Err(ParseError::MissingArguments {
+                    context: format!("save: unsupported file type '{}' (supported: .mhtml, .html)", path),
+                    usage: USAGE,
+                }),
+            }
+        }
+
         // === Snapshot ===
         "snapshot" => {
             let mut cmd = json!({ "id": id, "action": "snapshot" });
@@ -300,6 +420,9 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     "-c" | "--compact" => {
                         obj.insert("compact".to_string(), json!(true));
                     }
+                    "--json-tree" => {
+                        obj.insert("format".to_string(), json!("json"));
+                    }
                     "-d" | "--depth" => {
                         if let Some(d) = rest.get(i + 1) {
                             if let Ok(n) = d.parse::<i32>() {
@@ -322,10 +445,28 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
         }
 
         // === Eval ===
-        "eval" => Ok(json!({ "id": id, "action": "evaluate", "script": rest.join(" ") })),
+        "eval" => {
+            let force_await = rest.contains(&"--await");
+            let no_await = rest.contains(&"--no-await");
+            let mut script = rest
+                .iter()
+                .filter(|t| **t != "--await" && **t != "--no-await")
+                .copied()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if script == "-" {
+                script = read_stdin("eval", "eval <js|->", &mut stdin)?;
+            }
+            let mut cmd = json!({ "id": id, "action": "evaluate", "script": script });
+            if force_await || (!no_await && looks_async(&script)) {
+                cmd["await"] = json!(true);
+            }
+            Ok(cmd)
+        }
 
         // === Close ===
         "close" | "quit" | "exit" | "stop" => Ok(json!({ "id": id, "action": "close" })),
+        "ping" => Ok(json!({ "id": id, "action": "ping" })),
 
         // === Start (explicit browser configuration) ===
         "start" => {
@@ -338,6 +479,9 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
             if let Some(ref profile) = flags.profile {
                 cmd["profile"] = json!(profile);
             }
+            if let Some(ref dir) = flags.user_data_dir {
+                cmd["userDataDir"] = json!(dir);
+            }
             if let Some(ref proxy) = flags.proxy {
                 cmd["proxy"] = json!(proxy);
             }
@@ -361,16 +505,28 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Connect (CDP) ===
         "connect" => {
-            let endpoint = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            const USAGE: &str = "connect <port|ws://url> | connect --discover";
+            if rest.contains(&"--discover") {
+                if let Some(endpoint) = rest.iter().find(|a| **a != "--discover") {
+                    return Err(ParseError::MissingArguments {
+                        context: format!("connect: --discover can't be combined with an endpoint ('{}')", endpoint),
+                        usage: USAGE,
+                    });
+                }
+                // The daemon probes the usual debugging ports and reports the
+                // endpoint it picked
+                return Ok(json!({ "id": id, "action": "launch", "cdpDiscover": true }));
+            }
+            let endpoint = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "connect".to_string(),
-                usage: "connect <port|ws://url>",
+                usage: USAGE,
             })?;
             let cdp_value: serde_json::Value = if endpoint.starts_with("ws://") || endpoint.starts_with("wss://") {
                 json!(endpoint)
             } else {
                 let port: u16 = endpoint.parse().map_err(|_| ParseError::MissingArguments {
                     context: format!("connect: invalid endpoint '{}'. Use port number or ws:// URL", endpoint),
-                    usage: "connect <port|ws://url>",
+                    usage: USAGE,
                 })?;
                 json!(port)
             };
@@ -386,6 +542,9 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
         // === Find (locators) ===
         "find" => parse_find(&rest, &id),
 
+        // === Text search ===
+        "find-text" => parse_find_text(&rest, &id),
+
         // === Mouse ===
         "mouse" => parse_mouse(&rest, &id),
 
@@ -394,13 +553,17 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Network ===
         "network" => parse_network(&rest, &id),
+        "fetch" => parse_fetch(&rest, &id),
 
         // === Storage ===
         "storage" => parse_storage(&rest, &id),
 
+        // === Clipboard ===
+        "clipboard" => parse_clipboard(&rest, &id, &mut stdin),
+
         // === Cookies ===
         "cookies" => {
-            let op = rest.get(0).unwrap_or(&"get");
+            let op = rest.first().unwrap_or(&"get");
             match *op {
                 "set" => {
                     let name = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
@@ -414,16 +577,37 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     Ok(json!({ "id": id, "action": "cookies_set", "cookies": [{ "name": name, "value": value }] }))
                 }
                 "clear" => Ok(json!({ "id": id, "action": "cookies_clear" })),
-                _ => Ok(json!({ "id": id, "action": "cookies_get" })),
+                _ => {
+                    let mut cmd = json!({ "id": id, "action": "cookies_get" });
+                    // Only the cookies that would be sent to this URL
+                    if let Some(idx) = rest.iter().position(|&s| s == "--url") {
+                        let url = rest.get(idx + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "cookies get --url".to_string(),
+                            usage: "cookies get [--url <url>]",
+                        })?;
+                        cmd["url"] = json!(url);
+                    }
+                    Ok(cmd)
+                }
             }
         }
 
         // === Tabs ===
         "tab" => {
-            match rest.get(0).map(|s| *s) {
+            match rest.first().copied() {
                 Some("new") => Ok(json!({ "id": id, "action": "tab_new", "url": rest.get(1) })),
                 Some("list") => Ok(json!({ "id": id, "action": "tab_list" })),
                 Some("close") => {
+                    if rest.contains(&"--all") {
+                        return Ok(json!({ "id": id, "action": "tab_close", "all": true }));
+                    }
+                    if let Some(idx) = rest.iter().position(|&s| s == "--title") {
+                        let title = rest.get(idx + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "tab close --title".to_string(),
+                            usage: "tab close --title <substring>",
+                        })?;
+                        return Ok(json!({ "id": id, "action": "tab_close", "title": title }));
+                    }
                     Ok(json!({ "id": id, "action": "tab_close", "index": rest.get(1).and_then(|s| s.parse::<i32>().ok()) }))
                 }
                 Some(n) if n.parse::<i32>().is_ok() => {
@@ -436,7 +620,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
         // === Window ===
         "window" => {
             const VALID: &[&str] = &["new"];
-            match rest.get(0).map(|s| *s) {
+            match rest.first().copied() {
                 Some("new") => Ok(json!({ "id": id, "action": "window_new" })),
                 Some(sub) => Err(ParseError::UnknownSubcommand {
                     subcommand: sub.to_string(),
@@ -451,10 +635,10 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Frame ===
         "frame" => {
-            if rest.get(0).map(|s| *s) == Some("main") {
+            if rest.first().copied() == Some("main") {
                 Ok(json!({ "id": id, "action": "frame_main" }))
             } else {
-                let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+                let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                     context: "frame".to_string(),
                     usage: "frame <selector|main>",
                 })?;
@@ -465,7 +649,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
         // === Dialog ===
         "dialog" => {
             const VALID: &[&str] = &["accept", "dismiss"];
-            match rest.get(0).map(|s| *s) {
+            match rest.first().copied() {
                 Some("accept") => {
                     Ok(json!({ "id": id, "action": "dialog", "response": "accept", "promptText": rest.get(1) }))
                 }
@@ -483,17 +667,25 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Debug ===
         "trace" => {
-            const VALID: &[&str] = &["start", "stop"];
-            match rest.get(0).map(|s| *s) {
-                Some("start") => Ok(json!({ "id": id, "action": "trace_start", "path": rest.get(1) })),
+            const VALID: &[&str] = &["start", "stop", "view"];
+            match rest.first().copied() {
+                Some("start") => Ok(parse_trace_start(&rest[1..], &id)),
                 Some("stop") => Ok(json!({ "id": id, "action": "trace_stop", "path": rest.get(1) })),
+                // Handled locally in main.rs (see trace::run_trace_view); never sent to the daemon
+                Some("view") => {
+                    let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                        context: "trace view".to_string(),
+                        usage: "trace view <path>",
+                    })?;
+                    Ok(json!({ "id": id, "action": "trace_view", "path": path }))
+                }
                 Some(sub) => Err(ParseError::UnknownSubcommand {
                     subcommand: sub.to_string(),
                     valid_options: VALID,
                 }),
                 None => Err(ParseError::MissingArguments {
                     context: "trace".to_string(),
-                    usage: "trace <start|stop> [path]",
+                    usage: "trace <start|stop|view> [path]",
                 }),
             }
         }
@@ -501,97 +693,73 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
         // === Recording (Playwright native video recording) ===
         "record" => {
             const VALID: &[&str] = &["start", "stop", "restart"];
-            match rest.get(0).map(|s| *s) {
-                Some("start") => {
-                    let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                        context: "record start".to_string(),
-                        usage: "record start <output.webm> [url]",
-                    })?;
-                    // Optional URL parameter
-                    let url = rest.get(2);
-                    let mut cmd = json!({ "id": id, "action": "recording_start", "path": path });
-                    if let Some(u) = url {
-                        // Add https:// prefix if needed
-                        let url_str = if u.starts_with("http") {
-                            u.to_string()
-                        } else {
-                            format!("https://{}", u)
-                        };
-                        cmd["url"] = json!(url_str);
+            match rest.first().copied() {
+                Some("start") => parse_record_start(&rest[1..], &id, "recording_start", "record start"),
+                Some("stop") => {
+                    const USAGE: &str = "record stop [--gif <out.gif> | --frames <dir>]";
+                    let gif_idx = rest.iter().position(|&s| s == "--gif");
+                    let frames_idx = rest.iter().position(|&s| s == "--frames");
+                    if gif_idx.is_some() && frames_idx.is_some() {
+                        return Err(ParseError::MissingArguments {
+                            context: "record stop: --gif and --frames are mutually exclusive".to_string(),
+                            usage: USAGE,
+                        });
                     }
-                    Ok(cmd)
-                }
-                Some("stop") => Ok(json!({ "id": id, "action": "recording_stop" })),
-                Some("restart") => {
-                    let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                        context: "record restart".to_string(),
-                        usage: "record restart <output.webm> [url]",
-                    })?;
-                    // Optional URL parameter
-                    let url = rest.get(2);
-                    let mut cmd = json!({ "id": id, "action": "recording_restart", "path": path });
-                    if let Some(u) = url {
-                        // Add https:// prefix if needed
-                        let url_str = if u.starts_with("http") {
-                            u.to_string()
-                        } else {
-                            format!("https://{}", u)
-                        };
-                        cmd["url"] = json!(url_str);
+                    let mut cmd = json!({ "id": id, "action": "recording_stop" });
+                    // Conversion happens in the CLI once the daemon returns the webm path
+                    if let Some(i) = gif_idx {
+                        let path = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "record stop --gif".to_string(),
+                            usage: USAGE,
+                        })?;
+                        cmd["gif"] = json!(path);
+                    }
+                    if let Some(i) = frames_idx {
+                        let dir = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "record stop --frames".to_string(),
+                            usage: USAGE,
+                        })?;
+                        cmd["framesDir"] = json!(dir);
                     }
                     Ok(cmd)
                 }
+                Some("restart") => parse_record_start(&rest[1..], &id, "recording_restart", "record restart"),
                 Some(sub) => Err(ParseError::UnknownSubcommand {
                     subcommand: sub.to_string(),
                     valid_options: VALID,
                 }),
                 None => Err(ParseError::MissingArguments {
                     context: "record".to_string(),
-                    usage: "record <start|stop|restart> [path] [url]",
+                    usage: "record <start|stop|restart> [path] [url] [--size <WxH>] [--fps <n>]",
                 }),
             }
         }
-        "console" => {
-            let clear = rest.iter().any(|&s| s == "--clear");
-            Ok(json!({ "id": id, "action": "console", "clear": clear }))
-        }
+        "console" => parse_console(&rest, &id),
         "errors" => {
-            let clear = rest.iter().any(|&s| s == "--clear");
-            Ok(json!({ "id": id, "action": "errors", "clear": clear }))
-        }
-        "highlight" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "highlight".to_string(),
-                usage: "highlight <selector>",
-            })?;
-            Ok(json!({ "id": id, "action": "highlight", "selector": sel }))
+            let clear = rest.contains(&"--clear");
+            // --short is consumed by print_response to keep one line per error
+            let short = rest.contains(&"--short");
+            let mut cmd = json!({ "id": id, "action": "errors", "clear": clear, "short": short });
+            // Ask the daemon for complete stack traces
+            if rest.contains(&"--stack") {
+                cmd["includeStack"] = json!(true);
+            }
+            Ok(cmd)
         }
+        "highlight" => parse_highlight(&rest, &id),
 
         // === State ===
         "state" => {
             const VALID: &[&str] = &["save", "load"];
-            match rest.get(0).map(|s| *s) {
-                Some("save") => {
-                    let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                        context: "state save".to_string(),
-                        usage: "state save <path>",
-                    })?;
-                    Ok(json!({ "id": id, "action": "state_save", "path": path }))
-                }
-                Some("load") => {
-                    let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                        context: "state load".to_string(),
-                        usage: "state load <path>",
-                    })?;
-                    Ok(json!({ "id": id, "action": "state_load", "path": path }))
-                }
+            match rest.first().copied() {
+                Some(op @ ("save" | "load")) => parse_state(op, &rest[1..], &id),
                 Some(sub) => Err(ParseError::UnknownSubcommand {
                     subcommand: sub.to_string(),
                     valid_options: VALID,
                 }),
                 None => Err(ParseError::MissingArguments {
                     context: "state".to_string(),
-                    usage: "state <save|load> <path>",
+                    usage: "state <save|load> <path> [--cookies-only|--storage-only] [--origin <url>]",
                 }),
             }
         }
@@ -602,10 +770,299 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
     }
 }
 
+/// Parse `record start|restart <path> [url] [--size <WxH>] [--fps <n>] [--no-preserve-state]`
+fn parse_record_start(rest: &[&str], id: &str, action: &str, context: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "record start|restart <output.webm> [url] [--size <WxH>] [--fps <n>] [--no-preserve-state]";
+
+    let mut positionals: Vec<&str> = Vec::new();
+    let mut size: Option<(u32, u32)> = None;
+    let mut fps: Option<u32> = None;
+    let mut preserve_state = true;
+
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--size" => {
+                let value = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("{} --size", context),
+                    usage: USAGE,
+                })?;
+                size = Some(parse_size(value).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("{}: invalid --size '{}' (expected WxH, e.g. 1280x720)", context, value),
+                    usage: USAGE,
+                })?);
+                i += 1;
+            }
+            "--fps" => {
+                let value = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("{} --fps", context),
+                    usage: USAGE,
+                })?;
+                fps = Some(value.parse::<u32>().ok().filter(|n| *n > 0).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("{}: invalid --fps '{}' (expected a positive integer)", context, value),
+                    usage: USAGE,
+                })?);
+                i += 1;
+            }
+            "--no-preserve-state" => preserve_state = false,
+            other => positionals.push(other),
+        }
+        i += 1;
+    }
+
+    let path = positionals.first().ok_or_else(|| ParseError::MissingArguments {
+        context: context.to_string(),
+        usage: USAGE,
+    })?;
+    let mut cmd = json!({ "id": id, "action": action, "path": path });
+    // Optional URL parameter
+    if let Some(u) = positionals.get(1) {
+        // Add https:// prefix if needed
+        let url_str = if u.starts_with("http") {
+            u.to_string()
+        } else {
+            format!("https://{}", u)
+        };
+        cmd["url"] = json!(url_str);
+    }
+    if let Some((width, height)) = size {
+        cmd["size"] = json!({ "width": width, "height": height });
+    }
+    if let Some(fps) = fps {
+        cmd["fps"] = json!(fps);
+    }
+    if !preserve_state {
+        cmd["preserveState"] = json!(false);
+    }
+    Ok(cmd)
+}
+
+/// Parse a `WxH` size such as `1280x720`
+fn parse_size(s: &str) -> Option<(u32, u32)> {
+    let (w, h) = s.split_once('x')?;
+    if w.is_empty() || h.is_empty() || !w.chars().all(|c| c.is_ascii_digit()) || !h.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((w.parse().ok()?, h.parse().ok()?))
+}
+
+/// Parse `upload <selector> <files...|dir> [--glob <pattern>]`, expanding
+/// directories and globs into the `files` array before anything is sent.
+fn parse_upload(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "upload <selector> <files...|dir> [--glob <pattern>]";
+    let err = |context: String| ParseError::MissingArguments { context, usage: USAGE };
+
+    let mut positionals: Vec<&str> = Vec::new();
+    let mut glob: Option<&str> = None;
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i] == "--glob" {
+            glob = Some(rest.get(i + 1).ok_or_else(|| err("upload --glob".to_string()))?);
+            i += 1;
+        } else {
+            positionals.push(rest[i]);
+        }
+        i += 1;
+    }
+
+    let sel = positionals.first().ok_or_else(|| err("upload".to_string()))?;
+    let paths = &positionals[1..];
+    let mut files: Vec<String> = Vec::new();
+
+    if let [single] = paths {
+        if Path::new(single).is_dir() {
+            files = list_dir_files(Path::new(single))
+                .map_err(|e| err(format!("upload: cannot read directory {}: {}", single, e)))?;
+            if files.is_empty() {
+                return Err(err(format!("upload: directory {} contains no files", single)));
+            }
+        }
+    }
+    if files.is_empty() {
+        for path in paths {
+            if is_ref(path) {
+                return Err(err(format!("upload: {} is an element ref; the selector comes before the files", path)));
+            }
+            if !Path::new(path).is_file() {
+                return Err(err(format!("upload: file not found: {}", path)));
+            }
+            files.push(path.to_string());
+        }
+    }
+    if let Some(pattern) = glob {
+        let matched = expand_glob(pattern);
+        if matched.is_empty() {
+            return Err(err(format!("upload: no files match {}", pattern)));
+        }
+        files.extend(matched);
+    }
+    if files.is_empty() {
+        return Err(err("upload".to_string()));
+    }
+
+    Ok(json!({ "id": id, "action": "upload", "selector": sel, "files": files }))
+}
+
+/// Immediate (non-recursive) files of a directory, sorted
+fn list_dir_files(dir: &Path) -> std::io::Result<Vec<String>> {
+    let mut files: Vec<String> = std::fs::read_dir(dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Expand a glob whose wildcards (`*`, `?`) are in the file name part,
+/// e.g. `./fixtures/*.png`. Returns matching files, sorted.
+fn expand_glob(pattern: &str) -> Vec<String> {
+    let path = Path::new(pattern);
+    let name_pattern = match path.file_name().and_then(|n| n.to_str()) {
+        Some(n) => n,
+        None => return Vec::new(),
+    };
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let mut files: Vec<String> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|e| e.path().is_file())
+            .filter(|e| wildcard_match(name_pattern, &e.file_name().to_string_lossy()))
+            .map(|e| dir.join(e.file_name()).to_string_lossy().to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+/// Match `*` (any run of characters) and `?` (one character)
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            backtrack = Some((pi, ni));
+            pi += 1;
+        } else if let Some((star, matched)) = backtrack {
+            pi = star + 1;
+            ni = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// A snapshot element reference such as `@e12`. Refs are handed to the
+/// daemon as-is wherever a selector is accepted.
+pub fn is_ref(s: &str) -> bool {
+    s.strip_prefix("@e").is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Heuristic for `eval`: scripts that mention `await` or Promises should have
+/// their result awaited so the settled value comes back instead of `{}`.
+fn looks_async(script: &str) -> bool {
+    script
+        .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '$')
+        .any(|word| word == "await" || word == "Promise")
+        || script.contains(".then(")
+}
+
+/// Parse `state save|load <path> [--cookies-only|--storage-only] [--origin <url>]...`
+fn parse_state(op: &str, rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    let usage = if op == "save" {
+        "state save <path> [--cookies-only|--storage-only] [--origin <url>]... [--no-cookies|--include-cookies]"
+    } else {
+        "state load <path> [--cookies-only|--storage-only] [--origin <url>]..."
+    };
+
+    let mut path: Option<&str> = None;
+    let mut cookies_only = false;
+    let mut storage_only = false;
+    let mut origins: Vec<&str> = Vec::new();
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--cookies-only" => cookies_only = true,
+            // --no-cookies is --storage-only; --include-cookies undoes either
+            "--storage-only" | "--no-cookies" => storage_only = true,
+            "--include-cookies" => storage_only = false,
+            "--origin" => {
+                let origin = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("state {} --origin", op),
+                    usage,
+                })?;
+                origins.push(origin);
+                i += 1;
+            }
+            other if path.is_none() => path = Some(other),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let path = path.ok_or_else(|| ParseError::MissingArguments {
+        context: format!("state {}", op),
+        usage,
+    })?;
+    if cookies_only && storage_only {
+        return Err(ParseError::MissingArguments {
+            context: format!("state {}: --cookies-only and --storage-only are mutually exclusive", op),
+            usage,
+        });
+    }
+    let mut cmd = json!({ "id": id, "action": format!("state_{}", op), "path": path });
+    if cookies_only {
+        cmd["cookiesOnly"] = json!(true);
+    }
+    if storage_only {
+        cmd["storageOnly"] = json!(true);
+    }
+    if !origins.is_empty() {
+        cmd["origins"] = json!(origins);
+    }
+    Ok(cmd)
+}
+
+/// Check that a saved state file is JSON with Playwright's storage state shape
+/// (`cookies` and/or `origins` arrays at the top level).
+pub fn validate_state_file(path: &str) -> Result<(), String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read state file {}: {}", path, e))?;
+    let state: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("state file {} is not valid JSON ({})", path, e))?;
+    let obj = state
+        .as_object()
+        .ok_or_else(|| format!("state file {} must contain a JSON object", path))?;
+    if !obj.contains_key("cookies") && !obj.contains_key("origins") {
+        return Err(format!(
+            "state file {} has no \"cookies\" or \"origins\" key (was it created with 'state save'?)",
+            path
+        ));
+    }
+    for key in ["cookies", "origins"] {
+        if obj.get(key).is_some_and(|v| !v.is_array()) {
+            return Err(format!("state file {}: \"{}\" must be an array", path, key));
+        }
+    }
+    Ok(())
+}
+
 fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["text", "html", "value", "attr", "url", "title", "count", "box"];
+    const VALID: &[&str] = &["text", "html", "value", "attr", "url", "title", "count", "box", "article"];
     
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("text") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "get text".to_string(),
@@ -614,11 +1071,13 @@ fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             Ok(json!({ "id": id, "action": "gettext", "selector": sel }))
         }
         Some("html") => {
-            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+            let outer = rest[1..].contains(&"--outer");
+            let sel = rest[1..].iter().find(|s| **s != "--outer").ok_or_else(|| ParseError::MissingArguments {
                 context: "get html".to_string(),
-                usage: "get html <selector>",
+                usage: "get html <selector> [--outer]",
             })?;
-            Ok(json!({ "id": id, "action": "innerhtml", "selector": sel }))
+            let action = if outer { "outerhtml" } else { "innerhtml" };
+            Ok(json!({ "id": id, "action": action, "selector": sel }))
         }
         Some("value") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
@@ -640,6 +1099,32 @@ fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
         }
         Some("url") => Ok(json!({ "id": id, "action": "url" })),
         Some("title") => Ok(json!({ "id": id, "action": "title" })),
+        Some("article") => {
+            const USAGE: &str = "get article [--html] [--max-chars <n>]";
+            let mut cmd = json!({ "id": id, "action": "article" });
+            let mut i = 1;
+            while i < rest.len() {
+                match rest[i] {
+                    // Output options, applied when printing the response
+                    "--html" => cmd["html"] = json!(true),
+                    "--max-chars" => {
+                        let n = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "get article --max-chars".to_string(),
+                            usage: USAGE,
+                        })?;
+                        let n = n.parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(|| ParseError::MissingArguments {
+                            context: format!("get article: invalid --max-chars '{}' (expected a positive number)", n),
+                            usage: USAGE,
+                        })?;
+                        cmd["maxChars"] = json!(n);
+                        i += 1;
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            Ok(cmd)
+        }
         Some("count") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "get count".to_string(),
@@ -668,7 +1153,7 @@ fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
 fn parse_is(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     const VALID: &[&str] = &["visible", "enabled", "checked"];
     
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("visible") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "is visible".to_string(),
@@ -701,57 +1186,160 @@ fn parse_is(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     }
 }
 
+fn parse_find_text(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    let mut positionals: Vec<&str> = Vec::new();
+    let mut case_sensitive = false;
+    let mut regex = false;
+    let mut count_only = false;
+    for arg in rest {
+        match *arg {
+            "--case-sensitive" => case_sensitive = true,
+            "--regex" => regex = true,
+            "--count-only" => count_only = true,
+            other => positionals.push(other),
+        }
+    }
+    if positionals.is_empty() {
+        return Err(ParseError::MissingArguments {
+            context: "find-text".to_string(),
+            usage: "find-text <needle> [--case-sensitive] [--regex] [--count-only]",
+        });
+    }
+    let mut cmd = json!({
+        "id": id,
+        "action": "textsearch",
+        "text": positionals.join(" "),
+        "caseSensitive": case_sensitive,
+    });
+    if regex {
+        cmd["regex"] = json!(true);
+    }
+    // Output option, applied when printing the response
+    if count_only {
+        cmd["countOnly"] = json!(true);
+    }
+    Ok(cmd)
+}
+
 fn parse_find(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     const VALID: &[&str] = &["role", "text", "label", "placeholder", "alt", "title", "testid", "first", "last", "nth"];
     
-    let locator = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+    let locator = rest.first().ok_or_else(|| ParseError::MissingArguments {
         context: "find".to_string(),
         usage: "find <locator> <value> [action] [text]",
     })?;
-    
-    let name_idx = rest.iter().position(|&s| s == "--name");
-    let name = name_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
-    let exact = rest.iter().any(|&s| s == "--exact");
+
+    // Separate options from positional arguments so flags never leak into fill values
+    let mut positionals: Vec<&str> = Vec::new();
+    let mut name: Option<&str> = None;
+    let mut exact = false;
+    let mut nth: Option<i32> = None;
+    let mut has_text: Option<&str> = None;
+    let mut within: Option<&str> = None;
+    // `--locate`: report matches instead of clicking when no action is given
+    let mut default_action = "click";
+    let mut i = 1;
+    while i < rest.len() {
+        match rest[i] {
+            "--name" => {
+                name = rest.get(i + 1).copied();
+                i += 1;
+            }
+            "--exact" => exact = true,
+            "--locate" => default_action = "locate",
+            "--nth" => {
+                let idx_str = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("find {} --nth", locator),
+                    usage: "find <locator> <value> [action] --nth <index>",
+                })?;
+                nth = Some(idx_str.parse::<i32>().map_err(|_| ParseError::MissingArguments {
+                    context: format!("find {}: invalid --nth '{}' (expected an integer, negative counts from the end)", locator, idx_str),
+                    usage: "find <locator> <value> [action] --nth <index>",
+                })?);
+                i += 1;
+            }
+            "--has-text" => {
+                has_text = Some(rest.get(i + 1).copied().ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("find {} --has-text", locator),
+                    usage: "find <locator> <value> [action] --has-text <text>",
+                })?);
+                i += 1;
+            }
+            "--within" => {
+                within = Some(rest.get(i + 1).copied().ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("find {} --within", locator),
+                    usage: "find <locator> <value> [action] --within <selector>",
+                })?);
+                i += 1;
+            }
+            other => positionals.push(other),
+        }
+        i += 1;
+    }
+
+    // `first`/`last`/`nth` already pick one element by position; reject the
+    // filters of the role/text/label family rather than dropping them
+    if matches!(*locator, "first" | "last" | "nth") {
+        let filters = [("--nth", nth.is_some()), ("--has-text", has_text.is_some()), ("--within", within.is_some())];
+        if let Some((flag, _)) = filters.iter().find(|(_, given)| *given) {
+            return Err(ParseError::MissingArguments {
+                context: format!("find {}: {} only applies to role, text, label, placeholder, alt, title and testid", locator, flag),
+                usage: "find first|last <selector> [action] [text], find nth <index> <selector> [action] [text]",
+            });
+        }
+    }
 
     match *locator {
         "role" | "text" | "label" | "placeholder" | "alt" | "title" | "testid" | "first" | "last" => {
-            let value = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+            let value = positionals.first().ok_or_else(|| ParseError::MissingArguments {
                 context: format!("find {}", locator),
                 usage: match *locator {
-                    "role" => "find role <role> [action] [--name <name>] [--exact]",
-                    "text" => "find text <text> [action] [--exact]",
-                    "label" => "find label <label> [action] [text] [--exact]",
-                    "placeholder" => "find placeholder <text> [action] [text] [--exact]",
-                    "alt" => "find alt <text> [action] [--exact]",
-                    "title" => "find title <text> [action] [--exact]",
-                    "testid" => "find testid <id> [action] [text]",
+                    "role" => "find role <role> [action] [--name <name>] [--exact] [--nth <index>]",
+                    "text" => "find text <text> [action] [--exact] [--nth <index>]",
+                    "label" => "find label <label> [action] [text] [--exact] [--nth <index>]",
+                    "placeholder" => "find placeholder <text> [action] [text] [--exact] [--nth <index>]",
+                    "alt" => "find alt <text> [action] [--exact] [--nth <index>]",
+                    "title" => "find title <text> [action] [--exact] [--nth <index>]",
+                    "testid" => "find testid <id> [action] [text] [--nth <index>]",
                     "first" => "find first <selector> [action] [text]",
                     "last" => "find last <selector> [action] [text]",
                     _ => "find <locator> <value> [action] [text]",
                 },
             })?;
-            let subaction = rest.get(2).unwrap_or(&"click");
-            let fill_value = if rest.len() > 3 {
-                Some(rest[3..].join(" "))
+            let subaction = positionals.get(1).unwrap_or(&default_action);
+            let fill_value = if positionals.len() > 2 {
+                Some(positionals[2..].join(" "))
             } else {
                 None
             };
 
-            match *locator {
-                "role" => Ok(json!({ "id": id, "action": "getbyrole", "role": value, "subaction": subaction, "value": fill_value, "name": name, "exact": exact })),
-                "text" => Ok(json!({ "id": id, "action": "getbytext", "text": value, "subaction": subaction, "exact": exact })),
-                "label" => Ok(json!({ "id": id, "action": "getbylabel", "label": value, "subaction": subaction, "value": fill_value, "exact": exact })),
-                "placeholder" => Ok(json!({ "id": id, "action": "getbyplaceholder", "placeholder": value, "subaction": subaction, "value": fill_value, "exact": exact })),
-                "alt" => Ok(json!({ "id": id, "action": "getbyalttext", "text": value, "subaction": subaction, "exact": exact })),
-                "title" => Ok(json!({ "id": id, "action": "getbytitle", "text": value, "subaction": subaction, "exact": exact })),
-                "testid" => Ok(json!({ "id": id, "action": "getbytestid", "testId": value, "subaction": subaction, "value": fill_value })),
-                "first" => Ok(json!({ "id": id, "action": "nth", "selector": value, "index": 0, "subaction": subaction, "value": fill_value })),
-                "last" => Ok(json!({ "id": id, "action": "nth", "selector": value, "index": -1, "subaction": subaction, "value": fill_value })),
+            let mut cmd = match *locator {
+                "role" => json!({ "id": id, "action": "getbyrole", "role": value, "subaction": subaction, "value": fill_value, "name": name, "exact": exact }),
+                "text" => json!({ "id": id, "action": "getbytext", "text": value, "subaction": subaction, "exact": exact }),
+                "label" => json!({ "id": id, "action": "getbylabel", "label": value, "subaction": subaction, "value": fill_value, "exact": exact }),
+                "placeholder" => json!({ "id": id, "action": "getbyplaceholder", "placeholder": value, "subaction": subaction, "value": fill_value, "exact": exact }),
+                "alt" => json!({ "id": id, "action": "getbyalttext", "text": value, "subaction": subaction, "exact": exact }),
+                "title" => json!({ "id": id, "action": "getbytitle", "text": value, "subaction": subaction, "exact": exact }),
+                "testid" => json!({ "id": id, "action": "getbytestid", "testId": value, "subaction": subaction, "value": fill_value }),
+                "first" => return Ok(json!({ "id": id, "action": "nth", "selector": value, "index": 0, "subaction": subaction, "value": fill_value })),
+                "last" => return Ok(json!({ "id": id, "action": "nth", "selector": value, "index": -1, "subaction": subaction, "value": fill_value })),
                 _ => unreachable!(),
+            };
+            // Index into the matched set; negative counts from the end
+            if let Some(n) = nth {
+                cmd["nth"] = json!(n);
+            }
+            // Compound filters: keep matches containing text / scope inside a container
+            if let Some(text) = has_text {
+                cmd["hasText"] = json!(text);
             }
+            if let Some(sel) = within {
+                cmd["within"] = json!(sel);
+            }
+            Ok(cmd)
         }
         "nth" => {
-            let idx_str = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+            let idx_str = positionals.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "find nth".to_string(),
                 usage: "find nth <index> <selector> [action] [text]",
             })?;
@@ -759,13 +1347,13 @@ fn parse_find(rest: &[&str], id: &str) -> Result<Value, ParseError> {
                 context: "find nth".to_string(),
                 usage: "find nth <index> <selector> [action] [text]",
             })?;
-            let sel = rest.get(2).ok_or_else(|| ParseError::MissingArguments {
+            let sel = positionals.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "find nth".to_string(),
                 usage: "find nth <index> <selector> [action] [text]",
             })?;
-            let sub = rest.get(3).unwrap_or(&"click");
-            let fv = if rest.len() > 4 {
-                Some(rest[4..].join(" "))
+            let sub = positionals.get(2).unwrap_or(&default_action);
+            let fv = if positionals.len() > 3 {
+                Some(positionals[3..].join(" "))
             } else {
                 None
             };
@@ -778,10 +1366,282 @@ fn parse_find(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     }
 }
 
+fn parse_console(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "console [--clear] [--level <log|info|warning|error>]...";
+    const LEVELS: &[&str] = &["log", "info", "warning", "error"];
+    let mut levels: Vec<&str> = Vec::new();
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i] == "--level" {
+            let value = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                context: "console --level".to_string(),
+                usage: USAGE,
+            })?;
+            // Repeatable, and also accepts a comma-separated list
+            for level in value.split(',').map(str::trim).filter(|l| !l.is_empty()) {
+                let level = if level == "warn" { "warning" } else { level };
+                if !LEVELS.contains(&level) {
+                    return Err(ParseError::MissingArguments {
+                        context: format!("console: invalid --level '{}' (expected log, info, warning or error)", level),
+                        usage: USAGE,
+                    });
+                }
+                if !levels.contains(&level) {
+                    levels.push(level);
+                }
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+    let mut cmd = json!({ "id": id, "action": "console", "clear": rest.contains(&"--clear") });
+    if !levels.is_empty() {
+        cmd["levels"] = json!(levels);
+    }
+    Ok(cmd)
+}
+
+/// `trace start [path] [--screenshots] [--snapshots] [--sources]`. Options
+/// not given are left out so the daemon's defaults apply.
+fn parse_trace_start(rest: &[&str], id: &str) -> Value {
+    let mut cmd = json!({ "id": id, "action": "trace_start", "path": rest.iter().find(|a| !a.starts_with("--")) });
+    for (flag, key) in [("--screenshots", "screenshots"), ("--snapshots", "snapshots"), ("--sources", "sources")] {
+        if rest.contains(&flag) {
+            cmd[key] = json!(true);
+        }
+    }
+    cmd
+}
+
+fn parse_drag(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "drag <source> <target> [--steps <n>] [--hold <ms>]";
+    let mut positionals: Vec<&str> = Vec::new();
+    let mut steps: Option<u64> = None;
+    let mut hold: Option<u64> = None;
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--steps" => {
+                let n = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: "drag --steps".to_string(),
+                    usage: USAGE,
+                })?;
+                steps = Some(n.parse::<u64>().ok().filter(|n| *n > 0).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("drag: invalid --steps '{}' (expected a positive integer)", n),
+                    usage: USAGE,
+                })?);
+                i += 1;
+            }
+            "--hold" => {
+                let ms = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: "drag --hold".to_string(),
+                    usage: USAGE,
+                })?;
+                hold = Some(ms.parse::<u64>().map_err(|_| ParseError::MissingArguments {
+                    context: format!("drag: invalid --hold '{}' (expected milliseconds, 0 or more)", ms),
+                    usage: USAGE,
+                })?);
+                i += 1;
+            }
+            other => positionals.push(other),
+        }
+        i += 1;
+    }
+    let [src, tgt, ..] = positionals[..] else {
+        return Err(ParseError::MissingArguments { context: "drag".to_string(), usage: USAGE });
+    };
+    // Without either option the daemon does its usual single-motion drag
+    let mut cmd = json!({ "id": id, "action": "drag", "source": src, "target": tgt });
+    if let Some(n) = steps {
+        cmd["steps"] = json!(n);
+    }
+    if let Some(ms) = hold {
+        cmd["hold"] = json!(ms);
+    }
+    Ok(cmd)
+}
+
+fn parse_highlight(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "highlight <selector>... [--color <css-color>] [--duration <ms>] | highlight --clear";
+    let mut selectors: Vec<&str> = Vec::new();
+    let mut color: Option<&str> = None;
+    let mut duration: Option<u64> = None;
+    let mut clear = false;
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--clear" => clear = true,
+            "--color" => {
+                let value = rest.get(i + 1).copied().ok_or_else(|| ParseError::MissingArguments {
+                    context: "highlight --color".to_string(),
+                    usage: USAGE,
+                })?;
+                if !is_css_color(value) {
+                    return Err(ParseError::MissingArguments {
+                        context: format!("highlight: invalid --color '{}' (expected a hex code like #f80, a color name, or rgb()/hsl())", value),
+                        usage: USAGE,
+                    });
+                }
+                color = Some(value);
+                i += 1;
+            }
+            "--duration" => {
+                let ms = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: "highlight --duration".to_string(),
+                    usage: USAGE,
+                })?;
+                duration = Some(ms.parse::<u64>().map_err(|_| ParseError::MissingArguments {
+                    context: format!("highlight: invalid --duration '{}' (expected milliseconds, 0 or more)", ms),
+                    usage: USAGE,
+                })?);
+                i += 1;
+            }
+            other => selectors.push(other),
+        }
+        i += 1;
+    }
+    if clear {
+        if !selectors.is_empty() || color.is_some() || duration.is_some() {
+            return Err(ParseError::MissingArguments {
+                context: "highlight --clear takes no selectors or options".to_string(),
+                usage: USAGE,
+            });
+        }
+        return Ok(json!({ "id": id, "action": "highlight_clear" }));
+    }
+    let mut cmd = match selectors.as_slice() {
+        [] => {
+            return Err(ParseError::MissingArguments {
+                context: "highlight".to_string(),
+                usage: USAGE,
+            })
+        }
+        [selector] => json!({ "id": id, "action": "highlight", "selector": selector }),
+        _ => json!({ "id": id, "action": "highlight", "selectors": selectors }),
+    };
+    if let Some(color) = color {
+        cmd["color"] = json!(color);
+    }
+    if let Some(ms) = duration {
+        cmd["duration"] = json!(ms);
+    }
+    Ok(cmd)
+}
+
+/// CSS named colors, for catching `--color` typos before they reach the page
+const CSS_COLOR_NAMES: &[&str] = &[
+    "aliceblue", "antiquewhite", "aqua", "aquamarine", "azure", "beige", "bisque", "black", "blanchedalmond", "blue",
+    "blueviolet", "brown", "burlywood", "cadetblue", "chartreuse", "chocolate", "coral", "cornflowerblue", "cornsilk",
+    "crimson", "cyan", "darkblue", "darkcyan", "darkgoldenrod", "darkgray", "darkgreen", "darkgrey", "darkkhaki",
+    "darkmagenta", "darkolivegreen", "darkorange", "darkorchid", "darkred", "darksalmon", "darkseagreen",
+    "darkslateblue", "darkslategray", "darkslategrey", "darkturquoise", "darkviolet", "deeppink", "deepskyblue",
+    "dimgray", "dimgrey", "dodgerblue", "firebrick", "floralwhite", "forestgreen", "fuchsia", "gainsboro",
+    "ghostwhite", "gold", "goldenrod", "gray", "green", "greenyellow", "grey", "honeydew", "hotpink", "indianred",
+    "indigo", "ivory", "khaki", "lavender", "lavenderblush", "lawngreen", "lemonchiffon", "lightblue", "lightcoral",
+    "lightcyan", "lightgoldenrodyellow", "lightgray", "lightgreen", "lightgrey", "lightpink", "lightsalmon",
+    "lightseagreen", "lightskyblue", "lightslategray", "lightslategrey", "lightsteelblue", "lightyellow", "lime",
+    "limegreen", "linen", "magenta", "maroon", "mediumaquamarine", "mediumblue", "mediumorchid", "mediumpurple",
+    "mediumseagreen", "mediumslateblue", "mediumspringgreen", "mediumturquoise", "mediumvioletred", "midnightblue",
+    "mintcream", "mistyrose", "moccasin", "navajowhite", "navy", "oldlace", "olive", "olivedrab", "orange",
+    "orangered", "orchid", "palegoldenrod", "palegreen", "paleturquoise", "palevioletred", "papayawhip",
+    "peachpuff", "peru", "pink", "plum", "powderblue", "purple", "rebeccapurple", "red", "rosybrown", "royalblue",
+    "saddlebrown", "salmon", "sandybrown", "seagreen", "seashell", "sienna", "silver", "skyblue", "slateblue",
+    "slategray", "slategrey", "snow", "springgreen", "steelblue", "tan", "teal", "thistle", "tomato", "transparent",
+    "turquoise", "violet", "wheat", "white", "whitesmoke", "yellow", "yellowgreen",
+];
+
+/// `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, a named color, or an
+/// `rgb()`/`rgba()`/`hsl()`/`hsla()` function
+fn is_css_color(value: &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    let is_function = ["rgb(", "rgba(", "hsl(", "hsla("].iter().any(|f| lower.starts_with(f)) && lower.ends_with(')');
+    is_function || CSS_COLOR_NAMES.contains(&lower.as_str())
+}
+
+/// Optional positive step count for history moves; 1 when omitted
+fn parse_step_count(context: &str, arg: Option<&str>, usage: &'static str) -> Result<i64, ParseError> {
+    match arg {
+        Some(n) => n.parse::<i64>().ok().filter(|n| *n > 0).ok_or_else(|| ParseError::MissingArguments {
+            context: format!("{}: invalid step count '{}'", context, n),
+            usage,
+        }),
+        None => Ok(1),
+    }
+}
+
+fn parse_open(cmd: &str, rest: &[&str], id: &str, flags: &Flags) -> Result<Value, ParseError> {
+    const USAGE: &str = "open <url> [--wait-until <load|domcontentloaded|networkidle|commit>] [--referer <url>]";
+    const WAIT_STATES: &[&str] = &["load", "domcontentloaded", "networkidle", "commit"];
+    let mut url: Option<&str> = None;
+    let mut wait_until: Option<&str> = None;
+    let mut referer: Option<&str> = None;
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--wait-until" => {
+                let state = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("{} --wait-until", cmd),
+                    usage: USAGE,
+                })?;
+                if !WAIT_STATES.contains(state) {
+                    return Err(ParseError::MissingArguments {
+                        context: format!(
+                            "{}: invalid --wait-until '{}' (expected one of: {})",
+                            cmd,
+                            state,
+                            WAIT_STATES.join(", ")
+                        ),
+                        usage: USAGE,
+                    });
+                }
+                wait_until = Some(state);
+                i += 1;
+            }
+            "--referer" => {
+                referer = Some(rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("{} --referer", cmd),
+                    usage: USAGE,
+                })?);
+                i += 1;
+            }
+            arg if url.is_none() => url = Some(arg),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let url = url.ok_or_else(|| ParseError::MissingArguments {
+        context: cmd.to_string(),
+        usage: "open <url>",
+    })?;
+    let url = if url.starts_with("http") || url.starts_with("about:") || url.starts_with("data:") || url.starts_with("file:") {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    };
+    let mut nav_cmd = json!({ "id": id, "action": "navigate", "url": url });
+    // If --headers flag is set, include headers (scoped to this origin)
+    if let Some(ref headers_json) = flags.headers {
+        if let Ok(headers) = serde_json::from_str::<serde_json::Value>(headers_json) {
+            nav_cmd["headers"] = headers;
+        }
+    }
+    if let Some(state) = wait_until {
+        nav_cmd["waitUntil"] = json!(state);
+    }
+    if let Some(referer) = referer {
+        nav_cmd["referer"] = json!(referer);
+    }
+    Ok(nav_cmd)
+}
+
 fn parse_mouse(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["move", "down", "up", "wheel"];
+    const VALID: &[&str] = &["move", "down", "up", "wheel", "click"];
     
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("move") => {
             let x_str = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "mouse move".to_string(),
@@ -801,16 +1661,75 @@ fn parse_mouse(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             })?;
             Ok(json!({ "id": id, "action": "mousemove", "x": x, "y": y }))
         }
-        Some("down") => {
-            Ok(json!({ "id": id, "action": "mousedown", "button": rest.get(1).unwrap_or(&"left") }))
-        }
-        Some("up") => {
-            Ok(json!({ "id": id, "action": "mouseup", "button": rest.get(1).unwrap_or(&"left") }))
-        }
-        Some("wheel") => {
-            let dy = rest.get(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(100);
-            let dx = rest.get(2).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
-            Ok(json!({ "id": id, "action": "mousewheel", "deltaX": dx, "deltaY": dy }))
+        Some("click") => {
+            const USAGE: &str = "mouse click <x> <y> [--button <left|right|middle>]";
+            let mut positionals: Vec<&str> = Vec::new();
+            let mut button = "left";
+            let mut i = 1;
+            while i < rest.len() {
+                if rest[i] == "--button" {
+                    button = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                        context: "mouse click --button".to_string(),
+                        usage: USAGE,
+                    })?;
+                    if !matches!(button, "left" | "right" | "middle") {
+                        return Err(ParseError::MissingArguments {
+                            context: format!("mouse click: invalid button '{}'", button),
+                            usage: USAGE,
+                        });
+                    }
+                    i += 1;
+                } else {
+                    positionals.push(rest[i]);
+                }
+                i += 1;
+            }
+            let coords: Vec<i32> = positionals.iter().filter_map(|s| s.parse::<i32>().ok()).collect();
+            match coords[..] {
+                [x, y] if positionals.len() == 2 => {
+                    Ok(json!({ "id": id, "action": "mouseclick", "x": x, "y": y, "button": button }))
+                }
+                _ => Err(ParseError::MissingArguments {
+                    context: "mouse click".to_string(),
+                    usage: USAGE,
+                }),
+            }
+        }
+        Some("down") => {
+            Ok(json!({ "id": id, "action": "mousedown", "button": rest.get(1).unwrap_or(&"left") }))
+        }
+        Some("up") => {
+            Ok(json!({ "id": id, "action": "mouseup", "button": rest.get(1).unwrap_or(&"left") }))
+        }
+        Some("wheel") => {
+            const USAGE: &str = "mouse wheel <dy> [dx] [--steps <n>]";
+            let mut positionals: Vec<&str> = Vec::new();
+            let mut steps: u32 = 1;
+            let mut i = 1;
+            while i < rest.len() {
+                if rest[i] == "--steps" {
+                    let value = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                        context: "mouse wheel --steps".to_string(),
+                        usage: USAGE,
+                    })?;
+                    steps = match value.parse::<u32>() {
+                        Ok(n) if n > 0 => n,
+                        _ => {
+                            return Err(ParseError::MissingArguments {
+                                context: format!("mouse wheel: --steps must be a positive integer, got '{}'", value),
+                                usage: USAGE,
+                            })
+                        }
+                    };
+                    i += 1;
+                } else {
+                    positionals.push(rest[i]);
+                }
+                i += 1;
+            }
+            let dy = positionals.first().and_then(|s| s.parse::<i32>().ok()).unwrap_or(100);
+            let dx = positionals.get(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
+            Ok(json!({ "id": id, "action": "mousewheel", "deltaX": dx, "deltaY": dy, "steps": steps }))
         }
         Some(sub) => Err(ParseError::UnknownSubcommand {
             subcommand: sub.to_string(),
@@ -818,39 +1737,89 @@ fn parse_mouse(rest: &[&str], id: &str) -> Result<Value, ParseError> {
         }),
         None => Err(ParseError::MissingArguments {
             context: "mouse".to_string(),
-            usage: "mouse <move|down|up|wheel> [args...]",
+            usage: "mouse <move|click|down|up|wheel> [args...]",
         }),
     }
 }
 
 fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["viewport", "device", "geo", "geolocation", "offline", "headers", "credentials", "auth", "media"];
+    const VALID: &[&str] = &["viewport", "device", "geo", "geolocation", "offline", "headless", "headers", "credentials", "auth", "media"];
     
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("viewport") => {
-            let w_str = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "set viewport".to_string(),
-                usage: "set viewport <width> <height>",
-            })?;
-            let h_str = rest.get(2).ok_or_else(|| ParseError::MissingArguments {
-                context: "set viewport".to_string(),
-                usage: "set viewport <width> <height>",
-            })?;
+            const USAGE: &str = "set viewport <width> <height> [--dsf <n>] [--mobile]";
+            let mut positionals: Vec<&str> = Vec::new();
+            let mut dsf: Option<f64> = None;
+            let mut mobile = false;
+            let mut i = 1;
+            while i < rest.len() {
+                match rest[i] {
+                    "--dsf" => {
+                        let value = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "set viewport --dsf".to_string(),
+                            usage: USAGE,
+                        })?;
+                        dsf = Some(value.parse::<f64>().ok().filter(|n| *n > 0.0 && n.is_finite()).ok_or_else(|| {
+                            ParseError::MissingArguments {
+                                context: format!("set viewport: invalid --dsf '{}' (expected a positive number)", value),
+                                usage: USAGE,
+                            }
+                        })?);
+                        i += 1;
+                    }
+                    "--mobile" => mobile = true,
+                    other => positionals.push(other),
+                }
+                i += 1;
+            }
+            let (w_str, h_str) = match positionals[..] {
+                [w, h, ..] => (w, h),
+                _ => {
+                    return Err(ParseError::MissingArguments {
+                        context: "set viewport".to_string(),
+                        usage: USAGE,
+                    })
+                }
+            };
             let w = w_str.parse::<i32>().map_err(|_| ParseError::MissingArguments {
                 context: "set viewport".to_string(),
-                usage: "set viewport <width> <height>",
+                usage: USAGE,
             })?;
             let h = h_str.parse::<i32>().map_err(|_| ParseError::MissingArguments {
                 context: "set viewport".to_string(),
-                usage: "set viewport <width> <height>",
+                usage: USAGE,
             })?;
-            Ok(json!({ "id": id, "action": "viewport", "width": w, "height": h }))
+            let mut cmd = json!({ "id": id, "action": "viewport", "width": w, "height": h });
+            if let Some(dsf) = dsf {
+                cmd["deviceScaleFactor"] = json!(dsf);
+            }
+            if mobile {
+                cmd["isMobile"] = json!(true);
+            }
+            Ok(cmd)
         }
         Some("device") => {
-            let dev = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "set device".to_string(),
-                usage: "set device <name>",
-            })?;
+            const USAGE: &str = "set device <name> [--allow-unknown]";
+            let allow_unknown = rest[1..].contains(&"--allow-unknown");
+            let words: Vec<&str> = rest[1..].iter().copied().filter(|a| *a != "--allow-unknown").collect();
+            if words.is_empty() {
+                return Err(ParseError::MissingArguments {
+                    context: "set device".to_string(),
+                    usage: USAGE,
+                });
+            }
+            // Unquoted names like `set device iPhone 14` arrive as several words
+            let dev = words.join(" ");
+            if !allow_unknown && !DEVICES.contains(&dev.as_str()) {
+                let hint = match closest_device(&dev) {
+                    Some(known) => format!("did you mean '{}'?", known),
+                    None => "pass --allow-unknown to send it anyway".to_string(),
+                };
+                return Err(ParseError::MissingArguments {
+                    context: format!("set device: unknown device '{}' ({})", dev, hint),
+                    usage: USAGE,
+                });
+            }
             Ok(json!({ "id": id, "action": "device", "device": dev }))
         }
         Some("geo") | Some("geolocation") => {
@@ -873,8 +1842,35 @@ fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             Ok(json!({ "id": id, "action": "geolocation", "latitude": lat, "longitude": lng }))
         }
         Some("offline") => {
-            let off = rest.get(1).map(|s| *s != "off" && *s != "false").unwrap_or(true);
-            Ok(json!({ "id": id, "action": "offline", "offline": off }))
+            let offline = match rest.get(1).map(|s| s.to_ascii_lowercase()).as_deref() {
+                None | Some("on") | Some("true") => true,
+                Some("off") | Some("false") => false,
+                Some(other) => {
+                    return Err(ParseError::MissingArguments {
+                        context: format!("set offline: invalid value '{}'", other),
+                        usage: "set offline [on|off|true|false]",
+                    })
+                }
+            };
+            Ok(json!({ "id": id, "action": "offline", "offline": offline }))
+        }
+        // Show or hide the window of a running browser, where the backend
+        // can switch without a restart
+        Some("headless") => {
+            let headless = match rest.get(1).map(|s| s.to_ascii_lowercase()).as_deref() {
+                Some("on") | Some("true") => true,
+                Some("off") | Some("false") => false,
+                other => {
+                    return Err(ParseError::MissingArguments {
+                        context: match other {
+                            Some(value) => format!("set headless: invalid value '{}'", value),
+                            None => "set headless".to_string(),
+                        },
+                        usage: "set headless <on|off>",
+                    })
+                }
+            };
+            Ok(json!({ "id": id, "action": "headless", "headless": headless }))
         }
         Some("headers") => {
             let headers_json = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
@@ -901,15 +1897,29 @@ fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             Ok(json!({ "id": id, "action": "credentials", "username": user, "password": pass }))
         }
         Some("media") => {
-            let color = if rest.iter().any(|&s| s == "dark") {
+            let color = if rest.contains(&"dark") {
                 "dark"
-            } else if rest.iter().any(|&s| s == "light") {
+            } else if rest.contains(&"light") {
                 "light"
             } else {
                 "no-preference"
             };
-            let reduced = rest.iter().any(|&s| s == "reduced-motion");
-            Ok(json!({ "id": id, "action": "media", "colorScheme": color, "reducedMotion": reduced }))
+            let reduced = rest.contains(&"reduced-motion");
+            let mut cmd = json!({ "id": id, "action": "media", "colorScheme": color, "reducedMotion": reduced });
+            // forced-colors [active|none]
+            if let Some(idx) = rest.iter().position(|&s| s == "forced-colors") {
+                let value = match rest.get(idx + 1).copied() {
+                    Some("none") => "none",
+                    _ => "active",
+                };
+                cmd["forcedColors"] = json!(value);
+            }
+            if rest.contains(&"print") {
+                cmd["media"] = json!("print");
+            } else if rest.contains(&"screen") {
+                cmd["media"] = json!("screen");
+            }
+            Ok(cmd)
         }
         Some(sub) => Err(ParseError::UnknownSubcommand {
             subcommand: sub.to_string(),
@@ -925,22 +1935,13 @@ fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
 fn parse_network(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     const VALID: &[&str] = &["route", "unroute", "requests"];
     
-    match rest.get(0).map(|s| *s) {
-        Some("route") => {
-            let url = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "network route".to_string(),
-                usage: "network route <url> [--abort|--body <json>]",
-            })?;
-            let abort = rest.iter().any(|&s| s == "--abort");
-            let body_idx = rest.iter().position(|&s| s == "--body");
-            let body = body_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
-            Ok(json!({ "id": id, "action": "route", "url": url, "abort": abort, "body": body }))
-        }
+    match rest.first().copied() {
+        Some("route") => parse_route(&rest[1..], id),
         Some("unroute") => Ok(json!({ "id": id, "action": "unroute", "url": rest.get(1) })),
         Some("requests") => {
-            let clear = rest.iter().any(|&s| s == "--clear");
+            let clear = rest.contains(&"--clear");
             let filter_idx = rest.iter().position(|&s| s == "--filter");
-            let filter = filter_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
+            let filter = filter_idx.and_then(|i| rest.get(i + 1).copied());
             Ok(json!({ "id": id, "action": "requests", "clear": clear, "filter": filter }))
         }
         Some(sub) => Err(ParseError::UnknownSubcommand {
@@ -954,12 +1955,141 @@ fn parse_network(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     }
 }
 
+/// `network route <url>`: abort matching requests, answer them with a body,
+/// or let them through with `--continue`, optionally rewritten
+fn parse_route(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str =
+        "network route <url> [--abort | --body <json> | --continue [--set-url <url>] [--set-method <METHOD>] [--set-header <k:v>]...]";
+    let err = |context: String| ParseError::MissingArguments { context, usage: USAGE };
+
+    let mut url: Option<&str> = None;
+    let mut abort = false;
+    let mut body: Option<&str> = None;
+    let mut continue_request = false;
+    let mut overrides = serde_json::Map::new();
+    let mut headers = serde_json::Map::new();
+    let mut i = 0;
+    while i < rest.len() {
+        let value = || rest.get(i + 1).copied().ok_or_else(|| err(format!("network route {}", rest[i])));
+        match rest[i] {
+            "--abort" => abort = true,
+            "--continue" => continue_request = true,
+            "--body" => {
+                body = Some(value()?);
+                i += 1;
+            }
+            "--set-url" => {
+                overrides.insert("url".to_string(), json!(value()?));
+                i += 1;
+            }
+            "--set-method" => {
+                overrides.insert("method".to_string(), json!(value()?.to_ascii_uppercase()));
+                i += 1;
+            }
+            "--set-header" => {
+                let header = value()?;
+                let (name, val) = header
+                    .split_once(':')
+                    .filter(|(name, _)| !name.trim().is_empty())
+                    .ok_or_else(|| err(format!("network route: invalid --set-header '{}' (expected name:value)", header)))?;
+                headers.insert(name.trim().to_string(), json!(val.trim()));
+                i += 1;
+            }
+            other if url.is_none() => url = Some(other),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let url = url.ok_or_else(|| err("network route".to_string()))?;
+    if [abort, body.is_some(), continue_request].iter().filter(|set| **set).count() > 1 {
+        return Err(err("network route: --abort, --body and --continue can't be combined".to_string()));
+    }
+    if !headers.is_empty() {
+        overrides.insert("headers".to_string(), Value::Object(headers));
+    }
+    if !overrides.is_empty() && !continue_request {
+        return Err(err("network route: --set-url, --set-method and --set-header need --continue".to_string()));
+    }
+
+    let mut cmd = json!({ "id": id, "action": "route", "url": url, "abort": abort, "body": body });
+    if continue_request {
+        cmd["continue"] = Value::Object(overrides);
+    }
+    Ok(cmd)
+}
+
+/// `fetch <url>`: an HTTP request made by the daemon from the browser
+/// context, so it carries the session's cookies. `--data @file` reads the
+/// body from a file; a body without `--method` is a POST.
+fn parse_fetch(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str =
+        "fetch <url> [--method <m>] [--data <body|@file>] [--header \"K: V\"]... [--include] [--raw] [--no-fail] [-o <path>]";
+    let err = |context: String| ParseError::MissingArguments { context, usage: USAGE };
+
+    let mut url: Option<&str> = None;
+    let mut method: Option<String> = None;
+    let mut body: Option<String> = None;
+    let mut headers = serde_json::Map::new();
+    let (mut include, mut raw, mut no_fail) = (false, false, false);
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--method" | "-X" => {
+                let m = rest.get(i + 1).ok_or_else(|| err("fetch --method".to_string()))?;
+                method = Some(m.to_ascii_uppercase());
+                i += 1;
+            }
+            "--data" | "-d" => {
+                let data = rest.get(i + 1).ok_or_else(|| err("fetch --data".to_string()))?;
+                body = Some(match data.strip_prefix('@') {
+                    Some(path) => std::fs::read_to_string(path)
+                        .map_err(|e| err(format!("fetch: cannot read {}: {}", path, e)))?,
+                    None => data.to_string(),
+                });
+                i += 1;
+            }
+            "--header" | "-H" => {
+                let header = rest.get(i + 1).ok_or_else(|| err("fetch --header".to_string()))?;
+                let (name, value) = header
+                    .split_once(':')
+                    .filter(|(name, _)| !name.trim().is_empty())
+                    .ok_or_else(|| err(format!("fetch: invalid header '{}' (expected \"Name: value\")", header)))?;
+                headers.insert(name.trim().to_string(), json!(value.trim()));
+                i += 1;
+            }
+            "--include" | "-i" => include = true,
+            "--raw" => raw = true,
+            "--no-fail" => no_fail = true,
+            arg if url.is_none() => url = Some(arg),
+            arg => return Err(err(format!("fetch: unexpected argument '{}'", arg))),
+        }
+        i += 1;
+    }
+
+    let url = url.ok_or_else(|| err("fetch".to_string()))?;
+    let method = method.unwrap_or_else(|| if body.is_some() { "POST" } else { "GET" }.to_string());
+    let mut cmd = json!({ "id": id, "action": "fetch", "url": url, "method": method });
+    if !headers.is_empty() {
+        cmd["headers"] = Value::Object(headers);
+    }
+    if let Some(body) = body {
+        cmd["body"] = json!(body);
+    }
+    for (key, set) in [("include", include), ("raw", raw), ("noFail", no_fail)] {
+        if set {
+            cmd[key] = json!(true);
+        }
+    }
+    Ok(cmd)
+}
+
 fn parse_storage(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     const VALID: &[&str] = &["local", "session"];
     
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("local") | Some("session") => {
-            let storage_type = rest.get(0).unwrap();
+            let storage_type = rest.first().unwrap();
             let op = rest.get(1).unwrap_or(&"get");
             let key = rest.get(2);
             let value = rest.get(3);
@@ -996,31 +2126,99 @@ fn parse_storage(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     }
 }
 
+/// Everything on `stdin`, minus one trailing newline (as left by `echo`)
+fn read_stdin(context: &str, usage: &'static str, mut stdin: impl Read) -> Result<String, ParseError> {
+    let mut text = String::new();
+    stdin.read_to_string(&mut text).map_err(|e| ParseError::MissingArguments {
+        context: format!("{}: failed to read stdin ({})", context, e),
+        usage,
+    })?;
+    let trimmed = text.strip_suffix('\n').map(|t| t.strip_suffix('\r').unwrap_or(t));
+    Ok(trimmed.map(String::from).unwrap_or(text))
+}
+
+/// `clipboard read` / `clipboard write <text>`. With `--stdin` the text is
+/// read from `stdin`.
+fn parse_clipboard(rest: &[&str], id: &str, stdin: impl Read) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["read", "write"];
+    const USAGE: &str = "clipboard write <text> | clipboard write --stdin";
+
+    match rest.first().copied() {
+        Some("read") => Ok(json!({ "id": id, "action": "clipboard_read" })),
+        Some("write") => {
+            let text = if rest[1..].contains(&"--stdin") {
+                read_stdin("clipboard write", USAGE, stdin)?
+            } else if rest.len() > 1 {
+                rest[1..].join(" ")
+            } else {
+                return Err(ParseError::MissingArguments {
+                    context: "clipboard write".to_string(),
+                    usage: USAGE,
+                });
+            };
+            Ok(json!({ "id": id, "action": "clipboard_write", "text": text }))
+        }
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "clipboard".to_string(),
+            usage: "clipboard <read|write> [text]",
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::flags::Stealth;
+    use crate::test_support::TempDir;
 
     fn default_flags() -> Flags {
         Flags {
             session: "test".to_string(),
             json: false,
+            jsonl: false,
             full: false,
             headed: false,
             debug: false,
+            quiet: false,
             headers: None,
             executable_path: None,
             extensions: Vec::new(),
             cdp: None,
             proxy: None,
             profile: None,
+            user_data_dir: None,
             ignore_https_errors: false,
             session_name: None,
             state: None,
             persist: false,
             args: None,
             user_agent: None,
-            stealth: false,
+            stealth: Stealth::Off,
             backend: None,
+            no_daemon: false,
+            dry_run: false,
+            with_id: false,
+            fix: false,
+            timing: false,
+            timing_log: false,
+            record_script: None,
+            redact: false,
+            output: None,
+            retry: 0,
+            retry_delay: 500,
+            session_timeout: None,
+            connect_timeout: None,
+            remote: None,
+            remote_token: None,
+            listen: None,
+            proxy_file: None,
+            proxy_strategy: None,
+            proxy_bypass: None,
+            browser: None,
         }
     }
 
@@ -1183,6 +2381,278 @@ mod tests {
         assert!(cmd.get("headers").is_none());
     }
 
+    #[test]
+    fn test_navigate_wait_until() {
+        let cmd = parse_command(&args("open example.com --wait-until networkidle"), &default_flags()).unwrap();
+        assert_eq!(cmd["url"], "https://example.com");
+        assert_eq!(cmd["waitUntil"], "networkidle");
+        let cmd = parse_command(&args("open example.com"), &default_flags()).unwrap();
+        assert!(cmd.get("waitUntil").is_none());
+    }
+
+    #[test]
+    fn test_navigate_referer() {
+        let cmd = parse_command(&args("open example.com/page --referer https://google.com/"), &default_flags()).unwrap();
+        assert_eq!(cmd["url"], "https://example.com/page");
+        assert_eq!(cmd["referer"], "https://google.com/");
+        assert!(cmd.get("headers").is_none());
+        assert!(parse_command(&args("open example.com --referer"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_navigate_wait_until_referer_with_headers() {
+        let mut flags = default_flags();
+        flags.headers = Some(r#"{"Authorization": "Bearer token"}"#.to_string());
+        let cmd = parse_command(&args("open app.example.com --referer https://google.com/ --wait-until commit"), &flags).unwrap();
+        assert_eq!(cmd["url"], "https://app.example.com");
+        assert_eq!(cmd["waitUntil"], "commit");
+        assert_eq!(cmd["referer"], "https://google.com/");
+        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
+    }
+
+    #[test]
+    fn test_navigate_wait_until_invalid() {
+        let err = parse_command(&args("open example.com --wait-until idle"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("networkidle"));
+        assert!(parse_command(&args("open example.com --wait-until"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_reload_hard() {
+        let cmd = parse_command(&args("reload --hard"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "reload");
+        assert_eq!(cmd["bypassCache"], true);
+        let cmd = parse_command(&args("reload"), &default_flags()).unwrap();
+        assert!(cmd.get("bypassCache").is_none());
+    }
+
+    // === Set Viewport Tests ===
+
+    #[test]
+    fn test_set_viewport() {
+        let cmd = parse_command(&args("set viewport 1280 720"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "viewport");
+        assert_eq!(cmd["width"], 1280);
+        assert_eq!(cmd["height"], 720);
+        assert!(cmd.get("deviceScaleFactor").is_none());
+        assert!(cmd.get("isMobile").is_none());
+    }
+
+    #[test]
+    fn test_set_viewport_dsf_mobile() {
+        let cmd = parse_command(&args("set viewport 390 844 --dsf 3 --mobile"), &default_flags()).unwrap();
+        assert_eq!(cmd["width"], 390);
+        assert_eq!(cmd["height"], 844);
+        assert_eq!(cmd["deviceScaleFactor"], 3.0);
+        assert_eq!(cmd["isMobile"], true);
+    }
+
+    #[test]
+    fn test_set_viewport_invalid_dsf() {
+        assert!(parse_command(&args("set viewport 390 844 --dsf 0"), &default_flags()).is_err());
+        assert!(parse_command(&args("set viewport 390 844 --dsf abc"), &default_flags()).is_err());
+        assert!(parse_command(&args("set viewport 390 --mobile"), &default_flags()).is_err());
+    }
+
+    // === Set Device Tests ===
+
+    #[test]
+    fn test_set_device_known() {
+        let cmd = parse_command(&["set".to_string(), "device".to_string(), "iPhone 14".to_string()], &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "device");
+        assert_eq!(cmd["device"], "iPhone 14");
+        let cmd = parse_command(&args("set device Pixel 7 landscape"), &default_flags()).unwrap();
+        assert_eq!(cmd["device"], "Pixel 7 landscape");
+    }
+
+    #[test]
+    fn test_set_device_typo_suggests() {
+        let err = parse_command(&args("set device iPhone12"), &default_flags()).unwrap_err().format();
+        assert!(err.contains("unknown device 'iPhone12'"));
+        assert!(err.contains("did you mean 'iPhone 12'?"));
+        let err = parse_command(&args("set device Toaster"), &default_flags()).unwrap_err().format();
+        assert!(err.contains("--allow-unknown"));
+    }
+
+    #[test]
+    fn test_set_device_allow_unknown() {
+        let cmd = parse_command(&args("set device Galaxy Z Fold 9 --allow-unknown"), &default_flags()).unwrap();
+        assert_eq!(cmd["device"], "Galaxy Z Fold 9");
+        assert!(parse_command(&args("set device --allow-unknown"), &default_flags()).is_err());
+    }
+
+    // === Clipboard Tests ===
+
+    #[test]
+    fn test_clipboard_read() {
+        let cmd = parse_command(&args("clipboard read"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "clipboard_read");
+    }
+
+    #[test]
+    fn test_clipboard_write() {
+        let cmd = parse_command(&args("clipboard write hello world"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "clipboard_write");
+        assert_eq!(cmd["text"], "hello world");
+        assert!(parse_command(&args("clipboard write"), &default_flags()).is_err());
+        assert!(parse_command(&args("clipboard"), &default_flags()).is_err());
+        assert!(matches!(
+            parse_command(&args("clipboard paste"), &default_flags()),
+            Err(ParseError::UnknownSubcommand { .. })
+        ));
+    }
+
+    #[test]
+    fn test_clipboard_write_stdin() {
+        let cmd = parse_clipboard(&["write", "--stdin"], "1", "line one\n\"quoted\"\n".as_bytes()).unwrap();
+        assert_eq!(cmd["text"], "line one\n\"quoted\"");
+        let cmd = parse_clipboard(&["write", "--stdin"], "1", "no newline".as_bytes()).unwrap();
+        assert_eq!(cmd["text"], "no newline");
+    }
+
+    // === Get Article Tests ===
+
+    #[test]
+    fn test_get_article() {
+        let cmd = parse_command(&args("get article"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "article");
+        assert!(cmd.get("html").is_none());
+        assert!(cmd.get("maxChars").is_none());
+
+        let cmd = parse_command(&args("get article --html --max-chars 500"), &default_flags()).unwrap();
+        assert_eq!(cmd["html"], true);
+        assert_eq!(cmd["maxChars"], 500);
+    }
+
+    #[test]
+    fn test_get_article_invalid_max_chars() {
+        assert!(parse_command(&args("get article --max-chars"), &default_flags()).is_err());
+        assert!(parse_command(&args("get article --max-chars 0"), &default_flags()).is_err());
+        assert!(parse_command(&args("get article --max-chars lots"), &default_flags()).is_err());
+    }
+
+    // === Save Tests ===
+
+    #[test]
+    fn test_save_mhtml() {
+        let cmd = parse_command(&args("save /tmp/page.mhtml"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "savemhtml");
+        assert_eq!(cmd["path"], "/tmp/page.mhtml");
+        assert!(cmd.get("inline").is_none());
+    }
+
+    #[test]
+    fn test_save_html_inline() {
+        let cmd = parse_command(&args("save /tmp/Page.HTML"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "savehtml");
+        assert_eq!(cmd["inline"], true);
+    }
+
+    #[test]
+    fn test_save_resolves_relative_path() {
+        let cmd = parse_command(&args("save out/page.mhtml"), &default_flags()).unwrap();
+        let expected = std::env::current_dir().unwrap().join("out/page.mhtml");
+        assert_eq!(cmd["path"], expected.display().to_string());
+    }
+
+    #[test]
+    fn test_save_unknown_extension() {
+        let err = parse_command(&args("save page.pdf"), &default_flags()).unwrap_err().format();
+        assert!(err.contains(".mhtml, .html"));
+        assert!(parse_command(&args("save page"), &default_flags()).is_err());
+        assert!(parse_command(&args("save"), &default_flags()).is_err());
+    }
+
+    // === Stdin Value Tests ===
+
+    fn parse_with_stdin(line: &str, input: &str) -> Result<Value, ParseError> {
+        parse_command_with_stdin(&args(line), &default_flags(), input.as_bytes())
+    }
+
+    #[test]
+    fn test_fill_value_from_stdin() {
+        let cmd = parse_with_stdin("fill #pw -", "s3cr\"et $HOME\n").unwrap();
+        assert_eq!(cmd["action"], "fill");
+        assert_eq!(cmd["selector"], "#pw");
+        assert_eq!(cmd["value"], "s3cr\"et $HOME");
+        // Only a lone `-` reads stdin
+        let cmd = parse_with_stdin("fill #pw a - b", "unused").unwrap();
+        assert_eq!(cmd["value"], "a - b");
+    }
+
+    #[test]
+    fn test_type_text_from_stdin() {
+        let cmd = parse_with_stdin("type #msg -", "line 1\nline 2\r\n").unwrap();
+        assert_eq!(cmd["text"], "line 1\nline 2");
+    }
+
+    #[test]
+    fn test_eval_script_from_stdin() {
+        let cmd = parse_with_stdin("eval -", "document.querySelectorAll('a').length\n").unwrap();
+        assert_eq!(cmd["script"], "document.querySelectorAll('a').length");
+        let cmd = parse_with_stdin("eval --await -", "await fetch('/api')").unwrap();
+        assert_eq!(cmd["script"], "await fetch('/api')");
+        assert_eq!(cmd["await"], true);
+    }
+
+    // === Find Text Tests ===
+
+    #[test]
+    fn test_find_text() {
+        let cmd = parse_command(&args("find-text refund policy"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "textsearch");
+        assert_eq!(cmd["text"], "refund policy");
+        assert_eq!(cmd["caseSensitive"], false);
+        assert!(cmd.get("regex").is_none());
+        assert!(cmd.get("countOnly").is_none());
+    }
+
+    #[test]
+    fn test_find_text_options() {
+        let cmd = parse_command(&args("find-text --regex refund(s)? --case-sensitive --count-only"), &default_flags()).unwrap();
+        assert_eq!(cmd["text"], "refund(s)?");
+        assert_eq!(cmd["caseSensitive"], true);
+        assert_eq!(cmd["regex"], true);
+        assert_eq!(cmd["countOnly"], true);
+        assert!(parse_command(&args("find-text --regex"), &default_flags()).is_err());
+    }
+
+    // === Set Offline Tests ===
+
+    #[test]
+    fn test_set_offline_default_on() {
+        let cmd = parse_command(&args("set offline"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "offline");
+        assert_eq!(cmd["offline"], true);
+    }
+
+    #[test]
+    fn test_set_offline_off() {
+        let cmd = parse_command(&args("set offline off"), &default_flags()).unwrap();
+        assert_eq!(cmd["offline"], false);
+        let cmd = parse_command(&args("set offline FALSE"), &default_flags()).unwrap();
+        assert_eq!(cmd["offline"], false);
+        let cmd = parse_command(&args("set offline True"), &default_flags()).unwrap();
+        assert_eq!(cmd["offline"], true);
+    }
+
+    #[test]
+    fn test_set_offline_invalid_value() {
+        let err = parse_command(&args("set offline maybe"), &default_flags()).unwrap_err();
+        assert!(matches!(err, ParseError::MissingArguments { .. }));
+        assert!(err.format().contains("maybe"));
+    }
+
+    #[test]
+    fn test_set_headless() {
+        let cmd = parse_command(&args("set headless off"), &default_flags()).unwrap();
+        assert_eq!(cmd, json!({ "id": cmd["id"], "action": "headless", "headless": false }));
+        assert_eq!(parse_command(&args("set headless ON"), &default_flags()).unwrap()["headless"], true);
+        assert!(parse_command(&args("set headless"), &default_flags()).is_err());
+        let err = parse_command(&args("set headless maybe"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("invalid value 'maybe'"));
+    }
+
     // === Set Headers Tests ===
 
     #[test]
@@ -1232,6 +2702,24 @@ mod tests {
     fn test_forward() {
         let cmd = parse_command(&args("forward"), &default_flags()).unwrap();
         assert_eq!(cmd["action"], "forward");
+        assert_eq!(cmd["steps"], 1);
+    }
+
+    #[test]
+    fn test_back_steps() {
+        let cmd = parse_command(&args("back 2"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "back");
+        assert_eq!(cmd["steps"], 2);
+        let cmd = parse_command(&args("forward 3"), &default_flags()).unwrap();
+        assert_eq!(cmd["steps"], 3);
+    }
+
+    #[test]
+    fn test_back_invalid_steps() {
+        let err = parse_command(&args("back two"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("two"));
+        assert!(parse_command(&args("back 0"), &default_flags()).is_err());
+        assert!(parse_command(&args("forward -1"), &default_flags()).is_err());
     }
 
     #[test]
@@ -1263,6 +2751,18 @@ mod tests {
         assert_eq!(cmd["action"], "type");
         assert_eq!(cmd["selector"], "#input");
         assert_eq!(cmd["text"], "some text");
+        assert!(cmd.get("clear").is_none());
+    }
+
+    #[test]
+    fn test_type_clear() {
+        let cmd = parse_command(&args("type #input hello --clear"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "type");
+        assert_eq!(cmd["text"], "hello");
+        assert_eq!(cmd["clear"], true);
+        let cmd = parse_command(&args("type #input --clear-first hello world"), &default_flags()).unwrap();
+        assert_eq!(cmd["text"], "hello world");
+        assert_eq!(cmd["clear"], true);
     }
 
     // === Tabs ===
@@ -1292,6 +2792,33 @@ mod tests {
         assert_eq!(cmd["action"], "tab_close");
     }
 
+    #[test]
+    fn test_tab_close_all() {
+        let cmd = parse_command(&args("tab close --all"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "tab_close");
+        assert_eq!(cmd["all"], true);
+        assert!(cmd.get("index").is_none());
+    }
+
+    #[test]
+    fn test_tab_close_by_title() {
+        let cmd = parse_command(&args("tab close --title Gmail"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "tab_close");
+        assert_eq!(cmd["title"], "Gmail");
+    }
+
+    #[test]
+    fn test_tab_close_title_missing_value() {
+        let result = parse_command(&args("tab close --title"), &default_flags());
+        assert!(matches!(result.unwrap_err(), ParseError::MissingArguments { .. }));
+    }
+
+    #[test]
+    fn test_tab_close_index() {
+        let cmd = parse_command(&args("tab close 1"), &default_flags()).unwrap();
+        assert_eq!(cmd["index"], 1);
+    }
+
     // === Screenshot ===
 
     #[test]
@@ -1338,6 +2865,22 @@ mod tests {
         assert_eq!(cmd["maxDepth"], 3);
     }
 
+    #[test]
+    fn test_snapshot_json_tree() {
+        let cmd = parse_command(&args("snapshot --json-tree"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "snapshot");
+        assert_eq!(cmd["format"], "json");
+    }
+
+    #[test]
+    fn test_snapshot_json_tree_composes() {
+        let cmd = parse_command(&args("snapshot -i --json-tree -d 3 -s #main"), &default_flags()).unwrap();
+        assert_eq!(cmd["format"], "json");
+        assert_eq!(cmd["interactive"], true);
+        assert_eq!(cmd["maxDepth"], 3);
+        assert_eq!(cmd["selector"], "#main");
+    }
+
     // === Wait ===
 
     #[test]
@@ -1382,6 +2925,43 @@ mod tests {
         assert_eq!(cmd["expression"], "window.ready");
     }
 
+    #[test]
+    fn test_wait_any() {
+        let cmd = parse_command(&args("wait --any #toast-success #toast-error --timeout 10000"), &default_flags()).unwrap();
+        assert_eq!(
+            cmd,
+            json!({
+                "id": cmd["id"],
+                "action": "wait_multi",
+                "selectors": ["#toast-success", "#toast-error"],
+                "mode": "any",
+                "timeout": 10000
+            })
+        );
+    }
+
+    #[test]
+    fn test_wait_all() {
+        let cmd = parse_command(&args("wait #a @e2 --all"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "wait_multi");
+        assert_eq!(cmd["selectors"], json!(["#a", "@e2"]));
+        assert_eq!(cmd["mode"], "all");
+        assert!(cmd.get("timeout").is_none());
+
+        // A single selector without a mode is still a plain wait
+        assert_eq!(parse_command(&args("wait #a"), &default_flags()).unwrap()["action"], "wait");
+    }
+
+    #[test]
+    fn test_wait_multi_errors() {
+        let err = parse_command(&args("wait --any --timeout 5000"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("wait --any: no selectors given"), "{}", err.format());
+        let err = parse_command(&args("wait --all"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("wait --all: no selectors given"));
+        let err = parse_command(&args("wait --any --all #a"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("can't be combined"));
+    }
+
     #[test]
     fn test_wait_text() {
         let cmd = parse_command(&args("wait --text Welcome"), &default_flags()).unwrap();
@@ -1424,12 +3004,96 @@ mod tests {
         assert!(matches!(result.unwrap_err(), ParseError::MissingArguments { .. }));
     }
 
+    #[test]
+    fn test_record_start_with_size_and_fps() {
+        let cmd = parse_command(&args("record start demo.webm --size 390x844 --fps 30"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "recording_start");
+        assert_eq!(cmd["path"], "demo.webm");
+        assert_eq!(cmd["size"], json!({ "width": 390, "height": 844 }));
+        assert!(cmd.get("width").is_none());
+        assert_eq!(cmd["fps"], 30);
+        assert!(cmd.get("url").is_none());
+        assert!(cmd.get("preserveState").is_none());
+    }
+
+    #[test]
+    fn test_record_start_size_object() {
+        let cmd = parse_command(&args("record start demo.webm --size 1280x720"), &default_flags()).unwrap();
+        assert_eq!(cmd["path"], "demo.webm");
+        assert_eq!(cmd["size"]["width"], 1280);
+        assert_eq!(cmd["size"]["height"], 720);
+    }
+
+    #[test]
+    fn test_record_start_options_before_url() {
+        let cmd = parse_command(&args("record start demo.webm --size 1280x720 example.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["url"], "https://example.com");
+        assert_eq!(cmd["size"], json!({ "width": 1280, "height": 720 }));
+    }
+
+    #[test]
+    fn test_record_start_invalid_size() {
+        for bad in ["1280", "1280x", "x720", "12a0x720", "1280*720"] {
+            let result = parse_command(&args(&format!("record start demo.webm --size {}", bad)), &default_flags());
+            let err = result.unwrap_err();
+            assert!(matches!(err, ParseError::MissingArguments { .. }));
+            assert!(err.format().contains("--size"), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_record_start_invalid_fps() {
+        let result = parse_command(&args("record start demo.webm --fps 0"), &default_flags());
+        assert!(result.unwrap_err().format().contains("--fps"));
+    }
+
+    #[test]
+    fn test_record_start_no_preserve_state() {
+        let cmd = parse_command(&args("record start demo.webm --no-preserve-state"), &default_flags()).unwrap();
+        assert_eq!(cmd["preserveState"], false);
+    }
+
+    #[test]
+    fn test_record_restart_with_size() {
+        let cmd = parse_command(&args("record restart take2.webm --size 800x600"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "recording_restart");
+        assert_eq!(cmd["size"], json!({ "width": 800, "height": 600 }));
+    }
+
     #[test]
     fn test_record_stop() {
         let cmd = parse_command(&args("record stop"), &default_flags()).unwrap();
         assert_eq!(cmd["action"], "recording_stop");
     }
 
+    #[test]
+    fn test_record_stop_gif() {
+        let cmd = parse_command(&args("record stop --gif out.gif"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "recording_stop");
+        assert_eq!(cmd["gif"], "out.gif");
+        assert!(cmd.get("framesDir").is_none());
+    }
+
+    #[test]
+    fn test_record_stop_frames() {
+        let cmd = parse_command(&args("record stop --frames ./frames/"), &default_flags()).unwrap();
+        assert_eq!(cmd["framesDir"], "./frames/");
+        assert!(cmd.get("gif").is_none());
+    }
+
+    #[test]
+    fn test_record_stop_gif_and_frames_exclusive() {
+        let result = parse_command(&args("record stop --gif out.gif --frames ./frames"), &default_flags());
+        let err = result.unwrap_err();
+        assert!(err.format().contains("mutually exclusive"));
+    }
+
+    #[test]
+    fn test_record_stop_gif_missing_path() {
+        let result = parse_command(&args("record stop --gif"), &default_flags());
+        assert!(matches!(result.unwrap_err(), ParseError::MissingArguments { .. }));
+    }
+
     #[test]
     fn test_record_restart() {
         let cmd = parse_command(&args("record restart output.webm"), &default_flags()).unwrap();
@@ -1510,4 +3174,913 @@ mod tests {
         assert!(matches!(err, ParseError::MissingArguments { .. }));
         assert!(err.format().contains("get text"));
     }
+
+    // === Errors ===
+
+    #[test]
+    fn test_errors_default() {
+        let cmd = parse_command(&args("errors"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "errors");
+        assert_eq!(cmd["short"], false);
+    }
+
+    #[test]
+    fn test_errors_short() {
+        let cmd = parse_command(&args("errors --short"), &default_flags()).unwrap();
+        assert_eq!(cmd["short"], true);
+        assert_eq!(cmd["clear"], false);
+    }
+
+    #[test]
+    fn test_errors_stack() {
+        let cmd = parse_command(&args("errors --stack"), &default_flags()).unwrap();
+        assert_eq!(cmd["includeStack"], true);
+        let cmd = parse_command(&args("errors"), &default_flags()).unwrap();
+        assert!(cmd.get("includeStack").is_none());
+    }
+
+    // === Trace ===
+
+    #[test]
+    fn test_trace_start_categories() {
+        let cmd = parse_command(&args("trace start ./t.zip --screenshots --sources"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "trace_start");
+        assert_eq!(cmd["path"], "./t.zip");
+        assert_eq!(cmd["screenshots"], true);
+        assert_eq!(cmd["sources"], true);
+        assert!(cmd.get("snapshots").is_none());
+
+        let cmd = parse_command(&args("trace start --snapshots"), &default_flags()).unwrap();
+        assert_eq!(cmd["snapshots"], true);
+        assert!(cmd["path"].is_null());
+
+        let cmd = parse_command(&args("trace start"), &default_flags()).unwrap();
+        assert!(cmd.get("screenshots").is_none() && cmd.get("snapshots").is_none() && cmd.get("sources").is_none());
+    }
+
+    #[test]
+    fn test_trace_view() {
+        let cmd = parse_command(&args("trace view ./debug-trace.zip"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "trace_view");
+        assert_eq!(cmd["path"], "./debug-trace.zip");
+    }
+
+    #[test]
+    fn test_trace_view_missing_path() {
+        let result = parse_command(&args("trace view"), &default_flags());
+        assert!(matches!(result.unwrap_err(), ParseError::MissingArguments { .. }));
+    }
+
+    // === Find ===
+
+    #[test]
+    fn test_find_role_with_name() {
+        let cmd = parse_command(&args("find role button click --name Submit"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "getbyrole");
+        assert_eq!(cmd["role"], "button");
+        assert_eq!(cmd["subaction"], "click");
+        assert_eq!(cmd["name"], "Submit");
+        assert!(cmd["value"].is_null());
+        assert!(cmd.get("nth").is_none());
+    }
+
+    #[test]
+    fn test_find_role_with_nth() {
+        let cmd = parse_command(&args("find role button click --name Save --nth 1"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "getbyrole");
+        assert_eq!(cmd["name"], "Save");
+        assert_eq!(cmd["nth"], 1);
+        assert!(cmd["value"].is_null());
+    }
+
+    #[test]
+    fn test_find_nth_negative_index() {
+        let cmd = parse_command(&args("find text Delete click --nth -1"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "getbytext");
+        assert_eq!(cmd["nth"], -1);
+    }
+
+    #[test]
+    fn test_find_nth_invalid_index() {
+        let result = parse_command(&args("find text Delete click --nth last"), &default_flags());
+        assert!(matches!(result.unwrap_err(), ParseError::MissingArguments { .. }));
+    }
+
+    #[test]
+    fn test_find_label_fill_with_nth() {
+        let cmd = parse_command(&args("find label Email fill --nth 0 user@example.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["subaction"], "fill");
+        assert_eq!(cmd["value"], "user@example.com");
+        assert_eq!(cmd["nth"], 0);
+    }
+
+    #[test]
+    fn test_find_nth_locator() {
+        let cmd = parse_command(&args("find nth 2 .card hover"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "nth");
+        assert_eq!(cmd["index"], 2);
+        assert_eq!(cmd["selector"], ".card");
+        assert_eq!(cmd["subaction"], "hover");
+    }
+
+    #[test]
+    fn test_find_positional_locators_reject_nth() {
+        for input in ["find first .row click --nth 2", "find last li click --nth 0", "find nth 1 .card click --nth 2"] {
+            match parse_command(&args(input), &default_flags()).unwrap_err() {
+                ParseError::MissingArguments { context, .. } => assert!(context.contains("--nth only applies"), "{}", context),
+                other => panic!("{}: unexpected {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_positional_locators_reject_filters() {
+        let input: Vec<String> = vec!["find", "last", "li", "click", "--within", "#cart", "--has-text", "In cart"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        match parse_command(&input, &default_flags()).unwrap_err() {
+            ParseError::MissingArguments { context, .. } => assert!(context.contains("--has-text only applies"), "{}", context),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(parse_command(&args("find first .row click --within #list"), &default_flags()).is_err());
+        assert!(parse_command(&args("find nth 1 .card click --has-text Sale"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_find_has_text_and_within() {
+        let input: Vec<String> = vec!["find", "role", "listitem", "click", "--has-text", "In cart", "--within", "#cart"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let cmd = parse_command(&input, &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "getbyrole");
+        assert_eq!(cmd["role"], "listitem");
+        assert_eq!(cmd["subaction"], "click");
+        assert_eq!(cmd["hasText"], "In cart");
+        assert_eq!(cmd["within"], "#cart");
+        assert!(cmd["value"].is_null());
+    }
+
+    #[test]
+    fn test_find_filters_omitted_when_absent() {
+        let cmd = parse_command(&args("find text Login"), &default_flags()).unwrap();
+        assert!(cmd.get("hasText").is_none());
+        assert!(cmd.get("within").is_none());
+    }
+
+    #[test]
+    fn test_find_default_action_is_click() {
+        let cmd = parse_command(&args("find text Login"), &default_flags()).unwrap();
+        assert_eq!(cmd["subaction"], "click");
+    }
+
+    #[test]
+    fn test_find_locate() {
+        let cmd = parse_command(&args("find text Login --locate"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "getbytext");
+        assert_eq!(cmd["subaction"], "locate");
+
+        let cmd = parse_command(&args("find role button locate --name Save"), &default_flags()).unwrap();
+        assert_eq!(cmd["subaction"], "locate");
+        assert_eq!(cmd["name"], "Save");
+
+        let cmd = parse_command(&args("find nth 1 .card --locate"), &default_flags()).unwrap();
+        assert_eq!(cmd["subaction"], "locate");
+
+        // An explicit action still wins over --locate
+        let cmd = parse_command(&args("find text Login click --locate"), &default_flags()).unwrap();
+        assert_eq!(cmd["subaction"], "click");
+    }
+
+    #[test]
+    fn test_highlight_color_and_duration() {
+        let cmd = parse_command(&args("highlight #x --color red --duration 2000"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "highlight");
+        assert_eq!(cmd["selector"], "#x");
+        assert_eq!(cmd["color"], "red");
+        assert_eq!(cmd["duration"], 2000);
+
+        let cmd = parse_command(&args("highlight @e5"), &default_flags()).unwrap();
+        assert!(cmd.get("color").is_none());
+        assert!(cmd.get("duration").is_none());
+    }
+
+    #[test]
+    fn test_highlight_invalid_duration() {
+        for input in ["highlight #x --duration -5", "highlight #x --duration soon", "highlight #x --duration"] {
+            let result = parse_command(&args(input), &default_flags());
+            assert!(matches!(result, Err(ParseError::MissingArguments { .. })), "{}", input);
+        }
+        assert!(parse_command(&args("highlight --color red"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_highlight_multiple_selectors() {
+        let cmd = parse_command(&args("highlight #a .b @e3 --color #0f08 --duration 0"), &default_flags()).unwrap();
+        assert_eq!(cmd["selectors"], json!(["#a", ".b", "@e3"]));
+        assert!(cmd.get("selector").is_none());
+        assert_eq!(cmd["color"], "#0f08");
+        assert_eq!(cmd["duration"], 0);
+    }
+
+    #[test]
+    fn test_highlight_clear() {
+        let cmd = parse_command(&args("highlight --clear"), &default_flags()).unwrap();
+        assert_eq!(cmd, json!({ "id": cmd["id"], "action": "highlight_clear" }));
+        let err = parse_command(&args("highlight --clear #a"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("--clear takes no selectors"));
+    }
+
+    #[test]
+    fn test_highlight_color_validation() {
+        for color in ["#f80", "#FF8800", "#ff880080", "Tomato", "rebeccapurple", "rgba(0,0,255,.4)", "hsl(120,50%,50%)"] {
+            let cmd = parse_command(&args(&format!("highlight #x --color {}", color)), &default_flags()).unwrap();
+            assert_eq!(cmd["color"], color);
+        }
+        for color in ["#ff888", "#ggg", "redd", "rgb(0,0,0", "blu"] {
+            let err = parse_command(&args(&format!("highlight #x --color {}", color)), &default_flags()).unwrap_err();
+            assert!(err.format().contains(&format!("invalid --color '{}'", color)), "{}", color);
+        }
+    }
+
+    #[test]
+    fn test_console_single_level() {
+        let cmd = parse_command(&args("console --level error"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "console");
+        assert_eq!(cmd["levels"], json!(["error"]));
+        assert_eq!(cmd["clear"], false);
+
+        let cmd = parse_command(&args("console"), &default_flags()).unwrap();
+        assert!(cmd.get("levels").is_none());
+    }
+
+    #[test]
+    fn test_console_multiple_levels() {
+        let cmd = parse_command(&args("console --level warn --level error --clear"), &default_flags()).unwrap();
+        assert_eq!(cmd["levels"], json!(["warning", "error"]));
+        assert_eq!(cmd["clear"], true);
+
+        let cmd = parse_command(&args("console --level log,info --level info"), &default_flags()).unwrap();
+        assert_eq!(cmd["levels"], json!(["log", "info"]));
+
+        let result = parse_command(&args("console --level debug"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        assert!(parse_command(&args("console --level"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_gen_id_unique_across_threads() {
+        let threads: Vec<_> = (0..8)
+            .map(|_| std::thread::spawn(|| (0..125_000).map(|_| gen_id()).collect::<Vec<_>>()))
+            .collect();
+        let mut seen = std::collections::HashSet::new();
+        for thread in threads {
+            for id in thread.join().unwrap() {
+                assert!(seen.insert(id));
+            }
+        }
+        assert_eq!(seen.len(), 1_000_000);
+        let id = gen_id();
+        let (prefix, counter) = id.strip_prefix('r').unwrap().split_once('-').unwrap();
+        assert!(!prefix.is_empty() && prefix.len() <= 13);
+        assert!(counter.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn test_base36() {
+        assert_eq!(base36(0), "0");
+        assert_eq!(base36(35), "z");
+        assert_eq!(base36(36), "10");
+        assert_eq!(base36(u64::MAX), "3w5e11264sgsf");
+    }
+
+    #[test]
+    fn test_wait_selector_with_timeout() {
+        let cmd = parse_command(&args("wait #results --timeout 10000"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "wait");
+        assert_eq!(cmd["selector"], "#results");
+        assert_eq!(cmd["timeout"], 10000);
+
+        let cmd = parse_command(&args("wait --timeout 5000 #results"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "#results");
+        assert_eq!(cmd["timeout"], 5000);
+
+        let cmd = parse_command(&args("wait #results"), &default_flags()).unwrap();
+        assert!(cmd.get("timeout").is_none());
+    }
+
+    #[test]
+    fn test_wait_text_short_flag_with_timeout() {
+        // -t stays --text; the timeout only has a long form
+        let cmd = parse_command(&args("wait -t Welcome --timeout 3000"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "text=Welcome");
+        assert_eq!(cmd["timeout"], 3000);
+
+        let cmd = parse_command(&args("wait --url **/done --timeout 8000"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "waitforurl");
+        assert_eq!(cmd["timeout"], 8000);
+    }
+
+    #[test]
+    fn test_wait_timeout_errors() {
+        for input in ["wait #x --timeout", "wait #x --timeout soon", "wait #x --timeout 0", "wait 2000 --timeout 100"] {
+            let err = parse_command(&args(input), &default_flags()).unwrap_err();
+            assert!(err.format().contains("--timeout"), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_get_html_inner_by_default() {
+        let cmd = parse_command(&args("get html #content"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "innerhtml");
+        assert_eq!(cmd["selector"], "#content");
+    }
+
+    #[test]
+    fn test_get_html_outer() {
+        for input in ["get html #content --outer", "get html --outer #content"] {
+            let cmd = parse_command(&args(input), &default_flags()).unwrap();
+            assert_eq!(cmd["action"], "outerhtml");
+            assert_eq!(cmd["selector"], "#content");
+        }
+        assert!(parse_command(&args("get html --outer"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_set_media_print() {
+        let cmd = parse_command(&args("set media print"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "media");
+        assert_eq!(cmd["media"], "print");
+        assert!(cmd.get("forcedColors").is_none());
+        assert_eq!(parse_command(&args("set media screen"), &default_flags()).unwrap()["media"], "screen");
+    }
+
+    #[test]
+    fn test_set_media_forced_colors() {
+        let cmd = parse_command(&args("set media forced-colors"), &default_flags()).unwrap();
+        assert_eq!(cmd["forcedColors"], "active");
+        assert!(cmd.get("media").is_none());
+        let cmd = parse_command(&args("set media dark forced-colors none reduced-motion"), &default_flags()).unwrap();
+        assert_eq!(cmd["forcedColors"], "none");
+        assert_eq!(cmd["colorScheme"], "dark");
+        assert_eq!(cmd["reducedMotion"], true);
+    }
+
+    #[test]
+    fn test_is_ref() {
+        assert!(is_ref("@e12"));
+        assert!(is_ref("@e1"));
+        assert!(!is_ref("@e"));
+        assert!(!is_ref("e12"));
+        assert!(!is_ref("@e12a"));
+        assert!(!is_ref("#e12"));
+    }
+
+    #[test]
+    fn test_refs_pass_through_selectors() {
+        let f = default_flags();
+        for sub in ["text", "html", "value", "count", "box"] {
+            let cmd = parse_command(&args(&format!("get {} @e12", sub)), &f).unwrap();
+            assert_eq!(cmd["selector"], "@e12", "get {}", sub);
+        }
+        let cmd = parse_command(&args("get attr @e12 href"), &f).unwrap();
+        assert_eq!(cmd["selector"], "@e12");
+        for sub in ["visible", "enabled", "checked"] {
+            let cmd = parse_command(&args(&format!("is {} @e12", sub)), &f).unwrap();
+            assert_eq!(cmd["selector"], "@e12", "is {}", sub);
+        }
+        let cmd = parse_command(&args("find text Save click --within @e12"), &f).unwrap();
+        assert_eq!(cmd["within"], "@e12");
+        let cmd = parse_command(&args("drag @e12 @e13"), &f).unwrap();
+        assert_eq!(cmd["source"], "@e12");
+        assert_eq!(cmd["target"], "@e13");
+        let cmd = parse_command(&args("scrollintoview @e12"), &f).unwrap();
+        assert_eq!(cmd["selector"], "@e12");
+        let cmd = parse_command(&args("highlight @e12"), &f).unwrap();
+        assert_eq!(cmd["selector"], "@e12");
+
+        let dir = TempDir::new("ref-upload");
+        let file = dir.write("f.txt", "x");
+        let cmd = parse_command(&args(&format!("upload @e12 {}", file.display())), &f).unwrap();
+        assert_eq!(cmd["selector"], "@e12");
+        // Files first is a mistake, not a file called @e12
+        let err = parse_command(&args(&format!("upload {} @e12", file.display())), &f).unwrap_err();
+        assert!(err.format().contains("element ref"));
+    }
+
+    #[test]
+    fn test_drag_steps_and_hold() {
+        let cmd = parse_command(&args("drag @e1 @e2 --steps 10 --hold 500"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "drag");
+        assert_eq!(cmd["source"], "@e1");
+        assert_eq!(cmd["target"], "@e2");
+        assert_eq!(cmd["steps"], 10);
+        assert_eq!(cmd["hold"], 500);
+
+        let cmd = parse_command(&args("drag --hold 0 #a #b"), &default_flags()).unwrap();
+        assert_eq!(cmd["source"], "#a");
+        assert_eq!(cmd["hold"], 0);
+        assert!(cmd.get("steps").is_none());
+
+        let cmd = parse_command(&args("drag #a #b"), &default_flags()).unwrap();
+        assert!(cmd.get("steps").is_none() && cmd.get("hold").is_none());
+    }
+
+    #[test]
+    fn test_drag_invalid_options() {
+        let f = default_flags();
+        assert!(parse_command(&args("drag #a #b --steps 0"), &f).unwrap_err().format().contains("--steps '0'"));
+        assert!(parse_command(&args("drag #a #b --steps"), &f).is_err());
+        assert!(parse_command(&args("drag #a #b --hold soon"), &f).unwrap_err().format().contains("--hold 'soon'"));
+        assert!(parse_command(&args("drag #a --steps 5"), &f).is_err());
+    }
+
+    #[test]
+    fn test_connect_discover() {
+        let cmd = parse_command(&args("connect --discover"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "launch");
+        assert_eq!(cmd["cdpDiscover"], true);
+        assert!(cmd.get("cdpPort").is_none());
+
+        let cmd = parse_command(&args("connect 9222"), &default_flags()).unwrap();
+        assert_eq!(cmd["cdpPort"], 9222);
+        assert!(cmd.get("cdpDiscover").is_none());
+
+        for line in ["connect 9222 --discover", "connect --discover ws://127.0.0.1:9222/devtools/browser/x"] {
+            let err = parse_command(&args(line), &default_flags()).unwrap_err().format();
+            assert!(err.contains("can't be combined"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_short_full_flag_position() {
+        let parse = |line: &str| {
+            let input = args(line);
+            let flags = crate::flags::parse_flags(&input);
+            parse_command(&crate::flags::clean_args(&input), &flags).unwrap()
+        };
+        let cmd = parse("-f screenshot out.png");
+        assert_eq!(cmd["fullPage"], true);
+        assert_eq!(cmd["path"], "out.png");
+        let cmd = parse("screenshot --full out.png");
+        assert_eq!(cmd["fullPage"], true);
+        assert_eq!(cmd["path"], "out.png");
+        let cmd = parse("screenshot -f");
+        assert_eq!(cmd["fullPage"], true);
+        assert!(cmd.get("path").is_none());
+        assert_eq!(parse("screenshot out.png")["fullPage"], false);
+
+        // A literal -f is a value like any other
+        let cmd = parse("fill #x -f");
+        assert_eq!(cmd["action"], "fill");
+        assert_eq!(cmd["value"], "-f");
+        assert_eq!(parse("type #x -p")["text"], "-p");
+        assert_eq!(parse("fill #x -o")["value"], "-o");
+        assert_eq!(parse("-o shot.png fill #x -o")["value"], "-o");
+        assert_eq!(parse("wait -f window.ready")["action"], "waitforfunction");
+    }
+
+    #[test]
+    fn test_cookies_get_url() {
+        let cmd = parse_command(&args("cookies get --url https://example.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_get");
+        assert_eq!(cmd["url"], "https://example.com");
+        let cmd = parse_command(&args("cookies --url https://example.com/account"), &default_flags()).unwrap();
+        assert_eq!(cmd["url"], "https://example.com/account");
+
+        let cmd = parse_command(&args("cookies get"), &default_flags()).unwrap();
+        assert!(cmd.get("url").is_none());
+        assert!(parse_command(&args("cookies get --url"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_network_route_continue_with_rewrite() {
+        let cmd = parse_command(
+            &args("network route **/api/v1/* --continue --set-url https://staging.example.com/api --set-method post --set-header X-Debug:1 --set-header Accept:text/json"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "route");
+        assert_eq!(cmd["url"], "**/api/v1/*");
+        assert_eq!(cmd["abort"], false);
+        assert_eq!(
+            cmd["continue"],
+            json!({ "url": "https://staging.example.com/api", "method": "POST", "headers": { "X-Debug": "1", "Accept": "text/json" } })
+        );
+
+        let cmd = parse_command(&args("network route **/*.js --continue"), &default_flags()).unwrap();
+        assert_eq!(cmd["continue"], json!({}));
+        let cmd = parse_command(&args("network route **/ads/* --abort"), &default_flags()).unwrap();
+        assert_eq!(cmd["abort"], true);
+        assert!(cmd.get("continue").is_none());
+    }
+
+    #[test]
+    fn test_network_route_conflicts() {
+        for input in ["network route ** --abort --continue", "network route ** --body {} --abort", "network route ** --continue --body {}"] {
+            let err = parse_command(&args(input), &default_flags()).unwrap_err();
+            assert!(err.format().contains("--abort, --body and --continue can't be combined"), "{}", input);
+        }
+        let err = parse_command(&args("network route ** --set-url https://x.test"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("need --continue"));
+        let err = parse_command(&args("network route ** --continue --set-header nocolon"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("invalid --set-header 'nocolon'"));
+        assert!(parse_command(&args("network route ** --continue --set-method"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_fetch_get() {
+        let cmd = parse_command(&args("fetch /api/me"), &default_flags()).unwrap();
+        assert_eq!(cmd, json!({ "id": cmd["id"], "action": "fetch", "url": "/api/me", "method": "GET" }));
+    }
+
+    #[test]
+    fn test_fetch_headers_repeat() {
+        let input = vec![
+            "fetch", "https://api.example.com/items", "-X", "put", "--header", "Accept: application/json",
+            "-H", "X-Token:abc:def", "--header", "Accept: text/plain", "--include", "--raw", "--no-fail",
+        ];
+        let cmd = parse_command(&input.into_iter().map(String::from).collect::<Vec<_>>(), &default_flags()).unwrap();
+        assert_eq!(cmd["method"], "PUT");
+        // Later headers win; values keep their own colons
+        assert_eq!(cmd["headers"], json!({ "Accept": "text/plain", "X-Token": "abc:def" }));
+        assert_eq!(cmd["include"], true);
+        assert_eq!(cmd["raw"], true);
+        assert_eq!(cmd["noFail"], true);
+
+        let err = parse_command(&args("fetch /x --header nocolon"), &default_flags()).unwrap_err();
+        assert!(err.format().contains("invalid header 'nocolon'"));
+        assert!(parse_command(&args("fetch /x --header"), &default_flags()).is_err());
+        assert!(parse_command(&args("fetch"), &default_flags()).is_err());
+        assert!(parse_command(&args("fetch /x /y"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_fetch_data_file() {
+        let dir = TempDir::new("fetch-body");
+        let file = dir.write("body.json", "{\"name\": \"x\"}");
+        let cmd = parse_command(&args(&format!("fetch /api/items --data @{}", file.display())), &default_flags()).unwrap();
+        assert_eq!(cmd["body"], "{\"name\": \"x\"}");
+        assert_eq!(cmd["method"], "POST");
+        std::fs::remove_file(&file).unwrap();
+
+        let err = parse_command(&args(&format!("fetch /api/items --data @{}", file.display())), &default_flags()).unwrap_err();
+        assert!(err.format().contains("cannot read"));
+
+        let cmd = parse_command(&args("fetch /api/items -d a=1 --method patch"), &default_flags()).unwrap();
+        assert_eq!(cmd["body"], "a=1");
+        assert_eq!(cmd["method"], "PATCH");
+    }
+
+    #[test]
+    fn test_ping() {
+        let cmd = parse_command(&args("ping"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "ping");
+    }
+
+    #[test]
+    fn test_find_within_missing_value() {
+        let result = parse_command(&args("find label Email fill --within"), &default_flags());
+        assert!(matches!(result.unwrap_err(), ParseError::MissingArguments { .. }));
+    }
+
+    // === State ===
+
+    #[test]
+    fn test_state_save() {
+        let cmd = parse_command(&args("state save ./auth.json"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "state_save");
+        assert_eq!(cmd["path"], "./auth.json");
+        assert!(cmd.get("cookiesOnly").is_none());
+        assert!(cmd.get("origins").is_none());
+    }
+
+    #[test]
+    fn test_state_save_cookies_only_with_origins() {
+        let cmd = parse_command(
+            &args("state save ./fixture.json --cookies-only --origin https://a.com --origin https://b.com"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["cookiesOnly"], true);
+        assert_eq!(cmd["origins"], json!(["https://a.com", "https://b.com"]));
+    }
+
+    #[test]
+    fn test_state_save_storage_only() {
+        let cmd = parse_command(&args("state save ./s.json --storage-only"), &default_flags()).unwrap();
+        assert_eq!(cmd["storageOnly"], true);
+    }
+
+    #[test]
+    fn test_state_save_origin_without_cookies() {
+        let cmd = parse_command(&args("state save ./auth.json --origin https://app.example.com --no-cookies"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "state_save");
+        assert_eq!(cmd["origins"], json!(["https://app.example.com"]));
+        assert_eq!(cmd["storageOnly"], true);
+
+        // The last toggle wins
+        let cmd = parse_command(&args("state save ./auth.json --no-cookies --origin https://a.com --include-cookies"), &default_flags()).unwrap();
+        assert!(cmd.get("storageOnly").is_none());
+        assert_eq!(cmd["origins"], json!(["https://a.com"]));
+
+        let result = parse_command(&args("state save ./s.json --cookies-only --no-cookies"), &default_flags());
+        assert!(result.unwrap_err().format().contains("mutually exclusive"));
+    }
+
+    #[test]
+    fn test_state_scopes_exclusive() {
+        let result = parse_command(&args("state save ./s.json --cookies-only --storage-only"), &default_flags());
+        assert!(result.unwrap_err().format().contains("mutually exclusive"));
+    }
+
+    #[test]
+    fn test_state_load_with_scope() {
+        // The file is checked in main, not here: it may live on a remote daemon's machine
+        let cmd = parse_command(&args("state load ./missing.json --cookies-only --origin https://a.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "state_load");
+        assert_eq!(cmd["cookiesOnly"], true);
+        assert_eq!(cmd["origins"], json!(["https://a.com"]));
+    }
+
+    #[test]
+    fn test_state_file_valid() {
+        let dir = TempDir::new("state");
+        let path = dir.write("valid.json", r#"{"cookies":[],"origins":[]}"#).display().to_string();
+        assert!(validate_state_file(&path).is_ok());
+    }
+
+    #[test]
+    fn test_state_file_invalid_json() {
+        let dir = TempDir::new("state");
+        let path = dir.write("broken.json", "{not json").display().to_string();
+        let err = validate_state_file(&path).unwrap_err();
+        assert!(err.contains("not valid JSON"));
+        assert!(err.contains("broken.json"));
+    }
+
+    #[test]
+    fn test_state_file_missing_keys() {
+        let dir = TempDir::new("state");
+        let path = dir.write("wrong.json", r#"{"foo":1}"#).display().to_string();
+        let err = validate_state_file(&path).unwrap_err();
+        assert!(err.contains("wrong.json"));
+        assert!(err.contains("cookies"));
+    }
+
+    #[test]
+    fn test_state_file_missing() {
+        let err = validate_state_file("/nonexistent/state.json").unwrap_err();
+        assert!(err.contains("/nonexistent/state.json"));
+    }
+
+
+    // === History ===
+
+    #[test]
+    fn test_history_list() {
+        let cmd = parse_command(&args("history"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "history");
+    }
+
+    #[test]
+    fn test_history_back_n() {
+        let cmd = parse_command(&args("history back 3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "history_go");
+        assert_eq!(cmd["delta"], -3);
+    }
+
+    #[test]
+    fn test_history_forward_default_one() {
+        let cmd = parse_command(&args("history forward"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "history_go");
+        assert_eq!(cmd["delta"], 1);
+    }
+
+    #[test]
+    fn test_history_invalid_steps() {
+        assert!(parse_command(&args("history back 0"), &default_flags()).is_err());
+        assert!(parse_command(&args("history back x"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_history_unknown_subcommand() {
+        let result = parse_command(&args("history sideways"), &default_flags());
+        assert!(matches!(result.unwrap_err(), ParseError::UnknownSubcommand { .. }));
+    }
+
+
+    // === Eval ===
+
+    #[test]
+    fn test_eval_plain_not_awaited() {
+        let cmd = parse_command(&args("eval document.title"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "evaluate");
+        assert_eq!(cmd["script"], "document.title");
+        assert!(cmd.get("await").is_none());
+    }
+
+    #[test]
+    fn test_eval_await_flag() {
+        let cmd = parse_command(&args("eval --await navigator.clipboard.readText()"), &default_flags()).unwrap();
+        assert_eq!(cmd["await"], true);
+        assert_eq!(cmd["script"], "navigator.clipboard.readText()");
+    }
+
+    #[test]
+    fn test_eval_auto_await_and_opt_out() {
+        let cmd = parse_command(&args("eval await fetch('/x')"), &default_flags()).unwrap();
+        assert_eq!(cmd["await"], true);
+        let cmd = parse_command(&args("eval --no-await await fetch('/x')"), &default_flags()).unwrap();
+        assert!(cmd.get("await").is_none());
+    }
+
+    #[test]
+    fn test_looks_async() {
+        assert!(looks_async("await fetch('/api')"));
+        assert!(looks_async("new Promise(r => setTimeout(r, 100))"));
+        assert!(looks_async("fetch('/api').then(r => r.json())"));
+        assert!(!looks_async("document.title"));
+        assert!(!looks_async("window.awaitingUser"));
+    }
+
+
+    // === Upload ===
+
+    fn temp_upload_dir(name: &str) -> TempDir {
+        let dir = TempDir::new(&format!("upload-{}", name));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        for f in ["a.png", "b.png", "notes.txt"] {
+            dir.write(f, "x");
+        }
+        dir
+    }
+
+    #[test]
+    fn test_upload_explicit_files() {
+        let dir = temp_upload_dir("explicit");
+        let a = dir.join("a.png").to_string_lossy().to_string();
+        let cmd = parse_command(&args(&format!("upload #file {}", a)), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "upload");
+        assert_eq!(cmd["selector"], "#file");
+        assert_eq!(cmd["files"], json!([a]));
+    }
+
+    #[test]
+    fn test_upload_directory_expands_files() {
+        let dir = temp_upload_dir("dir");
+        let cmd = parse_command(&args(&format!("upload #file {}", dir.display())), &default_flags()).unwrap();
+        let files = cmd["files"].as_array().unwrap();
+        assert_eq!(files.len(), 3);
+        assert!(files[0].as_str().unwrap().ends_with("a.png"));
+        assert!(files.iter().all(|f| !f.as_str().unwrap().ends_with("nested")));
+    }
+
+    #[test]
+    fn test_upload_glob() {
+        let dir = temp_upload_dir("glob");
+        let cmd = parse_command(&args(&format!("upload #file --glob {}/*.png", dir.display())), &default_flags()).unwrap();
+        let files: Vec<&str> = cmd["files"].as_array().unwrap().iter().map(|f| f.as_str().unwrap()).collect();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with("a.png"));
+        assert!(files[1].ends_with("b.png"));
+    }
+
+    #[test]
+    fn test_upload_missing_file_errors() {
+        let result = parse_command(&args("upload #file /nonexistent/file.pdf"), &default_flags());
+        assert!(result.unwrap_err().format().contains("file not found: /nonexistent/file.pdf"));
+    }
+
+    #[test]
+    fn test_upload_glob_no_match_errors() {
+        let dir = temp_upload_dir("nomatch");
+        let result = parse_command(&args(&format!("upload #file --glob {}/*.gif", dir.display())), &default_flags());
+        assert!(result.unwrap_err().format().contains("no files match"));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.png", "a.png"));
+        assert!(wildcard_match("shot-??.png", "shot-01.png"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(!wildcard_match("*.png", "a.jpg"));
+        assert!(!wildcard_match("a?", "a"));
+    }
+
+
+    // === Dry Run ===
+
+    fn dry_run(line: &str, with_id: bool, compact: bool) -> String {
+        let cmd = parse_command(&args(line), &default_flags()).unwrap();
+        crate::output::format_dry_run(&cmd, with_id, compact)
+    }
+
+    #[test]
+    fn test_dry_run_click() {
+        assert_eq!(
+            dry_run("click #submit", false, false),
+            "{\n  \"action\": \"click\",\n  \"selector\": \"#submit\"\n}"
+        );
+    }
+
+    #[test]
+    fn test_dry_run_compact() {
+        assert_eq!(
+            dry_run("fill #email test@example.com", false, true),
+            r##"{"action":"fill","selector":"#email","value":"test@example.com"}"##
+        );
+        assert_eq!(
+            dry_run("open example.com", false, true),
+            r#"{"action":"navigate","url":"https://example.com"}"#
+        );
+        assert_eq!(
+            dry_run("snapshot -i -d 2", false, true),
+            r#"{"action":"snapshot","interactive":true,"maxDepth":2}"#
+        );
+    }
+
+    #[test]
+    fn test_dry_run_with_id() {
+        let out = dry_run("reload", true, true);
+        assert!(out.contains(r#""id":"#));
+        assert!(!dry_run("reload", false, true).contains(r#""id""#));
+    }
+
+
+    // === Mouse ===
+
+    #[test]
+    fn test_mouse_click() {
+        let cmd = parse_command(&args("mouse click 100 200"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "mouseclick");
+        assert_eq!(cmd["x"], 100);
+        assert_eq!(cmd["y"], 200);
+        assert_eq!(cmd["button"], "left");
+    }
+
+    #[test]
+    fn test_mouse_click_button() {
+        let cmd = parse_command(&args("mouse click 10 10 --button right"), &default_flags()).unwrap();
+        assert_eq!(cmd["button"], "right");
+    }
+
+    #[test]
+    fn test_mouse_click_invalid() {
+        assert!(parse_command(&args("mouse click 10"), &default_flags()).is_err());
+        assert!(parse_command(&args("mouse click 10 x"), &default_flags()).is_err());
+        assert!(parse_command(&args("mouse click 10 10 --button side"), &default_flags()).is_err());
+    }
+
+
+    #[test]
+    fn test_mouse_wheel_steps() {
+        let cmd = parse_command(&args("mouse wheel 1000 0 --steps 10"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "mousewheel");
+        assert_eq!(cmd["deltaY"], 1000);
+        assert_eq!(cmd["deltaX"], 0);
+        assert_eq!(cmd["steps"], 10);
+    }
+
+    #[test]
+    fn test_mouse_wheel_default_single_step() {
+        let cmd = parse_command(&args("mouse wheel -50"), &default_flags()).unwrap();
+        assert_eq!(cmd["deltaY"], -50);
+        assert_eq!(cmd["steps"], 1);
+    }
+
+    #[test]
+    fn test_mouse_wheel_invalid_steps() {
+        assert!(parse_command(&args("mouse wheel 100 --steps 0"), &default_flags()).is_err());
+        assert!(parse_command(&args("mouse wheel 100 --steps -2"), &default_flags()).is_err());
+        assert!(parse_command(&args("mouse wheel 100 --steps"), &default_flags()).is_err());
+    }
+
+
+    #[test]
+    fn test_start_user_data_dir() {
+        let mut flags = default_flags();
+        flags.user_data_dir = Some("/tmp/chrome-data".to_string());
+        let cmd = parse_command(&args("start"), &flags).unwrap();
+        assert_eq!(cmd["action"], "configure");
+        assert_eq!(cmd["userDataDir"], "/tmp/chrome-data");
+        assert!(parse_command(&args("start"), &default_flags()).unwrap().get("userDataDir").is_none());
+    }
+
+    #[test]
+    fn test_start_stealth_payload() {
+        // The forms the daemon's stealth schema accepts (src/protocol.ts)
+        let stealth = |level: Stealth| {
+            let flags = Flags { stealth: level, ..default_flags() };
+            parse_command(&args("start"), &flags).unwrap()["stealth"].clone()
+        };
+        assert_eq!(stealth(Stealth::Off), json!(false));
+        assert_eq!(stealth(Stealth::Full), json!({ "level": "full" }));
+        assert_eq!(stealth(Stealth::Basic), json!({ "level": "basic" }));
+        assert_eq!(
+            stealth(Stealth::Evasions(vec!["navigator.webdriver".to_string()])),
+            json!({ "evasions": ["navigator.webdriver"] })
+        );
+    }
 }
@@ -0,0 +1,467 @@
+use crate::flags::{GLOBAL_FLAGS, GLOBAL_FLAGS_WITH_VALUE, SHORT_GLOBAL_FLAGS_WITH_VALUE};
+use crate::output::{fail, ErrorCode};
+
+const BIN: &str = "z-agent-browser";
+
+/// Shells `completions <shell>` can generate a script for
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+/// One top-level command with the words that can follow it
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub subcommands: &'static [&'static str],
+    pub flags: &'static [&'static str],
+}
+
+const fn cmd(
+    name: &'static str,
+    subcommands: &'static [&'static str],
+    flags: &'static [&'static str],
+) -> CommandSpec {
+    CommandSpec { name, subcommands, flags }
+}
+
+/// Every command the CLI accepts; the single source for shell completions.
+pub const COMMANDS: &[CommandSpec] = &[
+    // Browser lifecycle
+    cmd("start", &[], &[]),
+    cmd("status", &[], &[]),
+    cmd("stop", &[], &[]),
+    cmd("close", &[], &[]),
+    cmd("ping", &[], &[]),
+    cmd("connect", &[], &["--discover"]),
+    // Core
+    cmd("open", &[], &["--wait-until", "--referer"]),
+    cmd("click", &[], &[]),
+    cmd("dblclick", &[], &[]),
+    cmd("type", &[], &["--clear"]),
+    cmd("fill", &[], &[]),
+    cmd("press", &[], &[]),
+    cmd("keydown", &[], &[]),
+    cmd("keyup", &[], &[]),
+    cmd("hover", &[], &[]),
+    cmd("focus", &[], &[]),
+    cmd("check", &[], &[]),
+    cmd("uncheck", &[], &[]),
+    cmd("select", &[], &[]),
+    cmd("drag", &[], &["--steps", "--hold"]),
+    cmd("upload", &[], &["--glob"]),
+    cmd("scroll", &["up", "down", "left", "right"], &[]),
+    cmd("scrollintoview", &[], &[]),
+    cmd("wait", &[], &["--text", "--url", "--load", "--fn", "--any", "--all", "--timeout"]),
+    cmd("screenshot", &[], &[]),
+    cmd("pdf", &[], &[]),
+    cmd("save", &[], &[]),
+    cmd("snapshot", &[], &["--interactive", "--compact", "--depth", "--selector", "--json-tree"]),
+    cmd("eval", &[], &["--await", "--no-await"]),
+    // Navigation
+    cmd("back", &[], &[]),
+    cmd("forward", &[], &[]),
+    cmd("reload", &[], &["--hard"]),
+    cmd("history", &["back", "forward"], &[]),
+    // Grouped commands
+    cmd("get", &["text", "html", "value", "attr", "url", "title", "count", "box", "article"], &["--html", "--max-chars", "--outer"]),
+    cmd("is", &["visible", "enabled", "checked"], &[]),
+    cmd("find-text", &[], &["--case-sensitive", "--regex", "--count-only"]),
+    cmd(
+        "find",
+        &["role", "text", "label", "placeholder", "alt", "title", "testid", "first", "last", "nth"],
+        &["--name", "--exact", "--nth", "--has-text", "--within", "--locate"],
+    ),
+    cmd("mouse", &["move", "click", "down", "up", "wheel"], &["--button", "--steps"]),
+    cmd(
+        "set",
+        &["viewport", "device", "geo", "offline", "headless", "headers", "credentials", "media"],
+        &["--dsf", "--mobile", "--allow-unknown"],
+    ),
+    cmd(
+        "network",
+        &["route", "unroute", "requests"],
+        &["--abort", "--body", "--continue", "--set-url", "--set-method", "--set-header", "--clear", "--filter"],
+    ),
+    cmd("fetch", &[], &["--method", "--data", "--header", "--include", "--raw", "--no-fail", "--output"]),
+    cmd("cookies", &["get", "set", "clear"], &["--url"]),
+    cmd("storage", &["local", "session"], &[]),
+    cmd("clipboard", &["read", "write"], &["--stdin"]),
+    cmd("tab", &["new", "list", "close"], &["--all", "--title"]),
+    cmd("window", &["new"], &[]),
+    cmd("frame", &["main"], &[]),
+    cmd("dialog", &["accept", "dismiss"], &[]),
+    // Debug
+    cmd("trace", &["start", "stop", "view"], &["--screenshots", "--snapshots", "--sources"]),
+    cmd(
+        "record",
+        &["start", "stop", "restart"],
+        &["--fps", "--size", "--no-preserve-state", "--gif", "--frames"],
+    ),
+    cmd("console", &[], &["--clear", "--level"]),
+    cmd("errors", &[], &["--clear", "--short", "--stack"]),
+    cmd("highlight", &[], &["--color", "--duration", "--clear"]),
+    cmd("state", &["save", "load"], &["--cookies-only", "--storage-only", "--origin", "--no-cookies", "--include-cookies"]),
+    // Local (no daemon)
+    cmd("form", &["fill"], &[]),
+    cmd("assert", &["text", "visible", "url", "title"], &["--contains", "--regex", "--timeout"]),
+    cmd("session", &["current", "list", "info", "kill", "clean"], &["--check", "--filter"]),
+    cmd("daemon", &["logs", "restart"], &["--follow", "--lines"]),
+    cmd("restart", &[], &[]),
+    cmd("repl", &[], &[]),
+    cmd("run", &[], &["--continue-on-error", "--var"]),
+    cmd("script", &["export"], &["--format"]),
+    cmd("pipe", &[], &["--strict"]),
+    cmd("install", &["chromium", "firefox", "webkit"], &["--with-deps", "--check", "--from", "--download-only"]),
+    cmd("config", &["show"], &[]),
+    cmd("completions", SHELLS, &[]),
+];
+
+pub fn run_completions(args: &[String], json_mode: bool) {
+    let shell = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    match generate(shell) {
+        Some(script) => print!("{}", script),
+        None => {
+            let msg = if shell.is_empty() {
+                format!("Missing shell\nUsage: {} completions <{}>", BIN, SHELLS.join("|"))
+            } else {
+                format!("Unknown shell: {}\nSupported shells: {}", shell, SHELLS.join(", "))
+            };
+            fail(ErrorCode::ParseError, &msg, json_mode);
+        }
+    }
+}
+
+/// Completion script for `shell`, or `None` if the shell isn't supported
+pub fn generate(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash()),
+        "zsh" => Some(zsh()),
+        "fish" => Some(fish()),
+        "powershell" | "pwsh" => Some(powershell()),
+        _ => None,
+    }
+}
+
+fn command_names() -> String {
+    COMMANDS.iter().map(|c| c.name).collect::<Vec<_>>().join(" ")
+}
+
+fn global_flags() -> String {
+    GLOBAL_FLAGS
+        .iter()
+        .chain(GLOBAL_FLAGS_WITH_VALUE)
+        // Taken out by main before the others are parsed
+        .chain(&["--color"])
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `case` arms mapping a command to its words, e.g. `        get) words="text html" ;;`
+fn case_arms(words: impl Fn(&CommandSpec) -> &'static [&'static str], arm: &str) -> String {
+    COMMANDS
+        .iter()
+        .filter(|c| !words(c).is_empty())
+        .map(|c| arm.replace("{cmd}", c.name).replace("{words}", &words(c).join(" ")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn bash() -> String {
+    format!(
+        r#"# bash completion for {bin}
+# eval "$({bin} completions bash)"
+_z_agent_browser() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local cmd="" cmd_index=0 i words=""
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${{COMP_WORDS[i]}}" in
+            {value_flags}) ((i++)) ;;
+            -*) ;;
+            *) cmd="${{COMP_WORDS[i]}}"; cmd_index=$i; break ;;
+        esac
+    done
+
+    if [[ "$cur" == -* ]]; then
+        case "$cmd" in
+{flag_arms}
+        esac
+        COMPREPLY=($(compgen -W "{globals} $words" -- "$cur"))
+        return
+    fi
+
+    if [[ -z "$cmd" ]]; then
+        COMPREPLY=($(compgen -W "{commands}" -- "$cur"))
+        return
+    fi
+
+    if ((COMP_CWORD == cmd_index + 1)); then
+        case "$cmd" in
+{sub_arms}
+        esac
+        if [[ -n "$words" ]]; then
+            COMPREPLY=($(compgen -W "$words" -- "$cur"))
+            return
+        fi
+    fi
+
+    COMPREPLY=($(compgen -f -- "$cur"))
+}}
+complete -F _z_agent_browser {bin}
+"#,
+        bin = BIN,
+        value_flags = GLOBAL_FLAGS_WITH_VALUE.join("|"),
+        globals = global_flags(),
+        commands = command_names(),
+        flag_arms = case_arms(|c| c.flags, "            {cmd}) words=\"{words}\" ;;"),
+        sub_arms = case_arms(|c| c.subcommands, "            {cmd}) words=\"{words}\" ;;"),
+    )
+}
+
+fn zsh() -> String {
+    format!(
+        r#"#compdef {bin}
+# source <({bin} completions zsh)
+_z_agent_browser() {{
+    local cmd="" cmd_index=0 i
+    local -a words_for
+    for ((i = 2; i < CURRENT; i++)); do
+        case "${{words[i]}}" in
+            ({value_flags}) ((i++)) ;;
+            (-*) ;;
+            (*) cmd="${{words[i]}}"; cmd_index=$i; break ;;
+        esac
+    done
+
+    if [[ "$PREFIX" == -* ]]; then
+        case "$cmd" in
+{flag_arms}
+        esac
+        compadd -- {globals} $words_for
+        return
+    fi
+
+    if [[ -z "$cmd" ]]; then
+        compadd -- {commands}
+        return
+    fi
+
+    if ((CURRENT == cmd_index + 1)); then
+        case "$cmd" in
+{sub_arms}
+        esac
+        if ((${{#words_for}})); then
+            compadd -- $words_for
+            return
+        fi
+    fi
+
+    _files
+}}
+compdef _z_agent_browser {bin}
+"#,
+        bin = BIN,
+        value_flags = GLOBAL_FLAGS_WITH_VALUE.join("|"),
+        globals = global_flags(),
+        commands = command_names(),
+        flag_arms = case_arms(|c| c.flags, "            ({cmd}) words_for=({words}) ;;"),
+        sub_arms = case_arms(|c| c.subcommands, "            ({cmd}) words_for=({words}) ;;"),
+    )
+}
+
+/// fish `complete` option for a flag: `-l name` or `-s o`, plus `-r` if it takes a value
+fn fish_flag(flag: &str, takes_value: bool) -> String {
+    let opt = match flag.strip_prefix("--") {
+        Some(long) => format!("-l {}", long),
+        None => format!("-s {}", flag.trim_start_matches('-')),
+    };
+    if takes_value {
+        format!("{} -r", opt)
+    } else {
+        opt
+    }
+}
+
+fn fish() -> String {
+    let mut lines = vec![
+        format!("# fish completion for {}", BIN),
+        format!("# {} completions fish | source", BIN),
+        format!("complete -c {} -f", BIN),
+        format!("complete -c {} -n __fish_use_subcommand -a \"{}\"", BIN, command_names()),
+    ];
+    for flag in GLOBAL_FLAGS {
+        lines.push(format!("complete -c {} {}", BIN, fish_flag(flag, false)));
+    }
+    for flag in GLOBAL_FLAGS_WITH_VALUE.iter().chain(SHORT_GLOBAL_FLAGS_WITH_VALUE) {
+        lines.push(format!("complete -c {} {}", BIN, fish_flag(flag, true)));
+    }
+    for c in COMMANDS {
+        if !c.subcommands.is_empty() {
+            lines.push(format!(
+                "complete -c {} -n \"__fish_seen_subcommand_from {}\" -a \"{}\"",
+                BIN,
+                c.name,
+                c.subcommands.join(" ")
+            ));
+        }
+        for flag in c.flags {
+            lines.push(format!(
+                "complete -c {} -n \"__fish_seen_subcommand_from {}\" {}",
+                BIN,
+                c.name,
+                fish_flag(flag, false)
+            ));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+fn powershell_list(words: &[&str]) -> String {
+    let quoted: Vec<String> = words.iter().map(|w| format!("'{}'", w)).collect();
+    format!("@({})", quoted.join(", "))
+}
+
+fn powershell() -> String {
+    let table = COMMANDS
+        .iter()
+        .map(|c| {
+            format!(
+                "        '{}' = @{{ Sub = {}; Flags = {} }}",
+                c.name,
+                powershell_list(c.subcommands),
+                powershell_list(c.flags)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"# PowerShell completion for {bin}
+# {bin} completions powershell | Out-String | Invoke-Expression
+Register-ArgumentCompleter -Native -CommandName '{bin}' -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $commands = @{{
+{table}
+    }}
+    $globalFlags = {globals}
+    $valueFlags = {value_flags}
+
+    $elements = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object {{ $_.ToString() }})
+    if ($wordToComplete -and $elements.Count -gt 0) {{
+        $elements = @($elements | Select-Object -First ($elements.Count - 1))
+    }}
+    $cmd = $null
+    $afterCmd = 0
+    for ($i = 0; $i -lt $elements.Count; $i++) {{
+        $word = $elements[$i]
+        if ($cmd) {{ $afterCmd++ }}
+        elseif ($valueFlags -contains $word) {{ $i++ }}
+        elseif (-not $word.StartsWith('-')) {{ $cmd = $word }}
+    }}
+
+    if ($wordToComplete.StartsWith('-')) {{
+        $candidates = $globalFlags
+        if ($cmd -and $commands.ContainsKey($cmd)) {{ $candidates += $commands[$cmd].Flags }}
+    }} elseif (-not $cmd) {{
+        $candidates = $commands.Keys | Sort-Object
+    }} elseif ($afterCmd -eq 0 -and $commands.ContainsKey($cmd)) {{
+        $candidates = $commands[$cmd].Sub
+    }} else {{
+        return
+    }}
+
+    $candidates | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }}
+}}
+"#,
+        bin = BIN,
+        table = table,
+        globals = powershell_list(&GLOBAL_FLAGS.iter().chain(GLOBAL_FLAGS_WITH_VALUE).copied().collect::<Vec<_>>()),
+        value_flags = powershell_list(GLOBAL_FLAGS_WITH_VALUE),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{parse_command, ParseError};
+    use crate::flags::parse_flags;
+    use crate::output::HELP;
+
+    fn spec(name: &str) -> &'static CommandSpec {
+        COMMANDS.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_bash_script_contents() {
+        let script = generate("bash").unwrap();
+        assert!(script.contains("complete -F _z_agent_browser z-agent-browser"));
+        assert!(script.contains("snapshot"));
+        assert!(script.contains("get) words=\"text html value attr url title count box article\""));
+        assert!(script.contains("--json"));
+        assert!(script.contains("--session|"));
+    }
+
+    #[test]
+    fn test_all_shells_generate() {
+        for shell in SHELLS {
+            let script = generate(shell).unwrap();
+            assert!(script.contains("z-agent-browser"), "{}", shell);
+            assert!(script.contains("viewport"), "{}", shell);
+        }
+        assert!(generate("tcsh").is_none());
+    }
+
+    #[test]
+    fn test_fish_flags() {
+        let script = generate("fish").unwrap();
+        assert!(script.contains("complete -c z-agent-browser -l session -r"));
+        assert!(script.contains("complete -c z-agent-browser -s o -r"));
+        assert!(script.contains("__fish_seen_subcommand_from snapshot\" -l json-tree"));
+    }
+
+    /// Command names listed in the global help: the first word of each entry
+    /// line ("  open <url>   Navigate to URL") and grouped commands from
+    /// headers ("Get Info:  z-agent-browser get <what> [selector]").
+    fn help_commands() -> Vec<String> {
+        let mut names = Vec::new();
+        for line in HELP.lines().skip_while(|l| !l.starts_with("Usage:")).skip(1) {
+            if line.starts_with("Snapshot Options:") {
+                break;
+            }
+            if let Some((_, rest)) = line.split_once("z-agent-browser ") {
+                names.push(rest.split_whitespace().next().unwrap().to_string());
+            } else if let Some(entry) = line.strip_prefix("  ") {
+                let has_description = entry.trim_end().contains("  ");
+                let word = entry.split_whitespace().next().unwrap_or("");
+                if has_description && word.chars().all(|c| c.is_ascii_lowercase()) {
+                    names.push(word.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    #[test]
+    fn test_every_help_command_in_table() {
+        let names = help_commands();
+        assert!(names.len() > 40, "{:?}", names);
+        for name in names {
+            assert!(COMMANDS.iter().any(|c| c.name == name), "{} missing from COMMANDS", name);
+        }
+    }
+
+    #[test]
+    fn test_table_subcommands_are_valid() {
+        let flags = parse_flags(&[]);
+        for name in ["get", "is", "find", "set", "network", "storage", "tab", "trace", "record", "state", "mouse"] {
+            for sub in spec(name).subcommands {
+                let args: Vec<String> = vec![name.to_string(), sub.to_string()];
+                let result = parse_command(&args, &flags);
+                assert!(
+                    !matches!(result, Err(ParseError::UnknownSubcommand { .. })),
+                    "{} {} rejected",
+                    name,
+                    sub
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::flags::{Flags, Stealth};
+use crate::output::{fail, ErrorCode};
+
+/// Per-session defaults from `[sessions.<name>]` in the config file.
+/// Keys mirror the global CLI flags (`user-agent`, `executable-path`, ...).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SessionConfig {
+    pub json: Option<bool>,
+    pub headed: Option<bool>,
+    pub debug: Option<bool>,
+    pub quiet: Option<bool>,
+    pub headers: Option<String>,
+    pub executable_path: Option<String>,
+    pub cdp: Option<String>,
+    pub extensions: Option<Vec<String>>,
+    pub proxy: Option<String>,
+    pub proxy_file: Option<String>,
+    pub proxy_strategy: Option<String>,
+    pub proxy_bypass: Option<String>,
+    pub profile: Option<String>,
+    pub user_data_dir: Option<String>,
+    pub ignore_https_errors: Option<bool>,
+    pub session_name: Option<String>,
+    pub state: Option<String>,
+    pub persist: Option<bool>,
+    pub args: Option<String>,
+    pub user_agent: Option<String>,
+    pub stealth: Option<Stealth>,
+    pub backend: Option<String>,
+    pub browser: Option<String>,
+    pub retry: Option<u32>,
+    pub retry_delay: Option<u64>,
+    #[serde(alias = "idle-timeout")]
+    pub session_timeout: Option<u64>,
+    pub connect_timeout: Option<u64>,
+    pub remote: Option<String>,
+    pub remote_token: Option<String>,
+    pub listen: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    sessions: HashMap<String, SessionConfig>,
+}
+
+/// `AGENT_BROWSER_CONFIG`, else `~/.config/agent-browser/config.toml`
+pub fn config_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("AGENT_BROWSER_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    dirs::home_dir().map(|home| home.join(".config").join("agent-browser").join("config.toml"))
+}
+
+/// Load the defaults for `session`. A missing file (or a file without that
+/// session) yields empty defaults; a malformed file is an error naming the
+/// file and line.
+pub fn load_session_config(path: &Path, session: &str) -> Result<SessionConfig, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(SessionConfig::default()),
+        Err(e) => return Err(format!("Failed to read config {}: {}", path.display(), e)),
+    };
+    let mut config = parse_config(&content).map_err(|e| format!("{}:{}", path.display(), e))?;
+    Ok(config.sessions.remove(session).unwrap_or_default())
+}
+
+/// Parse config text; errors are formatted as `<line>: <message>`.
+fn parse_config(content: &str) -> Result<ConfigFile, String> {
+    toml::from_str(content).map_err(|e| {
+        let line = e
+            .span()
+            .map(|span| content[..span.start].matches('\n').count() + 1)
+            .unwrap_or(1);
+        format!("{}: {}", line, e.message())
+    })
+}
+
+/// Effective flags for `config show`
+pub fn flags_to_json(flags: &Flags) -> Value {
+    json!({
+        "session": flags.session,
+        "json": flags.json,
+        "headed": flags.headed,
+        "debug": flags.debug,
+        "quiet": flags.quiet,
+        "headers": flags.headers,
+        "executable-path": flags.executable_path,
+        "cdp": flags.cdp,
+        "extensions": flags.extensions,
+        "proxy": flags.proxy,
+        "proxy-file": flags.proxy_file,
+        "proxy-strategy": flags.proxy_strategy,
+        "proxy-bypass": flags.proxy_bypass,
+        "profile": flags.profile,
+        "user-data-dir": flags.user_data_dir,
+        "ignore-https-errors": flags.ignore_https_errors,
+        "session-name": flags.session_name,
+        "state": flags.state,
+        "persist": flags.persist,
+        "args": flags.args,
+        "user-agent": flags.user_agent,
+        "stealth": flags.stealth,
+        "backend": flags.backend,
+        "browser": flags.browser,
+        "retry": flags.retry,
+        "retry-delay": flags.retry_delay,
+        "session-timeout": flags.session_timeout,
+        "connect-timeout": flags.connect_timeout,
+        "remote": flags.remote,
+        // Never echo the secret itself
+        "remote-token": flags.remote_token.as_ref().map(|_| "(set)"),
+        "listen": flags.listen,
+    })
+}
+
+pub fn run_config(args: &[String], flags: &Flags) {
+    match args.get(1).map(|s| s.as_str()) {
+        Some("show") | None => {
+            let path = config_path()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default();
+            let effective = flags_to_json(flags);
+            if flags.json {
+                println!(
+                    "{}",
+                    json!({ "success": true, "data": { "path": path, "flags": effective } })
+                );
+                return;
+            }
+            println!("# config: {}", path);
+            if let Some(map) = effective.as_object() {
+                for (key, value) in map {
+                    let shown = match value {
+                        Value::Null => continue,
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    println!("{} = {}", key, shown);
+                }
+            }
+        }
+        Some(sub) => fail(
+            ErrorCode::ParseError,
+            &format!("Unknown subcommand: {}\nValid options: show", sub),
+            flags.json,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_parse_session_table() {
+        let config = parse_config(
+            r#"
+[sessions.scraper]
+stealth = true
+proxy = "http://proxy:8080"
+user-agent = "Bot/1.0"
+extensions = ["./ext"]
+
+[sessions.shop]
+stealth = "basic"
+"#,
+        )
+        .unwrap();
+        let scraper = &config.sessions["scraper"];
+        assert_eq!(scraper.stealth, Some(Stealth::Full));
+        assert_eq!(config.sessions["shop"].stealth, Some(Stealth::Basic));
+        assert_eq!(scraper.proxy.as_deref(), Some("http://proxy:8080"));
+        assert_eq!(scraper.user_agent.as_deref(), Some("Bot/1.0"));
+        assert_eq!(scraper.extensions, Some(vec!["./ext".to_string()]));
+        assert_eq!(scraper.headed, None);
+    }
+
+    #[test]
+    fn test_missing_file_is_empty() {
+        let config = load_session_config(Path::new("/nonexistent/config.toml"), "default").unwrap();
+        assert!(config.stealth.is_none());
+    }
+
+    #[test]
+    fn test_malformed_toml_names_file_and_line() {
+        let dir = TempDir::new("config");
+        let path = dir.write("config.toml", "[sessions.a]\nstealth = true\nproxy = \n");
+        let err = load_session_config(&path, "a").unwrap_err();
+        assert!(err.starts_with(&format!("{}:3:", path.display())), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_key_is_error() {
+        let err = parse_config("[sessions.a]\nstelth = true\n").unwrap_err();
+        assert!(err.starts_with("2:"), "{}", err);
+        assert!(err.contains("stelth"));
+    }
+}
@@ -1,62 +1,348 @@
 use std::env;
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+
+use crate::color::ColorMode;
+use crate::config::{config_path, load_session_config, SessionConfig};
+use crate::proxy::Strategy;
+
 pub struct Flags {
     pub json: bool,
+    pub jsonl: bool,
     pub full: bool,
     pub headed: bool,
     pub debug: bool,
+    pub quiet: bool,
     pub session: String,
     pub headers: Option<String>,
     pub executable_path: Option<String>,
     pub cdp: Option<String>,
     pub extensions: Vec<String>,
+    /// One proxy, or a comma list to rotate through
     pub proxy: Option<String>,
+    /// File of proxies to rotate through, one per line (`--proxy-file`)
+    pub proxy_file: Option<String>,
+    /// `round-robin` (default) or `random` (`--proxy-strategy`)
+    pub proxy_strategy: Option<String>,
+    /// Hosts that skip the proxy, comma separated (`--proxy-bypass`)
+    pub proxy_bypass: Option<String>,
     pub profile: Option<String>,
+    pub user_data_dir: Option<String>,
     pub ignore_https_errors: bool,
     pub session_name: Option<String>,
     pub state: Option<String>,
     pub persist: bool,
     pub args: Option<String>,
     pub user_agent: Option<String>,
-    pub stealth: bool,
+    pub stealth: Stealth,
     pub backend: Option<String>,
+    /// Browser engine: chromium (default), firefox or webkit (`--browser`)
+    pub browser: Option<String>,
+    pub no_daemon: bool,
+    pub dry_run: bool,
+    pub with_id: bool,
+    /// Install missing system libraries when the browser fails to launch
+    /// for lack of them (`--fix`)
+    pub fix: bool,
+    /// Print how long the command took (`--timing`)
+    pub timing: bool,
+    /// Append each command's duration to the session's timings file
+    /// (`--timing-log`)
+    pub timing_log: bool,
+    /// Append each successful command to this script file (`--record-script`)
+    pub record_script: Option<String>,
+    /// Record what `fill`/`type` enter as `${NAME}` placeholders (`--redact`)
+    pub redact: bool,
+    pub output: Option<String>,
+    /// Extra attempts for retryable failures (`--retry`)
+    pub retry: u32,
+    /// Pause before the first retry, doubled for each one after (`--retry-delay`)
+    pub retry_delay: u64,
+    /// Seconds without commands before the daemon exits; `None` = never
+    pub session_timeout: Option<u64>,
+    /// Milliseconds to wait for a daemon response (`--connect-timeout`)
+    pub connect_timeout: Option<u64>,
+    /// `host:port` of a daemon on another machine (`--remote`)
+    pub remote: Option<String>,
+    /// Shared secret sent with every command to a remote daemon
+    pub remote_token: Option<String>,
+    /// `host:port` a newly started daemon also accepts TCP clients on (`--listen`)
+    pub listen: Option<String>,
+}
+
+/// `--retry-delay` when not given, in milliseconds
+pub const DEFAULT_RETRY_DELAY_MS: u64 = 500;
+
+/// Evasions of puppeteer-extra-plugin-stealth that can be picked one by one
+pub const STEALTH_EVASIONS: &[&str] = &[
+    "chrome.app", "chrome.csi", "chrome.loadTimes", "chrome.runtime", "defaultArgs", "iframe.contentWindow",
+    "media.codecs", "navigator.hardwareConcurrency", "navigator.languages", "navigator.permissions",
+    "navigator.plugins", "navigator.webdriver", "sourceurl", "user-agent-override", "webgl.vendor",
+    "window.outerdimensions",
+];
+
+/// Anti-detection patching (`--stealth [basic|full|<evasion,...>]`). Bare
+/// `--stealth` is the full preset.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Stealth {
+    #[default]
+    Off,
+    /// The evasions that rarely break pages
+    Basic,
+    /// Every evasion
+    Full,
+    /// Only the listed evasions
+    Evasions(Vec<String>),
+}
+
+impl Stealth {
+    /// Parse a level or comma list of evasions. `1`/`true` and `0`/`false`
+    /// are accepted for the boolean `AGENT_BROWSER_STEALTH` and config values.
+    pub fn parse(value: &str) -> Result<Stealth, String> {
+        match value {
+            "full" | "1" | "true" => return Ok(Stealth::Full),
+            "basic" => return Ok(Stealth::Basic),
+            "off" | "0" | "false" => return Ok(Stealth::Off),
+            _ => {}
+        }
+        let evasions: Vec<String> = value.split(',').map(|e| e.trim().to_string()).filter(|e| !e.is_empty()).collect();
+        match evasions.iter().find(|e| !STEALTH_EVASIONS.contains(&e.as_str())) {
+            Some(unknown) => Err(format!(
+                "--stealth: unknown evasion '{}' (expected basic, full or a comma list of: {})",
+                unknown,
+                STEALTH_EVASIONS.join(", ")
+            )),
+            None if evasions.is_empty() => Err("--stealth: empty evasion list".to_string()),
+            None => Ok(Stealth::Evasions(evasions)),
+        }
+    }
+
+    /// The stealth setting a daemon reports: `true` (older daemons) or the
+    /// object sent in `launch`/`configure`
+    pub fn from_value(value: &Value) -> Stealth {
+        if let Some(on) = value.as_bool() {
+            return if on { Stealth::Full } else { Stealth::Off };
+        }
+        if let Some(list) = value.get("evasions").and_then(|v| v.as_array()) {
+            return Stealth::Evasions(list.iter().filter_map(|v| v.as_str().map(String::from)).collect());
+        }
+        match value.get("level").and_then(|v| v.as_str()) {
+            Some("basic") => Stealth::Basic,
+            Some(_) => Stealth::Full,
+            None => Stealth::Off,
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        *self != Stealth::Off
+    }
+
+    pub fn is_off(&self) -> bool {
+        !self.is_on()
+    }
+
+    /// `AGENT_BROWSER_STEALTH` for a new daemon; the full preset stays `1`
+    pub fn env_value(&self) -> Option<String> {
+        match self {
+            Stealth::Off => None,
+            Stealth::Basic => Some("basic".to_string()),
+            Stealth::Full => Some("1".to_string()),
+            Stealth::Evasions(list) => Some(list.join(",")),
+        }
+    }
+}
+
+/// `false` when off, otherwise `{"level": ...}` or `{"evasions": [...]}`
+impl Serialize for Stealth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Stealth::Off => json!(false),
+            Stealth::Basic => json!({ "level": "basic" }),
+            Stealth::Full => json!({ "level": "full" }),
+            Stealth::Evasions(list) => json!({ "evasions": list }),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Config files take `stealth = true` or `stealth = "basic"`
+impl<'de> Deserialize<'de> for Stealth {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Bool(bool),
+            Level(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Bool(on) => Ok(if on { Stealth::Full } else { Stealth::Off }),
+            Raw::Level(value) => Stealth::parse(&value).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Whether the word after `--stealth` is its value rather than the command
+fn is_stealth_value(arg: &str) -> bool {
+    matches!(arg, "basic" | "full" | "off") || arg.contains(',') || STEALTH_EVASIONS.contains(&arg)
+}
+
+/// Resolve flags for this invocation, applying per-session defaults from the
+/// config file. Precedence: CLI flags > env vars > config file > built-in.
+pub fn load_flags(args: &[String]) -> Result<Flags, String> {
+    check_flag_values(args)?;
+    let config = match config_path() {
+        Some(path) => load_session_config(&path, &session_from_args(args))?,
+        None => SessionConfig::default(),
+    };
+    Ok(parse_flags_with_config(args, &config, &|name| env::var(name).ok()))
 }
 
+/// Numeric global flags must have a value that parses
+fn check_flag_values(args: &[String]) -> Result<(), String> {
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--stealth" {
+            if let Some(value) = args.get(i + 1).filter(|v| is_stealth_value(v)) {
+                Stealth::parse(value)?;
+            }
+            continue;
+        }
+        if arg == "--browser" {
+            check_browser(args.get(i + 1).map(|s| s.as_str()).unwrap_or(""))?;
+            continue;
+        }
+        if arg == "--proxy-strategy" {
+            Strategy::parse(Some(args.get(i + 1).map(|s| s.as_str()).unwrap_or("")))?;
+            continue;
+        }
+        if !matches!(arg.as_str(), "--retry" | "--retry-delay" | "--session-timeout" | "--idle-timeout" | "--connect-timeout") {
+            continue;
+        }
+        // Skip a flag name that is itself the value of the previous flag
+        if i > 0 && GLOBAL_FLAGS_WITH_VALUE.contains(&args[i - 1].as_str()) {
+            continue;
+        }
+        let value = args.get(i + 1).map(|s| s.as_str()).unwrap_or("");
+        let valid = match arg.as_str() {
+            "--retry" => value.parse::<u32>().is_ok(),
+            _ => value.parse::<u64>().is_ok(),
+        };
+        if !valid {
+            return Err(format!("{} expects a non-negative whole number, got '{}'", arg, value));
+        }
+    }
+    Ok(())
+}
+
+/// Engines Playwright can install and launch
+pub const BROWSERS: &[&str] = &["chromium", "firefox", "webkit"];
+
+/// Reject a `--browser` / `install` name that isn't in [`BROWSERS`]
+pub fn check_browser(name: &str) -> Result<(), String> {
+    if BROWSERS.contains(&name) {
+        Ok(())
+    } else {
+        Err(format!("Unknown browser '{}'. Valid options: {}", name, BROWSERS.join(", ")))
+    }
+}
+
+/// Session selected by `--session` or `AGENT_BROWSER_SESSION`, used to pick
+/// the config table before the rest of the flags are parsed.
+fn session_from_args(args: &[String]) -> String {
+    args.iter()
+        .position(|a| a == "--session")
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+        .or_else(|| env::var("AGENT_BROWSER_SESSION").ok())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// CLI and env only, without reading the config file
+#[cfg(test)]
 pub fn parse_flags(args: &[String]) -> Flags {
-    let extensions_env = env::var("AGENT_BROWSER_EXTENSIONS")
-        .ok()
+    parse_flags_with_config(args, &SessionConfig::default(), &|name| env::var(name).ok())
+}
+
+/// `var` looks up environment variables, so tests don't have to change the
+/// process environment other tests read concurrently
+fn parse_flags_with_config(args: &[String], config: &SessionConfig, var: &dyn Fn(&str) -> Option<String>) -> Flags {
+    let extensions_env = var("AGENT_BROWSER_EXTENSIONS")
         .map(|s| s.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect::<Vec<_>>())
+        .or_else(|| config.extensions.clone())
         .unwrap_or_default();
+    let env_or = |name: &str, fallback: &Option<String>| var(name).or_else(|| fallback.clone());
 
     let mut flags = Flags {
-        json: false,
+        json: config.json.unwrap_or(false),
+        jsonl: false,
         full: false,
-        headed: env::var("AGENT_BROWSER_HEADED").map(|v| v == "1" || v == "true").unwrap_or(false),
-        debug: false,
-        session: env::var("AGENT_BROWSER_SESSION").unwrap_or_else(|_| "default".to_string()),
-        headers: None,
-        executable_path: env::var("AGENT_BROWSER_EXECUTABLE_PATH").ok(),
-        cdp: None,
+        headed: var("AGENT_BROWSER_HEADED").map(|v| v == "1" || v == "true").or(config.headed).unwrap_or(false),
+        debug: config.debug.unwrap_or(false),
+        quiet: config.quiet.unwrap_or(false),
+        session: var("AGENT_BROWSER_SESSION").unwrap_or_else(|| "default".to_string()),
+        headers: config.headers.clone(),
+        executable_path: env_or("AGENT_BROWSER_EXECUTABLE_PATH", &config.executable_path),
+        cdp: config.cdp.clone(),
         extensions: extensions_env,
-        proxy: None,
-        profile: env::var("AGENT_BROWSER_PROFILE").ok(),
-        ignore_https_errors: false,
-        session_name: env::var("AGENT_BROWSER_SESSION_NAME").ok(),
-        state: env::var("AGENT_BROWSER_STATE").ok(),
-        persist: env::var("AGENT_BROWSER_PERSIST").map(|v| v == "1").unwrap_or(false),
-        args: env::var("AGENT_BROWSER_ARGS").ok(),
-        user_agent: env::var("AGENT_BROWSER_USER_AGENT").ok(),
-        stealth: env::var("AGENT_BROWSER_STEALTH").map(|v| v == "1" || v == "true").unwrap_or(false),
-        backend: env::var("AGENT_BROWSER_BACKEND").ok(),
+        proxy: config.proxy.clone(),
+        proxy_file: config.proxy_file.clone(),
+        proxy_strategy: config.proxy_strategy.clone(),
+        proxy_bypass: env_or("AGENT_BROWSER_PROXY_BYPASS", &config.proxy_bypass),
+        profile: env_or("AGENT_BROWSER_PROFILE", &config.profile),
+        user_data_dir: config.user_data_dir.clone(),
+        ignore_https_errors: config.ignore_https_errors.unwrap_or(false),
+        session_name: env_or("AGENT_BROWSER_SESSION_NAME", &config.session_name),
+        state: env_or("AGENT_BROWSER_STATE", &config.state),
+        persist: var("AGENT_BROWSER_PERSIST").map(|v| v == "1").or(config.persist).unwrap_or(false),
+        args: env_or("AGENT_BROWSER_ARGS", &config.args),
+        user_agent: env_or("AGENT_BROWSER_USER_AGENT", &config.user_agent),
+        stealth: var("AGENT_BROWSER_STEALTH")
+            .map(|v| Stealth::parse(&v).unwrap_or_default())
+            .or_else(|| config.stealth.clone())
+            .unwrap_or_default(),
+        backend: env_or("AGENT_BROWSER_BACKEND", &config.backend),
+        browser: env_or("AGENT_BROWSER_BROWSER", &config.browser),
+        no_daemon: false,
+        dry_run: false,
+        with_id: false,
+        fix: false,
+        timing: false,
+        timing_log: false,
+        record_script: var("AGENT_BROWSER_RECORD_SCRIPT"),
+        redact: false,
+        output: None,
+        retry: config.retry.unwrap_or(0),
+        retry_delay: config.retry_delay.unwrap_or(DEFAULT_RETRY_DELAY_MS),
+        session_timeout: var("AGENT_BROWSER_IDLE_TIMEOUT")
+            .and_then(|secs| secs.parse().ok())
+            .or(config.session_timeout)
+            .filter(|secs| *secs > 0),
+        connect_timeout: var("AGENT_BROWSER_TIMEOUT").and_then(|ms| ms.parse().ok()).or(config.connect_timeout),
+        remote: env_or("AGENT_BROWSER_REMOTE", &config.remote),
+        remote_token: env_or("AGENT_BROWSER_REMOTE_TOKEN", &config.remote_token),
+        listen: config.listen.clone(),
     };
 
+    // -f, -p and -o after the command word belong to the command, and
+    // nothing after `--` is a global flag
+    let command_at = command_index(args);
     let mut i = 0;
     while i < args.len() {
         match args[i].as_str() {
+            "--" => break,
             "--json" => flags.json = true,
-            "--full" | "-f" => flags.full = true,
+            "--jsonl" => {
+                flags.json = true;
+                flags.jsonl = true;
+            }
+            "--full" => flags.full = true,
+            "-f" if i < command_at => flags.full = true,
             "--headed" => flags.headed = true,
             "--debug" => flags.debug = true,
+            "--quiet" => flags.quiet = true,
             "--session" => {
                 if let Some(s) = args.get(i + 1) {
                     flags.session = s.clone();
@@ -93,12 +379,36 @@ pub fn parse_flags(args: &[String]) -> Flags {
                     i += 1;
                 }
             }
+            "--proxy-file" => {
+                if let Some(p) = args.get(i + 1) {
+                    flags.proxy_file = Some(p.clone());
+                    i += 1;
+                }
+            }
+            "--proxy-strategy" => {
+                if let Some(s) = args.get(i + 1) {
+                    flags.proxy_strategy = Some(s.clone());
+                    i += 1;
+                }
+            }
+            "--proxy-bypass" => {
+                if let Some(b) = args.get(i + 1) {
+                    flags.proxy_bypass = Some(b.clone());
+                    i += 1;
+                }
+            }
             "--profile" => {
                 if let Some(p) = args.get(i + 1) {
                     flags.profile = Some(p.clone());
                     i += 1;
                 }
             }
+            "--user-data-dir" => {
+                if let Some(d) = args.get(i + 1) {
+                    flags.user_data_dir = Some(d.clone());
+                    i += 1;
+                }
+            }
             "--ignore-https-errors" => flags.ignore_https_errors = true,
             "--session-name" => {
                 if let Some(s) = args.get(i + 1) {
@@ -112,7 +422,8 @@ pub fn parse_flags(args: &[String]) -> Flags {
                     i += 1;
                 }
             }
-            "--persist" | "-p" => flags.persist = true,
+            "--persist" => flags.persist = true,
+            "-p" if i < command_at => flags.persist = true,
             "--args" => {
                 if let Some(a) = args.get(i + 1) {
                     flags.args = Some(a.clone());
@@ -125,13 +436,93 @@ pub fn parse_flags(args: &[String]) -> Flags {
                     i += 1;
                 }
             }
-            "--stealth" => flags.stealth = true,
+            "--stealth" => {
+                flags.stealth = Stealth::Full;
+                if let Some(value) = args.get(i + 1).filter(|v| is_stealth_value(v)) {
+                    flags.stealth = Stealth::parse(value).unwrap_or(Stealth::Full);
+                    i += 1;
+                }
+            }
+            "--no-daemon" => flags.no_daemon = true,
+            "--dry-run" => flags.dry_run = true,
+            "--with-id" => flags.with_id = true,
+            "--fix" => flags.fix = true,
+            "--timing" => flags.timing = true,
+            "--timing-log" => flags.timing_log = true,
+            "--redact" => flags.redact = true,
+            "--record-script" => {
+                if let Some(path) = args.get(i + 1) {
+                    flags.record_script = Some(path.clone());
+                    i += 1;
+                }
+            }
+            "--output" => {
+                if let Some(p) = args.get(i + 1) {
+                    flags.output = Some(p.clone());
+                    i += 1;
+                }
+            }
+            "-o" if i < command_at => {
+                if let Some(p) = args.get(i + 1) {
+                    flags.output = Some(p.clone());
+                    i += 1;
+                }
+            }
             "--backend" => {
                 if let Some(b) = args.get(i + 1) {
                     flags.backend = Some(b.clone());
                     i += 1;
                 }
             }
+            "--browser" => {
+                if let Some(b) = args.get(i + 1) {
+                    flags.browser = Some(b.clone());
+                    i += 1;
+                }
+            }
+            "--retry" => {
+                if let Some(n) = args.get(i + 1) {
+                    flags.retry = n.parse().unwrap_or(flags.retry);
+                    i += 1;
+                }
+            }
+            "--retry-delay" => {
+                if let Some(ms) = args.get(i + 1) {
+                    flags.retry_delay = ms.parse().unwrap_or(flags.retry_delay);
+                    i += 1;
+                }
+            }
+            "--connect-timeout" => {
+                if let Some(ms) = args.get(i + 1) {
+                    flags.connect_timeout = ms.parse().ok().or(flags.connect_timeout);
+                    i += 1;
+                }
+            }
+            "--remote" => {
+                if let Some(addr) = args.get(i + 1) {
+                    flags.remote = Some(addr.clone());
+                    i += 1;
+                }
+            }
+            "--remote-token" => {
+                if let Some(token) = args.get(i + 1) {
+                    flags.remote_token = Some(token.clone());
+                    i += 1;
+                }
+            }
+            "--listen" => {
+                if let Some(addr) = args.get(i + 1) {
+                    flags.listen = Some(addr.clone());
+                    i += 1;
+                }
+            }
+            "--session-timeout" | "--idle-timeout" => {
+                if let Some(secs) = args.get(i + 1) {
+                    // 0 keeps the daemon alive indefinitely, same as leaving it out
+                    flags.session_timeout = secs.parse().ok().filter(|secs| *secs > 0);
+                    i += 1;
+                }
+            }
             _ => {}
         }
         i += 1;
@@ -139,36 +530,241 @@ pub fn parse_flags(args: &[String]) -> Flags {
     flags
 }
 
+// Global flags that should be stripped from command args
+pub const GLOBAL_FLAGS: &[&str] = &["--json", "--jsonl", "--full", "--headed", "--debug", "--quiet", "--ignore-https-errors", "--persist", "--stealth", "--no-daemon", "--dry-run", "--with-id", "--fix", "--timing", "--timing-log", "--redact"];
+// Global flags that take a value (need to skip the next arg too)
+pub const GLOBAL_FLAGS_WITH_VALUE: &[&str] = &["--session", "--headers", "--executable-path", "--cdp", "--extension", "--proxy", "--proxy-file", "--proxy-strategy", "--proxy-bypass", "--profile", "--user-data-dir", "--session-name", "--state", "--args", "--user-agent", "--backend", "--browser", "--output", "--retry", "--retry-delay", "--session-timeout", "--idle-timeout", "--connect-timeout", "--remote", "--remote-token", "--listen", "--record-script"];
+// Other flags accepted before the command word
+const OTHER_FLAGS: &[&str] = &["-f", "-p", "-o", "--help", "-h", "--version", "-V"];
+// Short aliases of global flags, only recognized before the command word
+const SHORT_GLOBAL_FLAGS: &[&str] = &["-f", "-p"];
+// Short aliases that take a value, likewise only before the command word
+pub const SHORT_GLOBAL_FLAGS_WITH_VALUE: &[&str] = &["-o"];
+
+/// Whether a flag seen before the command word consumes the next argument
+fn takes_value_before_command(arg: &str, next: Option<&String>) -> bool {
+    GLOBAL_FLAGS_WITH_VALUE.contains(&arg)
+        || SHORT_GLOBAL_FLAGS_WITH_VALUE.contains(&arg)
+        || (arg == "--stealth" && next.is_some_and(|v| is_stealth_value(v)))
+}
+
+/// Remove `--color <mode>` / `--color=<mode>` from before the command word
+/// (after it, `--color` belongs to the command, as in `highlight`). The last
+/// one given wins.
+pub fn take_color_flag(args: &[String]) -> Result<(Vec<String>, Option<ColorMode>), String> {
+    let mut rest = Vec::with_capacity(args.len());
+    let mut mode = None;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if arg == "--" || !arg.starts_with('-') {
+            break;
+        }
+        if arg == "--color" {
+            let value = args.get(i + 1).ok_or("--color requires a mode (auto, always or never)")?;
+            mode = Some(ColorMode::parse(value)?);
+            i += 2;
+            continue;
+        }
+        if let Some(value) = arg.strip_prefix("--color=") {
+            mode = Some(ColorMode::parse(value)?);
+            i += 1;
+            continue;
+        }
+        rest.push(args[i].clone());
+        if takes_value_before_command(arg, args.get(i + 1)) {
+            rest.extend(args.get(i + 1).cloned());
+            i += 1;
+        }
+        i += 1;
+    }
+    rest.extend(args[i.min(args.len())..].iter().cloned());
+    Ok((rest, mode))
+}
+
+/// Index of the command word: the first argument that is neither a global
+/// flag nor a global flag's value (`args.len()` when there is none)
+pub fn command_index(args: &[String]) -> usize {
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if arg == "--" {
+            return i + 1;
+        }
+        if !arg.starts_with('-') {
+            return i;
+        }
+        if takes_value_before_command(arg, args.get(i + 1)) {
+            i += 1;
+        }
+        i += 1;
+    }
+    args.len()
+}
+
 pub fn clean_args(args: &[String]) -> Vec<String> {
     let mut result = Vec::new();
     let mut skip_next = false;
 
-    // Global flags that should be stripped from command args
-    const GLOBAL_FLAGS: &[&str] = &["--json", "--full", "--headed", "--debug", "--ignore-https-errors", "--persist", "--stealth"];
-    // Global flags that take a value (need to skip the next arg too)
-    const GLOBAL_FLAGS_WITH_VALUE: &[&str] = &["--session", "--headers", "--executable-path", "--cdp", "--extension", "--proxy", "--profile", "--session-name", "--state", "--args", "--user-agent", "--backend"];
+    let mut seen_command = false;
 
-    for arg in args.iter() {
+    for (i, arg) in args.iter().enumerate() {
         if skip_next {
             skip_next = false;
             continue;
         }
-        if GLOBAL_FLAGS_WITH_VALUE.contains(&arg.as_str()) {
+        // `--` ends the global flags wherever it appears: drop it and keep
+        // everything after it as-is
+        if arg == "--" {
+            result.extend(args[i + 1..].iter().cloned());
+            break;
+        }
+        if arg == "--stealth" {
+            skip_next = args.get(i + 1).is_some_and(|v| is_stealth_value(v));
+            continue;
+        }
+        if GLOBAL_FLAGS_WITH_VALUE.contains(&arg.as_str())
+            || (!seen_command && SHORT_GLOBAL_FLAGS_WITH_VALUE.contains(&arg.as_str()))
+        {
             skip_next = true;
             continue;
         }
         // Only strip known global flags, not command-specific flags
-        if GLOBAL_FLAGS.contains(&arg.as_str()) || arg == "-f" || arg == "-p" {
+        if GLOBAL_FLAGS.contains(&arg.as_str()) || (!seen_command && SHORT_GLOBAL_FLAGS.contains(&arg.as_str())) {
             continue;
         }
+        seen_command = true;
         result.push(arg.clone());
     }
     result
 }
 
+/// The global flags (with their values) in `args`: the complement of
+/// [`clean_args`], for re-applying the invocation's flags to sub-commands.
+pub fn global_args(args: &[String]) -> Vec<String> {
+    let mut result = Vec::new();
+    let command_at = command_index(args);
+    let mut iter = args.iter().enumerate().peekable();
+    while let Some((i, arg)) = iter.next() {
+        if arg == "--" {
+            break;
+        }
+        if GLOBAL_FLAGS_WITH_VALUE.contains(&arg.as_str())
+            || (i < command_at && SHORT_GLOBAL_FLAGS_WITH_VALUE.contains(&arg.as_str()))
+        {
+            result.push(arg.clone());
+            result.extend(iter.next().map(|(_, v)| v.clone()));
+        } else if arg == "--stealth" {
+            result.push(arg.clone());
+            result.extend(iter.next_if(|(_, v)| is_stealth_value(v)).map(|(_, v)| v.clone()));
+        } else if GLOBAL_FLAGS.contains(&arg.as_str()) || (i < command_at && SHORT_GLOBAL_FLAGS.contains(&arg.as_str())) {
+            result.push(arg.clone());
+        }
+    }
+    result
+}
+
+/// Reject unrecognized `--flags` before the command word, where only global
+/// flags are valid. Anything after the command word (or after `--`) is left
+/// to the command's own parser, so command-local flags like `snapshot -i` work.
+pub fn check_unknown_flags(args: &[String]) -> Result<(), String> {
+    let mut unknown: Vec<&str> = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if arg == "--" || !arg.starts_with('-') {
+            break;
+        }
+        if GLOBAL_FLAGS_WITH_VALUE.contains(&arg) || SHORT_GLOBAL_FLAGS_WITH_VALUE.contains(&arg) {
+            i += 1;
+        } else if !GLOBAL_FLAGS.contains(&arg) && !OTHER_FLAGS.contains(&arg) {
+            unknown.push(arg);
+        }
+        i += 1;
+    }
+
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    let described: Vec<String> = unknown
+        .iter()
+        .map(|flag| match suggest_flag(flag) {
+            Some(s) => format!("{} (did you mean {}?)", flag, s),
+            None => flag.to_string(),
+        })
+        .collect();
+    Err(format!("Unknown flag: {}", described.join(", ")))
+}
+
+/// Closest known global flag within a small edit distance
+fn suggest_flag(flag: &str) -> Option<&'static str> {
+    GLOBAL_FLAGS
+        .iter()
+        .chain(GLOBAL_FLAGS_WITH_VALUE)
+        .chain(OTHER_FLAGS)
+        .filter(|known| known.starts_with("--"))
+        .map(|known| (edit_distance(flag, known), *known))
+        .filter(|(d, _)| *d <= 2)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, known)| known)
+}
+
+/// Levenshtein distance, counted in chars
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// Check for flag combinations that cannot work together
+pub fn check_conflicts(flags: &Flags) -> Result<(), String> {
+    if flags.no_daemon && flags.cdp.is_some() {
+        return Err("--no-daemon cannot be combined with --cdp: a one-shot browser is launched and closed by the CLI, while --cdp attaches to an existing one".to_string());
+    }
+    if flags.no_daemon && flags.remote.is_some() {
+        return Err("--no-daemon cannot be combined with --remote: the remote daemon is managed on its own machine".to_string());
+    }
+    if let Some(addr) = &flags.listen {
+        if !is_loopback(addr) && flags.remote_token.is_none() {
+            return Err(format!("--listen {} accepts connections from other machines; set --remote-token so only clients with the token can drive the browser", addr));
+        }
+    }
+    Ok(())
+}
+
+/// Each `--extension` must be an unpacked extension directory
+pub fn check_extensions(paths: &[String]) -> Result<(), String> {
+    for path in paths {
+        let p = std::path::Path::new(path);
+        if !p.exists() {
+            return Err(format!("--extension {}: no such directory", path));
+        }
+        if !p.is_dir() {
+            return Err(format!("--extension {}: not a directory (expected an unpacked extension folder)", path));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a `host:port` listen address is only reachable from this machine
+fn is_loopback(addr: &str) -> bool {
+    let host = addr.rsplit_once(':').map(|(host, _)| host).unwrap_or(addr);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host == "localhost" || host == "::1" || host.starts_with("127.")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TempDir;
 
     fn args(s: &str) -> Vec<String> {
         s.split_whitespace().map(String::from).collect()
@@ -272,4 +868,348 @@ mod tests {
         assert_eq!(flags.session, "test");
         assert_eq!(flags.executable_path, Some("/custom/chrome".to_string()));
     }
+
+    #[test]
+    fn test_parse_no_daemon_flag() {
+        let flags = parse_flags(&args("--no-daemon open example.com"));
+        assert!(flags.no_daemon);
+        assert!(check_conflicts(&flags).is_ok());
+        let cleaned = clean_args(&args("--no-daemon open example.com"));
+        assert_eq!(cleaned, vec!["open", "example.com"]);
+    }
+
+    #[test]
+    fn test_no_daemon_conflicts_with_cdp() {
+        let flags = parse_flags(&args("--no-daemon --cdp 9222 snapshot"));
+        let err = check_conflicts(&flags).unwrap_err();
+        assert!(err.contains("--no-daemon"));
+        assert!(err.contains("--cdp"));
+    }
+
+    #[test]
+    fn test_config_defaults_apply() {
+        let config = SessionConfig {
+            stealth: Some(Stealth::Full),
+            proxy: Some("http://proxy:8080".to_string()),
+            ..Default::default()
+        };
+        let flags = parse_flags_with_config(&args("open example.com"), &config, &|_| None);
+        assert!(flags.stealth.is_on());
+        assert_eq!(flags.proxy.as_deref(), Some("http://proxy:8080"));
+    }
+
+    #[test]
+    fn test_cli_overrides_config() {
+        let config = SessionConfig {
+            proxy: Some("http://config:1".to_string()),
+            ..Default::default()
+        };
+        let flags = parse_flags_with_config(&args("--proxy http://cli:2 open example.com"), &config, &|_| None);
+        assert_eq!(flags.proxy.as_deref(), Some("http://cli:2"));
+    }
+
+    #[test]
+    fn test_session_from_args() {
+        assert_eq!(session_from_args(&args("--session scraper open x")), "scraper");
+    }
+
+
+    #[test]
+    fn test_env_overrides_config_and_cli_overrides_env() {
+        let config = SessionConfig {
+            backend: Some("config".to_string()),
+            ..Default::default()
+        };
+        let var = |name: &str| (name == "AGENT_BROWSER_BACKEND").then(|| "env".to_string());
+        let from_env = parse_flags_with_config(&args("open example.com"), &config, &var);
+        let from_cli = parse_flags_with_config(&args("--backend cli open example.com"), &config, &var);
+        assert_eq!(parse_flags_with_config(&args("open example.com"), &config, &|_| None).backend.as_deref(), Some("config"));
+        assert_eq!(from_env.backend.as_deref(), Some("env"));
+        assert_eq!(from_cli.backend.as_deref(), Some("cli"));
+    }
+
+
+    #[test]
+    fn test_unknown_flag_typo_suggests() {
+        let err = check_unknown_flags(&args("--sesion foo open example.com")).unwrap_err();
+        assert!(err.contains("--sesion"));
+        assert!(err.contains("did you mean --session?"));
+    }
+
+    #[test]
+    fn test_unknown_flag_without_suggestion() {
+        let err = check_unknown_flags(&args("--frobnicate open example.com")).unwrap_err();
+        assert_eq!(err, "Unknown flag: --frobnicate");
+    }
+
+    #[test]
+    fn test_known_global_flags_pass() {
+        assert!(check_unknown_flags(&args("--session a --json --headed open example.com")).is_ok());
+    }
+
+    #[test]
+    fn test_command_local_flags_pass() {
+        assert!(check_unknown_flags(&args("snapshot -i --depth 3")).is_ok());
+        assert!(check_unknown_flags(&args("--json find role button click --name Submit")).is_ok());
+    }
+
+    #[test]
+    fn test_double_dash_escape_hatch() {
+        let input = args("--json -- --weird-value");
+        assert!(check_unknown_flags(&input).is_ok());
+        assert_eq!(clean_args(&input), vec!["--weird-value"]);
+    }
+
+    #[test]
+    fn test_double_dash_after_command() {
+        let input = args("--dry-run fill #x -- --json -o");
+        assert_eq!(clean_args(&input), args("fill #x --json -o"));
+        assert_eq!(global_args(&input), args("--dry-run"));
+        let flags = parse_flags(&input);
+        assert!(flags.dry_run);
+        assert!(!flags.json && flags.output.is_none());
+        assert!(check_flag_values(&args("fill #x -- --retry soon")).is_ok());
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("--sesion", "--session"), 1);
+        assert_eq!(edit_distance("--json", "--json"), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+    }
+
+
+    #[test]
+    fn test_jsonl_implies_json() {
+        let flags = parse_flags(&args("--jsonl tab list"));
+        assert!(flags.jsonl);
+        assert!(flags.json);
+        assert_eq!(clean_args(&args("--jsonl tab list")), args("tab list"));
+    }
+
+
+    #[test]
+    fn test_parse_user_data_dir() {
+        let flags = parse_flags(&args("--user-data-dir /tmp/chrome-data open example.com"));
+        assert_eq!(flags.user_data_dir.as_deref(), Some("/tmp/chrome-data"));
+        assert!(parse_flags(&args("open example.com")).user_data_dir.is_none());
+    }
+
+    #[test]
+    fn test_clean_args_removes_user_data_dir() {
+        let cleaned = clean_args(&args("open example.com --user-data-dir /tmp/chrome-data --headed"));
+        assert_eq!(cleaned, args("open example.com"));
+    }
+
+
+    #[test]
+    fn test_take_color_flag() {
+        let (rest, mode) = take_color_flag(&args("--color never --json open example.com")).unwrap();
+        assert_eq!(rest, args("--json open example.com"));
+        assert_eq!(mode, Some(ColorMode::Never));
+
+        let (rest, mode) = take_color_flag(&args("--session s --color=always snapshot")).unwrap();
+        assert_eq!(rest, args("--session s snapshot"));
+        assert_eq!(mode, Some(ColorMode::Always));
+
+        // highlight's own --color is left alone
+        let input = args("--json highlight #x --color red");
+        assert_eq!(take_color_flag(&input).unwrap(), (input.clone(), None));
+        // A flag value that looks like --color isn't one
+        let input = args("--args --color=never open x");
+        assert_eq!(take_color_flag(&input).unwrap().0, input);
+
+        assert!(take_color_flag(&args("--color sometimes open x")).unwrap_err().contains("sometimes"));
+        assert!(take_color_flag(&args("--color")).is_err());
+    }
+
+    #[test]
+    fn test_check_extensions() {
+        let dir = TempDir::new("ext");
+        let file = dir.write("manifest.json", "{}");
+
+        assert!(check_extensions(&[]).is_ok());
+        assert!(check_extensions(&[dir.display().to_string()]).is_ok());
+        let missing = dir.join("missing").display().to_string();
+        let err = check_extensions(&[dir.display().to_string(), missing.clone()]).unwrap_err();
+        assert_eq!(err, format!("--extension {}: no such directory", missing));
+        let err = check_extensions(&[file.display().to_string()]).unwrap_err();
+        assert!(err.contains("not a directory"), "{}", err);
+    }
+
+    #[test]
+    fn test_short_aliases_only_before_command() {
+        let input = args("-f -p screenshot out.png");
+        assert_eq!(command_index(&input), 2);
+        let flags = parse_flags(&input);
+        assert!(flags.full && flags.persist);
+        assert_eq!(clean_args(&input), args("screenshot out.png"));
+        assert_eq!(global_args(&input), args("-f -p"));
+
+        // After the command word they are the command's own arguments
+        let input = args("--session s fill #x -f -p");
+        assert_eq!(command_index(&input), 2);
+        let flags = parse_flags(&input);
+        assert!(!flags.full && !flags.persist);
+        assert_eq!(clean_args(&input), args("fill #x -f -p"));
+        assert_eq!(global_args(&input), args("--session s"));
+
+        // Long forms still work anywhere
+        let flags = parse_flags(&args("screenshot --full --persist"));
+        assert!(flags.full && flags.persist);
+        assert_eq!(clean_args(&args("screenshot --full")), args("screenshot"));
+        assert_eq!(command_index(&args("--json -- -f")), 2);
+    }
+
+    #[test]
+    fn test_short_output_alias_only_before_command() {
+        let input = args("-o page.html get html body");
+        assert_eq!(command_index(&input), 2);
+        assert_eq!(parse_flags(&input).output.as_deref(), Some("page.html"));
+        assert_eq!(clean_args(&input), args("get html body"));
+        assert_eq!(global_args(&input), args("-o page.html"));
+        assert!(check_unknown_flags(&input).is_ok());
+
+        let input = args("--dry-run fill #x -o");
+        assert!(parse_flags(&input).output.is_none());
+        assert_eq!(clean_args(&input), args("fill #x -o"));
+        assert_eq!(global_args(&input), args("--dry-run"));
+
+        let flags = parse_flags(&args("get html body --output page.html"));
+        assert_eq!(flags.output.as_deref(), Some("page.html"));
+    }
+
+    #[test]
+    fn test_global_args_is_complement_of_clean_args() {
+        let input = args("--session work --json run steps.txt --headed --var A=1");
+        assert_eq!(global_args(&input), args("--session work --json --headed"));
+        assert_eq!(clean_args(&input), args("run steps.txt --var A=1"));
+    }
+
+    #[test]
+    fn test_parse_retry_flags() {
+        let flags = parse_flags(&args("--retry 3 --retry-delay 250 click #x"));
+        assert_eq!(flags.retry, 3);
+        assert_eq!(flags.retry_delay, 250);
+        assert_eq!(clean_args(&args("--retry 3 --retry-delay 250 click #x")), args("click #x"));
+
+        let flags = parse_flags(&args("click #x"));
+        assert_eq!(flags.retry, 0);
+        assert_eq!(flags.retry_delay, DEFAULT_RETRY_DELAY_MS);
+    }
+
+    #[test]
+    fn test_parse_session_timeout() {
+        let input = args("--session-timeout 300 open example.com");
+        assert_eq!(parse_flags(&input).session_timeout, Some(300));
+        assert_eq!(clean_args(&input), args("open example.com"));
+        assert_eq!(parse_flags(&args("--session-timeout 0 open example.com")).session_timeout, None);
+        assert_eq!(parse_flags(&args("open example.com")).session_timeout, None);
+        assert!(check_flag_values(&args("--session-timeout 1.5 open example.com")).is_err());
+        assert!(check_flag_values(&args("--session-timeout -5 open example.com")).is_err());
+    }
+
+    #[test]
+    fn test_parse_bare_stealth_is_full() {
+        let input = args("--stealth open example.com");
+        assert_eq!(parse_flags(&input).stealth, Stealth::Full);
+        assert_eq!(clean_args(&input), args("open example.com"));
+        assert_eq!(serde_json::to_value(Stealth::Full).unwrap(), json!({ "level": "full" }));
+        assert_eq!(serde_json::to_value(Stealth::Off).unwrap(), json!(false));
+        assert_eq!(parse_flags(&args("open example.com")).stealth, Stealth::Off);
+    }
+
+    #[test]
+    fn test_parse_stealth_levels() {
+        let input = args("--stealth basic open example.com");
+        assert_eq!(parse_flags(&input).stealth, Stealth::Basic);
+        assert_eq!(clean_args(&input), args("open example.com"));
+        assert_eq!(global_args(&input), args("--stealth basic"));
+
+        let input = args("--stealth navigator.webdriver,webgl.vendor --headed snapshot");
+        let stealth = parse_flags(&input).stealth;
+        assert_eq!(stealth, Stealth::Evasions(vec!["navigator.webdriver".to_string(), "webgl.vendor".to_string()]));
+        assert_eq!(serde_json::to_value(&stealth).unwrap(), json!({ "evasions": ["navigator.webdriver", "webgl.vendor"] }));
+        assert_eq!(clean_args(&input), args("snapshot"));
+
+        let err = check_flag_values(&args("--stealth navigator.webdriver,canvas open x")).unwrap_err();
+        assert!(err.contains("unknown evasion 'canvas'"));
+        assert_eq!(Stealth::from_value(&json!(true)), Stealth::Full);
+        assert_eq!(Stealth::from_value(&json!({ "level": "basic" })), Stealth::Basic);
+    }
+
+    #[test]
+    fn test_parse_proxy_rotation_flags() {
+        let input = args("--proxy-file pool.txt --proxy-strategy random open example.com");
+        let flags = parse_flags(&input);
+        assert_eq!(flags.proxy_file.as_deref(), Some("pool.txt"));
+        assert_eq!(flags.proxy_strategy.as_deref(), Some("random"));
+        assert_eq!(clean_args(&input), args("open example.com"));
+        assert!(check_flag_values(&input).is_ok());
+        assert!(check_flag_values(&args("--proxy-strategy sticky open x")).unwrap_err().contains("sticky"));
+    }
+
+    #[test]
+    fn test_parse_proxy_bypass_flag() {
+        let input = args("--proxy http://p:8080 --proxy-bypass localhost,*.internal open example.com");
+        let flags = parse_flags(&input);
+        assert_eq!(flags.proxy_bypass.as_deref(), Some("localhost,*.internal"));
+        assert_eq!(clean_args(&input), args("open example.com"));
+    }
+
+    #[test]
+    fn test_parse_browser_flag() {
+        let input = args("--browser firefox open example.com");
+        assert_eq!(parse_flags(&input).browser.as_deref(), Some("firefox"));
+        assert_eq!(clean_args(&input), args("open example.com"));
+        assert!(check_flag_values(&input).is_ok());
+        let err = check_flag_values(&args("--browser edge open x")).unwrap_err();
+        assert!(err.contains("'edge'") && err.contains("chromium, firefox, webkit"));
+        assert!(parse_flags(&args("open x")).browser.is_none());
+    }
+
+    #[test]
+    fn test_parse_idle_timeout() {
+        let input = args("--idle-timeout 600 open example.com");
+        assert_eq!(parse_flags(&input).session_timeout, Some(600));
+        assert_eq!(clean_args(&input), args("open example.com"));
+        assert_eq!(parse_flags(&args("--idle-timeout 0 open x")).session_timeout, None);
+        assert!(check_flag_values(&args("--idle-timeout soon open x")).is_err());
+    }
+
+    #[test]
+    fn test_parse_remote_flags() {
+        let input = args("--remote box:9300 --remote-token s3cret snapshot");
+        let flags = parse_flags(&input);
+        assert_eq!(flags.remote.as_deref(), Some("box:9300"));
+        assert_eq!(flags.remote_token.as_deref(), Some("s3cret"));
+        assert_eq!(clean_args(&input), args("snapshot"));
+    }
+
+    #[test]
+    fn test_listen_requires_token_off_loopback() {
+        assert!(check_conflicts(&parse_flags(&args("--listen 127.0.0.1:9300 open x"))).is_ok());
+        assert!(check_conflicts(&parse_flags(&args("--listen [::1]:9300 open x"))).is_ok());
+        let err = check_conflicts(&parse_flags(&args("--listen 0.0.0.0:9300 open x"))).unwrap_err();
+        assert!(err.contains("--remote-token"));
+        assert!(check_conflicts(&parse_flags(&args("--listen 0.0.0.0:9300 --remote-token t open x"))).is_ok());
+        assert!(check_conflicts(&parse_flags(&args("--remote box:9300 --no-daemon open x"))).is_err());
+    }
+
+    #[test]
+    fn test_parse_connect_timeout() {
+        let input = args("--connect-timeout 120000 wait #slow");
+        assert_eq!(parse_flags(&input).connect_timeout, Some(120000));
+        assert_eq!(clean_args(&input), args("wait #slow"));
+        assert!(check_flag_values(&args("--connect-timeout 2s wait #slow")).is_err());
+    }
+
+    #[test]
+    fn test_retry_flag_values_validated() {
+        assert!(check_flag_values(&args("--retry 2 title")).is_ok());
+        assert!(check_flag_values(&args("--retry -1 title")).is_err());
+        assert!(check_flag_values(&args("--retry-delay soon title")).is_err());
+        assert!(check_flag_values(&args("title --retry")).is_err());
+        assert!(check_flag_values(&args("--session --retry title")).is_ok());
+    }
 }
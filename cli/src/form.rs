@@ -0,0 +1,251 @@
+use std::fmt;
+use std::fs;
+use std::process::exit;
+
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::color;
+use crate::commands::gen_id;
+use crate::connection::{persistent_sender, Response};
+use crate::flags::Flags;
+use crate::output::{fail, format_dry_run, ErrorCode};
+
+const USAGE: &str = "form fill <file.json>";
+
+/// Fields of a form file in the order they appear. serde_json's `Map` is
+/// sorted by key, so the object is read entry by entry instead.
+struct FormFields(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for FormFields {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = FormFields;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object mapping selectors to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FormFields, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    fields.push(entry);
+                }
+                Ok(FormFields(fields))
+            }
+        }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+/// Parse a form file into `(key, value)` pairs, keeping file order
+pub fn parse_form(content: &str) -> Result<Vec<(String, Value)>, String> {
+    serde_json::from_str::<FormFields>(content)
+        .map(|fields| fields.0)
+        .map_err(|e| format!("Invalid form file: {}", e))
+}
+
+/// Selector for a form key. `label:`, `placeholder:` and `testid:` keys are
+/// turned into locators so every field can be filled, checked or selected;
+/// anything else is used as-is (CSS selector or @ref).
+pub fn field_selector(key: &str) -> String {
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    if let Some(label) = key.strip_prefix("label:") {
+        // Same selector page.getByLabel() builds: case-insensitive substring
+        format!("internal:label={}i", quote(label.trim()))
+    } else if let Some(text) = key.strip_prefix("placeholder:") {
+        format!("[placeholder={}]", quote(text.trim()))
+    } else if let Some(id) = key.strip_prefix("testid:") {
+        format!("[data-testid={}]", quote(id.trim()))
+    } else {
+        key.to_string()
+    }
+}
+
+/// Daemon command for one field: strings and numbers fill, booleans
+/// check/uncheck, arrays select options
+pub fn field_command(key: &str, value: &Value) -> Result<Value, String> {
+    let id = gen_id();
+    let selector = field_selector(key);
+    match value {
+        Value::String(s) => Ok(json!({ "id": id, "action": "fill", "selector": selector, "value": s })),
+        Value::Number(n) => Ok(json!({ "id": id, "action": "fill", "selector": selector, "value": n.to_string() })),
+        Value::Bool(true) => Ok(json!({ "id": id, "action": "check", "selector": selector })),
+        Value::Bool(false) => Ok(json!({ "id": id, "action": "uncheck", "selector": selector })),
+        Value::Array(items) => {
+            let values = items
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s.clone()),
+                    Value::Number(n) => Ok(n.to_string()),
+                    _ => Err(format!("Field '{}': select options must be strings", key)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(json!({ "id": id, "action": "select", "selector": selector, "values": values }))
+        }
+        Value::Null | Value::Object(_) => Err(format!(
+            "Field '{}': expected a string, number, boolean or array of options",
+            key
+        )),
+    }
+}
+
+/// Outcome of one field
+#[derive(Serialize)]
+pub struct FieldResult {
+    pub field: String,
+    /// fill, check, uncheck or select; absent when the value was rejected
+    pub action: Option<String>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Send every field in order. A failed field doesn't stop the rest.
+pub fn fill_form<F>(fields: &[(String, Value)], send: &mut F) -> Vec<FieldResult>
+where
+    F: FnMut(Value) -> Result<Response, String>,
+{
+    fields
+        .iter()
+        .map(|(key, value)| {
+            let cmd = match field_command(key, value) {
+                Ok(cmd) => cmd,
+                Err(e) => return FieldResult { field: key.clone(), action: None, success: false, error: Some(e) },
+            };
+            let action = cmd["action"].as_str().map(String::from);
+            let (success, error) = match send(cmd) {
+                Ok(resp) if resp.success => (true, None),
+                Ok(resp) => (false, Some(resp.error.unwrap_or_else(|| "Unknown error".to_string()))),
+                Err(e) => (false, Some(e)),
+            };
+            FieldResult { field: key.clone(), action, success, error }
+        })
+        .collect()
+}
+
+/// `form fill <file.json>`
+pub fn run_form(clean: &[String], flags: &Flags) {
+    let json_mode = flags.json;
+    let path = match (clean.get(1).map(|s| s.as_str()), clean.get(2)) {
+        (Some("fill"), Some(path)) => path,
+        (Some("fill"), None) | (None, _) => {
+            fail(ErrorCode::ParseError, &format!("Missing form file\nUsage: {}", USAGE), json_mode)
+        }
+        (Some(other), _) => fail(
+            ErrorCode::ParseError,
+            &format!("Unknown subcommand: {}\nValid options: fill", other),
+            json_mode,
+        ),
+    };
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => fail(ErrorCode::CommandFailed, &format!("Failed to read {}: {}", path, e), json_mode),
+    };
+    let fields = match parse_form(&content) {
+        Ok(f) => f,
+        Err(e) => fail(ErrorCode::ParseError, &e, json_mode),
+    };
+
+    if flags.dry_run {
+        for (key, value) in &fields {
+            match field_command(key, value) {
+                Ok(cmd) => println!("{}", format_dry_run(&cmd, flags.with_id, json_mode)),
+                Err(e) => fail(ErrorCode::ParseError, &e, json_mode),
+            }
+        }
+        return;
+    }
+
+    let mut send = persistent_sender(&flags.session);
+    let results = fill_form(&fields, &mut send);
+    let failed = results.iter().filter(|r| !r.success).count();
+    if json_mode {
+        println!("{}", json!({ "success": failed == 0, "data": { "fields": results } }));
+    } else {
+        for r in &results {
+            match &r.error {
+                None => println!("{} {}", color::success_indicator(), r.field),
+                Some(e) => eprintln!("{} {}: {}", color::error_indicator(), r.field, e),
+            }
+        }
+        if failed > 0 {
+            eprintln!("{} {} of {} fields failed", color::error_indicator(), failed, results.len());
+        }
+    }
+    if failed > 0 {
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_form_keeps_file_order() {
+        let fields = parse_form(r##"{"#zip": "10001", "#age": 30, "label:Email": "a@b.c"}"##).unwrap();
+        let keys: Vec<&str> = fields.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["#zip", "#age", "label:Email"]);
+        assert!(parse_form("[1, 2]").is_err());
+        assert!(parse_form("{oops").is_err());
+    }
+
+    #[test]
+    fn test_field_selector_prefixes() {
+        assert_eq!(field_selector("label:Email"), r#"internal:label="Email"i"#);
+        assert_eq!(field_selector("placeholder: Search"), r#"[placeholder="Search"]"#);
+        assert_eq!(field_selector(r#"testid:say "hi""#), r#"[data-testid="say \"hi\""]"#);
+        assert_eq!(field_selector("#email"), "#email");
+        assert_eq!(field_selector("@e3"), "@e3");
+    }
+
+    #[test]
+    fn test_field_command_types() {
+        let fill = field_command("#name", &json!("Ada")).unwrap();
+        assert_eq!(fill["action"], "fill");
+        assert_eq!(fill["value"], "Ada");
+        assert_eq!(field_command("#age", &json!(30)).unwrap()["value"], "30");
+        assert_eq!(field_command("#terms", &json!(true)).unwrap()["action"], "check");
+        assert_eq!(field_command("#news", &json!(false)).unwrap()["action"], "uncheck");
+
+        let select = field_command("testid:langs", &json!(["en", "fr"])).unwrap();
+        assert_eq!(select["action"], "select");
+        assert_eq!(select["selector"], r#"[data-testid="langs"]"#);
+        assert_eq!(select["values"], json!(["en", "fr"]));
+
+        assert!(field_command("#x", &Value::Null).unwrap_err().contains("'#x'"));
+        assert!(field_command("#x", &json!({ "a": 1 })).is_err());
+        assert!(field_command("#x", &json!([true])).is_err());
+    }
+
+    #[test]
+    fn test_fill_form_reports_partial_failure() {
+        let fields = parse_form(r##"{"#name": "Ada", "#missing": "x", "#bad": null, "#terms": true}"##).unwrap();
+        let mut sent = Vec::new();
+        let results = {
+            let mut send = |cmd: Value| {
+                let ok = cmd["selector"] != "#missing";
+                sent.push(cmd);
+                Ok(Response {
+                    success: ok,
+                    data: ok.then(|| json!({})),
+                    error: (!ok).then(|| "Element not found".to_string()),
+                })
+            };
+            fill_form(&fields, &mut send)
+        };
+        assert_eq!(sent.len(), 3);
+        let summary: Vec<(&str, bool)> = results.iter().map(|r| (r.field.as_str(), r.success)).collect();
+        assert_eq!(summary, vec![("#name", true), ("#missing", false), ("#bad", false), ("#terms", true)]);
+        assert_eq!(results[1].error.as_deref(), Some("Element not found"));
+        assert!(results[2].action.is_none());
+
+        let value = serde_json::to_value(&results).unwrap();
+        assert_eq!(value[1]["field"], "#missing");
+        assert_eq!(value[3]["action"], "check");
+    }
+}
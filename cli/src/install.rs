@@ -1,150 +1,77 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use std::process::{exit, Command, Stdio};
 
-pub fn run_install(with_deps: bool) {
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::color;
+use crate::flags::check_browser;
+
+/// `install` options followed by a path
+const VALUE_OPTIONS: &[&str] = &["--from", "--download-only"];
+
+/// Engines named after `install`, chromium when none are. Flags such as
+/// `--with-deps` and the paths given to `--from` / `--download-only` are
+/// skipped.
+pub fn install_targets(args: &[String]) -> Result<Vec<String>, String> {
+    let is_option_value = |i: usize| i > 0 && VALUE_OPTIONS.contains(&args[i - 1].as_str());
+    let names: Vec<String> = args
+        .iter()
+        .enumerate()
+        .filter(|(i, a)| !a.starts_with('-') && !is_option_value(*i))
+        .map(|(_, a)| a.clone())
+        .collect();
+    for name in &names {
+        check_browser(name)?;
+    }
+    Ok(if names.is_empty() { vec!["chromium".to_string()] } else { names })
+}
+
+/// The path after an `install` option such as `--from`
+pub fn install_option<'a>(args: &'a [String], option: &str) -> Result<Option<&'a str>, String> {
+    match args.iter().position(|a| a == option) {
+        None => Ok(None),
+        Some(i) => match args.get(i + 1) {
+            Some(value) if !value.starts_with('-') => Ok(Some(value)),
+            _ => Err(format!("install {} requires a path", option)),
+        },
+    }
+}
+
+/// Install `browsers` with `npx playwright install`, or unpack a bundle made
+/// by `install --download-only` when `from` is given
+pub fn run_install(with_deps: bool, browsers: &[String], from: Option<&Path>) {
+    let list = browsers.join(" ");
     let is_linux = cfg!(target_os = "linux");
 
     if is_linux {
         if with_deps {
             println!("\x1b[36mInstalling system dependencies...\x1b[0m");
-
-            let (pkg_mgr, deps) = if which_exists("apt-get") {
-                (
-                    "apt-get",
-                    vec![
-                        "libxcb-shm0",
-                        "libx11-xcb1",
-                        "libx11-6",
-                        "libxcb1",
-                        "libxext6",
-                        "libxrandr2",
-                        "libxcomposite1",
-                        "libxcursor1",
-                        "libxdamage1",
-                        "libxfixes3",
-                        "libxi6",
-                        "libgtk-3-0",
-                        "libpangocairo-1.0-0",
-                        "libpango-1.0-0",
-                        "libatk1.0-0",
-                        "libcairo-gobject2",
-                        "libcairo2",
-                        "libgdk-pixbuf-2.0-0",
-                        "libxrender1",
-                        "libasound2",
-                        "libfreetype6",
-                        "libfontconfig1",
-                        "libdbus-1-3",
-                        "libnss3",
-                        "libnspr4",
-                        "libatk-bridge2.0-0",
-                        "libdrm2",
-                        "libxkbcommon0",
-                        "libatspi2.0-0",
-                        "libcups2",
-                        "libxshmfence1",
-                        "libgbm1",
-                    ],
-                )
-            } else if which_exists("dnf") {
-                (
-                    "dnf",
-                    vec![
-                        "nss",
-                        "nspr",
-                        "atk",
-                        "at-spi2-atk",
-                        "cups-libs",
-                        "libdrm",
-                        "libXcomposite",
-                        "libXdamage",
-                        "libXrandr",
-                        "mesa-libgbm",
-                        "pango",
-                        "alsa-lib",
-                        "libxkbcommon",
-                        "libxcb",
-                        "libX11-xcb",
-                        "libX11",
-                        "libXext",
-                        "libXcursor",
-                        "libXfixes",
-                        "libXi",
-                        "gtk3",
-                        "cairo-gobject",
-                    ],
-                )
-            } else if which_exists("yum") {
-                (
-                    "yum",
-                    vec![
-                        "nss",
-                        "nspr",
-                        "atk",
-                        "at-spi2-atk",
-                        "cups-libs",
-                        "libdrm",
-                        "libXcomposite",
-                        "libXdamage",
-                        "libXrandr",
-                        "mesa-libgbm",
-                        "pango",
-                        "alsa-lib",
-                        "libxkbcommon",
-                    ],
-                )
-            } else {
+            let Some(pkg_mgr) = package_manager() else {
                 eprintln!("\x1b[31m✗\x1b[0m No supported package manager found (apt-get, dnf, or yum)");
                 exit(1);
             };
-
-            let install_cmd = match pkg_mgr {
-                "apt-get" => {
-                    format!(
-                        "sudo apt-get update && sudo apt-get install -y {}",
-                        deps.join(" ")
-                    )
-                }
-                _ => format!("sudo {} install -y {}", pkg_mgr, deps.join(" ")),
-            };
-
-            println!("Running: {}", install_cmd);
-            let status = Command::new("sh").arg("-c").arg(&install_cmd).status();
-
-            match status {
-                Ok(s) if s.success() => {
-                    println!("\x1b[32m✓\x1b[0m System dependencies installed")
-                }
-                Ok(_) => eprintln!(
-                    "\x1b[33m⚠\x1b[0m Failed to install some dependencies. You may need to run manually with sudo."
-                ),
-                Err(e) => eprintln!("\x1b[33m⚠\x1b[0m Could not run install command: {}", e),
-            }
+            install_packages(pkg_mgr, &system_deps(pkg_mgr));
         } else {
             println!("\x1b[33m⚠\x1b[0m Linux detected. If browser fails to launch, run:");
             println!("  agent-browser install --with-deps");
-            println!("  or: npx playwright install-deps chromium");
+            println!("  or: npx playwright install-deps {}", list);
             println!();
         }
     }
 
-    println!("\x1b[36mInstalling Chromium browser...\x1b[0m");
-    
-    // On Windows, we need to use cmd.exe to run npx because npx is actually npx.cmd
-    // and Command::new() doesn't resolve .cmd files the way the shell does.
-    // Pass the entire command as a single string to /c to handle paths with spaces.
-    #[cfg(windows)]
-    let status = Command::new("cmd")
-        .args(["/c", "npx playwright install chromium"])
-        .status();
-    
-    #[cfg(not(windows))]
-    let status = Command::new("npx")
-        .args(["playwright", "install", "chromium"])
-        .status();
+    if let Some(bundle) = from {
+        install_from_bundle(bundle);
+        return;
+    }
 
-    match status {
+    println!("\x1b[36mInstalling {}...\x1b[0m", browsers.join(", "));
+    match npx_playwright_install(browsers, None) {
         Ok(s) if s.success() => {
-            println!("\x1b[32m✓\x1b[0m Chromium installed successfully");
+            println!("\x1b[32m✓\x1b[0m {} installed successfully", browsers.join(", "));
             if is_linux && !with_deps {
                 println!();
                 println!("\x1b[33mNote:\x1b[0m If you see \"shared library\" errors when running, use:");
@@ -167,7 +94,625 @@ pub fn run_install(with_deps: bool) {
     }
 }
 
-fn which_exists(cmd: &str) -> bool {
+/// One row of the `install --check` report
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// Where Playwright keeps downloaded browsers: `PLAYWRIGHT_BROWSERS_PATH`,
+/// else the platform's default cache
+pub fn playwright_cache_dir() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("PLAYWRIGHT_BROWSERS_PATH").filter(|p| !p.is_empty() && p != "0") {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("ms-playwright"))
+    } else if cfg!(target_os = "macos") {
+        dirs::home_dir().map(|home| home.join("Library/Caches/ms-playwright"))
+    } else {
+        dirs::home_dir().map(|home| home.join(".cache/ms-playwright"))
+    }
+}
+
+/// Installed revisions of `browser` in the cache (`chromium-1140`, ...),
+/// newest last
+pub fn browser_dirs(cache: &Path, browser: &str) -> Vec<PathBuf> {
+    let prefix = format!("{}-", browser);
+    let mut dirs: Vec<PathBuf> = fs::read_dir(cache)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_name().to_string_lossy().starts_with(&prefix) && e.path().is_dir())
+                .map(|e| e.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+/// The Chromium executable inside one cache revision
+pub fn chromium_binary(revision: &Path) -> Option<PathBuf> {
+    [
+        "chrome-linux/chrome",
+        "chrome-linux64/chrome",
+        "chrome-mac/Chromium.app/Contents/MacOS/Chromium",
+        "chrome-win/chrome.exe",
+        "chrome-win64/chrome.exe",
+    ]
+    .iter()
+    .map(|rel| revision.join(rel))
+    .find(|path| path.is_file())
+}
+
+/// Libraries `ldd` reports as `=> not found`
+pub fn parse_ldd_missing(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| line.contains("not found"))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(String::from)
+        .collect()
+}
+
+/// Phrases in Chromium and Playwright launch failures caused by missing
+/// system libraries
+const SHARED_LIBRARY_ERRORS: &[&str] = &[
+    "error while loading shared libraries",
+    "Host system is missing dependencies",
+    "Missing libraries",
+];
+
+/// Libraries named in a launch failure, or `None` when the failure isn't
+/// about missing libraries. `Some` may be empty when the message doesn't
+/// say which ones.
+pub fn missing_libraries(msg: &str) -> Option<Vec<String>> {
+    if !SHARED_LIBRARY_ERRORS.iter().any(|p| msg.contains(p)) {
+        return None;
+    }
+    let mut libs: Vec<String> = Vec::new();
+    for token in msg.split(|c: char| c.is_whitespace() || matches!(c, ':' | ',' | '║' | '"' | '\'')) {
+        if token.starts_with("lib") && token.contains(".so") && !libs.iter().any(|l| l == token) {
+            libs.push(token.to_string());
+        }
+    }
+    Some(libs)
+}
+
+/// Library (soname up to `.so`) → (apt-get package, dnf/yum package)
+const LIBRARY_PACKAGES: &[(&str, &str, &str)] = &[
+    ("libnss3", "libnss3", "nss"),
+    ("libnssutil3", "libnss3", "nss"),
+    ("libsmime3", "libnss3", "nss"),
+    ("libnspr4", "libnspr4", "nspr"),
+    ("libplc4", "libnspr4", "nspr"),
+    ("libplds4", "libnspr4", "nspr"),
+    ("libatk-1.0", "libatk1.0-0", "atk"),
+    ("libatk-bridge-2.0", "libatk-bridge2.0-0", "at-spi2-atk"),
+    ("libatspi", "libatspi2.0-0", "at-spi2-core"),
+    ("libcups", "libcups2", "cups-libs"),
+    ("libdrm", "libdrm2", "libdrm"),
+    ("libgbm", "libgbm1", "mesa-libgbm"),
+    ("libxkbcommon", "libxkbcommon0", "libxkbcommon"),
+    ("libxcb", "libxcb1", "libxcb"),
+    ("libxcb-shm", "libxcb-shm0", "libxcb"),
+    ("libX11", "libx11-6", "libX11"),
+    ("libX11-xcb", "libx11-xcb1", "libX11-xcb"),
+    ("libXcomposite", "libxcomposite1", "libXcomposite"),
+    ("libXcursor", "libxcursor1", "libXcursor"),
+    ("libXdamage", "libxdamage1", "libXdamage"),
+    ("libXext", "libxext6", "libXext"),
+    ("libXfixes", "libxfixes3", "libXfixes"),
+    ("libXi", "libxi6", "libXi"),
+    ("libXrandr", "libxrandr2", "libXrandr"),
+    ("libXrender", "libxrender1", "libXrender"),
+    ("libxshmfence", "libxshmfence1", "libxshmfence"),
+    ("libgtk-3", "libgtk-3-0", "gtk3"),
+    ("libgdk-3", "libgtk-3-0", "gtk3"),
+    ("libgdk_pixbuf-2.0", "libgdk-pixbuf-2.0-0", "gdk-pixbuf2"),
+    ("libpango-1.0", "libpango-1.0-0", "pango"),
+    ("libpangocairo-1.0", "libpangocairo-1.0-0", "pango"),
+    ("libcairo", "libcairo2", "cairo"),
+    ("libcairo-gobject", "libcairo-gobject2", "cairo-gobject"),
+    ("libasound", "libasound2", "alsa-lib"),
+    ("libdbus-1", "libdbus-1-3", "dbus-libs"),
+    ("libfreetype", "libfreetype6", "freetype"),
+    ("libfontconfig", "libfontconfig1", "fontconfig"),
+];
+
+/// Packages providing `libs` for `pkg_mgr`, plus the libraries the table
+/// doesn't know
+pub fn packages_for_libraries(pkg_mgr: &str, libs: &[String]) -> (Vec<&'static str>, Vec<String>) {
+    let mut packages: Vec<&'static str> = Vec::new();
+    let mut unknown: Vec<String> = Vec::new();
+    for lib in libs {
+        let stem = lib.split(".so").next().unwrap_or(lib);
+        match LIBRARY_PACKAGES.iter().find(|(name, _, _)| *name == stem) {
+            Some((_, apt, rpm)) => {
+                let package = if pkg_mgr == "apt-get" { *apt } else { *rpm };
+                if !packages.contains(&package) {
+                    packages.push(package);
+                }
+            }
+            None => unknown.push(lib.clone()),
+        }
+    }
+    (packages, unknown)
+}
+
+/// What to tell the user after a launch failure caused by missing libraries
+pub fn shared_library_hint(libs: &[String]) -> String {
+    let which = match libs {
+        [] => "system libraries".to_string(),
+        [lib] => lib.clone(),
+        [lib, ..] => format!("{} and {} more", lib, libs.len() - 1),
+    };
+    format!(
+        "The browser is missing {}. Run 'z-agent-browser install --with-deps', or repeat the command with --fix to install just what's missing.",
+        which
+    )
+}
+
+/// `--fix`: install the packages behind a missing-library failure. Falls
+/// back to the full `--with-deps` set when a library isn't in the table.
+pub fn fix_missing_libraries(libs: &[String]) -> bool {
+    if !cfg!(target_os = "linux") {
+        eprintln!("\x1b[31m✗\x1b[0m --fix can only install system libraries on Linux");
+        return false;
+    }
+    let Some(pkg_mgr) = package_manager() else {
+        eprintln!("\x1b[31m✗\x1b[0m No supported package manager found (apt-get, dnf, or yum)");
+        return false;
+    };
+    let (packages, unknown) = packages_for_libraries(pkg_mgr, libs);
+    if packages.is_empty() || !unknown.is_empty() {
+        if !unknown.is_empty() {
+            println!("\x1b[33m⚠\x1b[0m No package known for {}; installing all browser dependencies", unknown.join(", "));
+        }
+        return install_packages(pkg_mgr, &system_deps(pkg_mgr));
+    }
+    println!("\x1b[36mInstalling {}...\x1b[0m", packages.join(", "));
+    install_packages(pkg_mgr, &packages)
+}
+
+/// Whether `node` and `npx` are on the PATH
+pub fn check_tools(exists: impl Fn(&str) -> bool) -> Vec<Check> {
+    ["node", "npx"]
+        .iter()
+        .map(|tool| {
+            let ok = exists(tool);
+            Check {
+                name: tool.to_string(),
+                ok,
+                detail: if ok { "found".to_string() } else { "not found on PATH (install Node.js)".to_string() },
+            }
+        })
+        .collect()
+}
+
+/// Whether the cache holds a revision of `browser`
+pub fn check_browser_cache(cache: &Path, browser: &str) -> Check {
+    match browser_dirs(cache, browser).last() {
+        Some(dir) => Check { name: browser.to_string(), ok: true, detail: dir.display().to_string() },
+        None => Check {
+            name: browser.to_string(),
+            ok: false,
+            detail: format!("not installed in {} (run 'z-agent-browser install {}')", cache.display(), browser),
+        },
+    }
+}
+
+/// Run `ldd` (or a stand-in) on `binary` and report missing libraries
+pub fn check_shared_libs(ldd: &str, binary: &Path) -> Check {
+    let name = "shared libraries".to_string();
+    match Command::new(ldd).arg(binary).output() {
+        Ok(out) => {
+            let missing = parse_ldd_missing(&String::from_utf8_lossy(&out.stdout));
+            if missing.is_empty() {
+                Check { name, ok: true, detail: "all found".to_string() }
+            } else {
+                Check {
+                    name,
+                    ok: false,
+                    detail: format!("missing {} (run 'z-agent-browser install --with-deps')", missing.join(", ")),
+                }
+            }
+        }
+        Err(e) => Check { name, ok: false, detail: format!("could not run {}: {}", ldd, e) },
+    }
+}
+
+/// `install --check`: report what's missing without installing anything.
+/// Exits 1 if any check fails.
+pub fn run_install_check(browsers: &[String], json_mode: bool) {
+    let mut checks = check_tools(which_exists);
+    match playwright_cache_dir() {
+        Some(cache) => {
+            for browser in browsers {
+                let check = check_browser_cache(&cache, browser);
+                let binary = (check.ok && browser == "chromium" && cfg!(target_os = "linux"))
+                    .then(|| browser_dirs(&cache, browser).last().and_then(|dir| chromium_binary(dir)))
+                    .flatten();
+                checks.push(check);
+                if let Some(binary) = binary {
+                    checks.push(check_shared_libs("ldd", &binary));
+                }
+            }
+        }
+        None => checks.push(Check {
+            name: "browser cache".to_string(),
+            ok: false,
+            detail: "could not locate the Playwright cache (set PLAYWRIGHT_BROWSERS_PATH)".to_string(),
+        }),
+    }
+
+    let ok = checks.iter().all(|c| c.ok);
+    if json_mode {
+        println!("{}", json!({ "success": ok, "data": { "checks": checks } }));
+    } else {
+        let width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        for c in &checks {
+            let mark = if c.ok { color::success_indicator() } else { color::error_indicator() };
+            println!("{} {:width$}  {}", mark, c.name, c.detail, width = width);
+        }
+    }
+    if !ok {
+        exit(1);
+    }
+}
+
+/// The first of apt-get, dnf or yum on the PATH
+fn package_manager() -> Option<&'static str> {
+    ["apt-get", "dnf", "yum"].into_iter().find(|m| which_exists(m))
+}
+
+/// Everything `--with-deps` installs for the browsers to run
+fn system_deps(pkg_mgr: &str) -> Vec<&'static str> {
+    match pkg_mgr {
+        "apt-get" => vec![
+                "libxcb-shm0",
+                "libx11-xcb1",
+                "libx11-6",
+                "libxcb1",
+                "libxext6",
+                "libxrandr2",
+                "libxcomposite1",
+                "libxcursor1",
+                "libxdamage1",
+                "libxfixes3",
+                "libxi6",
+                "libgtk-3-0",
+                "libpangocairo-1.0-0",
+                "libpango-1.0-0",
+                "libatk1.0-0",
+                "libcairo-gobject2",
+                "libcairo2",
+                "libgdk-pixbuf-2.0-0",
+                "libxrender1",
+                "libasound2",
+                "libfreetype6",
+                "libfontconfig1",
+                "libdbus-1-3",
+                "libnss3",
+                "libnspr4",
+                "libatk-bridge2.0-0",
+                "libdrm2",
+                "libxkbcommon0",
+                "libatspi2.0-0",
+                "libcups2",
+                "libxshmfence1",
+                "libgbm1",
+                    ],
+        "dnf" => vec![
+                "nss",
+                "nspr",
+                "atk",
+                "at-spi2-atk",
+                "cups-libs",
+                "libdrm",
+                "libXcomposite",
+                "libXdamage",
+                "libXrandr",
+                "mesa-libgbm",
+                "pango",
+                "alsa-lib",
+                "libxkbcommon",
+                "libxcb",
+                "libX11-xcb",
+                "libX11",
+                "libXext",
+                "libXcursor",
+                "libXfixes",
+                "libXi",
+                "gtk3",
+                "cairo-gobject",
+                    ],
+        _ => vec![
+                "nss",
+                "nspr",
+                "atk",
+                "at-spi2-atk",
+                "cups-libs",
+                "libdrm",
+                "libXcomposite",
+                "libXdamage",
+                "libXrandr",
+                "mesa-libgbm",
+                "pango",
+                "alsa-lib",
+                "libxkbcommon",
+                    ],
+    }
+}
+
+/// Install `deps` with `pkg_mgr` under sudo. Returns whether it succeeded.
+pub fn install_packages(pkg_mgr: &str, deps: &[&str]) -> bool {
+    let install_cmd = match pkg_mgr {
+        "apt-get" => {
+            format!(
+                "sudo apt-get update && sudo apt-get install -y {}",
+                deps.join(" ")
+            )
+        }
+        _ => format!("sudo {} install -y {}", pkg_mgr, deps.join(" ")),
+    };
+
+    println!("Running: {}", install_cmd);
+    let status = Command::new("sh").arg("-c").arg(&install_cmd).status();
+
+    match status {
+        Ok(s) if s.success() => {
+            println!("\x1b[32m✓\x1b[0m System dependencies installed");
+            true
+        }
+        Ok(_) => {
+            eprintln!(
+                "\x1b[33m⚠\x1b[0m Failed to install some dependencies. You may need to run manually with sudo."
+            );
+            false
+        }
+        Err(e) => {
+            eprintln!("\x1b[33m⚠\x1b[0m Could not run install command: {}", e);
+            false
+        }
+    }
+}
+
+/// Run `npx playwright install`, downloading into `browsers_path` instead
+/// of the default cache when given
+fn npx_playwright_install(browsers: &[String], browsers_path: Option<&Path>) -> std::io::Result<std::process::ExitStatus> {
+    // On Windows, we need to use cmd.exe to run npx because npx is actually npx.cmd
+    // and Command::new() doesn't resolve .cmd files the way the shell does.
+    // Pass the entire command as a single string to /c to handle paths with spaces.
+    #[cfg(windows)]
+    let mut command = Command::new("cmd");
+    #[cfg(windows)]
+    command.args(["/c", &format!("npx playwright install {}", browsers.join(" "))]);
+
+    #[cfg(not(windows))]
+    let mut command = Command::new("npx");
+    #[cfg(not(windows))]
+    command.args(["playwright", "install"]).args(browsers);
+
+    if let Some(path) = browsers_path {
+        command.env("PLAYWRIGHT_BROWSERS_PATH", path);
+    }
+    command.status()
+}
+
+/// Name of the manifest at the root of a browser bundle
+const BUNDLE_MANIFEST: &str = "bundle.json";
+
+/// What a bundle made by `install --download-only` holds
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BundleManifest {
+    /// `os-arch` of the machine that downloaded it, e.g. `linux-x86_64`
+    pub platform: String,
+    /// Cache folders in the archive (`chromium-1140`, `ffmpeg-1010`, ...)
+    pub browsers: Vec<String>,
+}
+
+/// `os-arch` of this machine, as recorded in bundles
+pub fn current_platform() -> String {
+    format!("{}-{}", env::consts::OS, env::consts::ARCH)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    TarGz,
+    Zip,
+}
+
+/// Tell a tar.gz from a zip by its first bytes
+pub fn archive_kind(path: &Path) -> Result<ArchiveKind, String> {
+    let cannot_read = |e: io::Error| format!("Cannot read {}: {}", path.display(), e);
+    let mut header = [0u8; 4];
+    match fs::File::open(path).map_err(cannot_read)?.read_exact(&mut header) {
+        Ok(()) => {}
+        // Too short to be either
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => header = [0; 4],
+        Err(e) => return Err(cannot_read(e)),
+    }
+    match header {
+        [0x1f, 0x8b, ..] => Ok(ArchiveKind::TarGz),
+        [b'P', b'K', 3, 4] => Ok(ArchiveKind::Zip),
+        _ => Err(format!("{} is not a tar.gz or zip archive", path.display())),
+    }
+}
+
+/// A Playwright cache folder name: engine, dash, numeric revision
+pub fn is_versioned_folder(name: &str) -> bool {
+    match name.rsplit_once('-') {
+        Some((engine, revision)) => {
+            !engine.is_empty() && !revision.is_empty() && revision.bytes().all(|b| b.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+fn archive_command(kind: ArchiveKind, archive: &Path, list: bool) -> Command {
+    let mut command;
+    if kind == ArchiveKind::Zip && !cfg!(windows) {
+        command = Command::new("unzip");
+        command.args(if list { &["-Z1"][..] } else { &["-q", "-o"][..] }).arg(archive);
+    } else {
+        // bsdtar (the tar shipped with Windows) reads zip as well
+        command = Command::new("tar");
+        command.arg(if list { "-tf" } else { "-xf" }).arg(archive);
+    }
+    command
+}
+
+/// Run an archive tool, turning a failure into `what` plus its first
+/// line of stderr
+fn run_archive_tool(mut command: Command, what: &str) -> Result<String, String> {
+    let out = command.output().map_err(|e| format!("{}: could not run {:?}: {}", what, command.get_program(), e))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let reason = stderr.lines().find(|l| !l.trim().is_empty()).unwrap_or("unknown error").trim().to_string();
+        return Err(format!("{} ({})", what, reason));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Unpack a bundle into `cache` after checking it: a readable archive, no
+/// entries escaping the cache, a manifest for this `platform`, and every
+/// folder it lists present and versioned. Returns the folders installed.
+pub fn extract_bundle(archive: &Path, cache: &Path, platform: &str) -> Result<Vec<String>, String> {
+    let name = archive.display();
+    let kind = archive_kind(archive)?;
+    let listing = run_archive_tool(archive_command(kind, archive, true), &format!("{} is corrupt or truncated", name))?;
+    for entry in listing.lines() {
+        let path = Path::new(entry);
+        if path.is_absolute() || path.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+            return Err(format!("{} contains an unsafe path: {}", name, entry));
+        }
+    }
+
+    fs::create_dir_all(cache).map_err(|e| format!("Cannot create {}: {}", cache.display(), e))?;
+    let staging = cache.join(format!(".bundle-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging).map_err(|e| format!("Cannot create {}: {}", staging.display(), e))?;
+    let result = install_staged(archive, kind, &staging, cache, platform);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+fn install_staged(archive: &Path, kind: ArchiveKind, staging: &Path, cache: &Path, platform: &str) -> Result<Vec<String>, String> {
+    let name = archive.display();
+    let mut extract = archive_command(kind, archive, false);
+    if kind == ArchiveKind::Zip && !cfg!(windows) {
+        extract.arg("-d").arg(staging);
+    } else {
+        extract.arg("-C").arg(staging);
+    }
+    run_archive_tool(extract, &format!("{} is corrupt or truncated", name))?;
+
+    let manifest = fs::read_to_string(staging.join(BUNDLE_MANIFEST)).map_err(|_| {
+        format!(
+            "{} is not a browser bundle (no {}; create one with 'z-agent-browser install --download-only <dir>')",
+            name, BUNDLE_MANIFEST
+        )
+    })?;
+    let manifest: BundleManifest =
+        serde_json::from_str(&manifest).map_err(|e| format!("{}: invalid {}: {}", name, BUNDLE_MANIFEST, e))?;
+    if manifest.platform != platform {
+        return Err(format!("{} was built for {}, but this machine is {}", name, manifest.platform, platform));
+    }
+    if manifest.browsers.is_empty() {
+        return Err(format!("{} contains no browsers", name));
+    }
+    for folder in &manifest.browsers {
+        if !is_versioned_folder(folder) || folder.contains(['/', '\\']) {
+            return Err(format!("{}: {} is not a versioned browser folder (expected e.g. chromium-1140)", name, folder));
+        }
+        if !staging.join(folder).is_dir() {
+            return Err(format!("{}: {} lists {} but the archive doesn't contain it", name, BUNDLE_MANIFEST, folder));
+        }
+    }
+
+    for folder in &manifest.browsers {
+        let dest = cache.join(folder);
+        if dest.exists() {
+            fs::remove_dir_all(&dest).map_err(|e| format!("Cannot replace {}: {}", dest.display(), e))?;
+        }
+        fs::rename(staging.join(folder), &dest).map_err(|e| format!("Cannot move {} into {}: {}", folder, cache.display(), e))?;
+    }
+    Ok(manifest.browsers)
+}
+
+/// Pack the versioned folders of a Playwright cache into
+/// `<dir>/z-agent-browser-browsers-<platform>.tar.gz` with a manifest
+pub fn create_bundle(cache: &Path, dir: &Path, platform: &str) -> Result<PathBuf, String> {
+    let mut browsers: Vec<String> = fs::read_dir(cache)
+        .map_err(|e| format!("Cannot read {}: {}", cache.display(), e))?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| is_versioned_folder(name))
+        .collect();
+    if browsers.is_empty() {
+        return Err(format!("Nothing to bundle: {} has no browsers", cache.display()));
+    }
+    browsers.sort();
+
+    let manifest = BundleManifest { platform: platform.to_string(), browsers };
+    let manifest_json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    fs::write(cache.join(BUNDLE_MANIFEST), manifest_json)
+        .map_err(|e| format!("Cannot write {}: {}", BUNDLE_MANIFEST, e))?;
+
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let out = dir.join(format!("z-agent-browser-browsers-{}.tar.gz", platform));
+    let mut tar = Command::new("tar");
+    tar.arg("-czf").arg(&out).arg("-C").arg(cache).arg(BUNDLE_MANIFEST).args(&manifest.browsers);
+    run_archive_tool(tar, &format!("Failed to write {}", out.display()))?;
+    Ok(out)
+}
+
+/// `install --from <bundle>`: install without network access
+fn install_from_bundle(bundle: &Path) {
+    let Some(cache) = playwright_cache_dir() else {
+        eprintln!("\x1b[31m✗\x1b[0m Could not locate the Playwright cache (set PLAYWRIGHT_BROWSERS_PATH)");
+        exit(1);
+    };
+    println!("\x1b[36mInstalling from {}...\x1b[0m", bundle.display());
+    match extract_bundle(bundle, &cache, &current_platform()) {
+        Ok(folders) => println!("\x1b[32m✓\x1b[0m Installed {} into {}", folders.join(", "), cache.display()),
+        Err(e) => {
+            eprintln!("\x1b[31m✗\x1b[0m {}", e);
+            exit(1);
+        }
+    }
+}
+
+/// `install --download-only <dir>`: download `browsers` into a scratch
+/// cache and pack them for `install --from` on an offline machine
+pub fn run_download_only(browsers: &[String], dir: &Path) {
+    let scratch = env::temp_dir().join(format!("z-agent-browser-download-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&scratch);
+    println!("\x1b[36mDownloading {}...\x1b[0m", browsers.join(", "));
+    let result = match npx_playwright_install(browsers, Some(&scratch)) {
+        Ok(s) if s.success() => create_bundle(&scratch, dir, &current_platform()),
+        Ok(_) => Err("Failed to download browser".to_string()),
+        Err(e) => Err(format!("Failed to run npx: {}", e)),
+    };
+    let _ = fs::remove_dir_all(&scratch);
+    match result {
+        Ok(out) => {
+            println!("\x1b[32m✓\x1b[0m Wrote {}", out.display());
+            println!("  On the offline machine: z-agent-browser install --from {}", out.display());
+        }
+        Err(e) => {
+            eprintln!("\x1b[31m✗\x1b[0m {}", e);
+            exit(1);
+        }
+    }
+}
+
+pub fn which_exists(cmd: &str) -> bool {
     #[cfg(unix)]
     {
         Command::new("which")
@@ -189,3 +734,279 @@ fn which_exists(cmd: &str) -> bool {
             .unwrap_or(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_install_targets() {
+        assert_eq!(install_targets(&args("")).unwrap(), vec!["chromium"]);
+        assert_eq!(install_targets(&args("--with-deps")).unwrap(), vec!["chromium"]);
+        assert_eq!(install_targets(&args("firefox webkit -d")).unwrap(), vec!["firefox", "webkit"]);
+        let err = install_targets(&args("firefox safari")).unwrap_err();
+        assert!(err.contains("'safari'") && err.contains("chromium, firefox, webkit"), "{}", err);
+    }
+
+    fn temp_dir(name: &str) -> TempDir {
+        TempDir::new(&format!("install-{}", name))
+    }
+
+    #[test]
+    fn test_check_tools() {
+        let checks = check_tools(|tool| tool == "node");
+        assert!(checks[0].ok);
+        assert_eq!(checks[1].name, "npx");
+        assert!(!checks[1].ok);
+    }
+
+    #[test]
+    fn test_browser_cache_and_binary() {
+        let cache = temp_dir("cache");
+        assert!(!check_browser_cache(&cache, "chromium").ok);
+
+        fs::create_dir_all(cache.join("chromium-1100/chrome-linux")).unwrap();
+        fs::create_dir_all(cache.join("chromium-1140/chrome-linux")).unwrap();
+        fs::create_dir_all(cache.join("firefox-1450")).unwrap();
+        fs::write(cache.join("chromium-1140/chrome-linux/chrome"), "").unwrap();
+
+        let check = check_browser_cache(&cache, "chromium");
+        assert!(check.ok);
+        assert!(check.detail.ends_with("chromium-1140"));
+        assert!(!check_browser_cache(&cache, "webkit").ok);
+        assert_eq!(chromium_binary(&cache.join("chromium-1140")), Some(cache.join("chromium-1140/chrome-linux/chrome")));
+        assert_eq!(chromium_binary(&cache.join("chromium-1100")), None);
+    }
+
+    #[test]
+    fn test_parse_ldd_missing() {
+        let output = "\tlinux-vdso.so.1 (0x00007ffd)\n\tlibnss3.so => not found\n\tlibc.so.6 => /lib/libc.so.6 (0x7f)\n\tlibgbm.so.1 => not found\n";
+        assert_eq!(parse_ldd_missing(output), vec!["libnss3.so", "libgbm.so.1"]);
+        assert!(parse_ldd_missing("\tlibc.so.6 => /lib/libc.so.6\n").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_shared_libs_with_fake_ldd() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = temp_dir("ldd");
+        let ldd = dir.join("fake-ldd");
+        fs::write(&ldd, "#!/bin/sh\necho \"\tlibnss3.so => not found\"\necho \"\tlibc.so.6 => /lib/libc.so.6\"\n").unwrap();
+        fs::set_permissions(&ldd, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let check = check_shared_libs(ldd.to_str().unwrap(), &dir.join("chrome"));
+        assert!(!check.ok);
+        assert!(check.detail.contains("libnss3.so"), "{}", check.detail);
+
+        let check = check_shared_libs(dir.join("no-such-ldd").to_str().unwrap(), &dir.join("chrome"));
+        assert!(!check.ok && check.detail.starts_with("could not run"));
+    }
+
+
+    #[test]
+    fn test_install_options() {
+        let a = args("install firefox --from /tmp/b.tar.gz --with-deps");
+        assert_eq!(install_option(&a, "--from").unwrap(), Some("/tmp/b.tar.gz"));
+        assert_eq!(install_option(&a, "--download-only").unwrap(), None);
+        assert_eq!(install_targets(&a[1..]).unwrap(), vec!["firefox"]);
+        assert_eq!(install_targets(&args("--download-only out")).unwrap(), vec!["chromium"]);
+        assert!(install_option(&args("install --from"), "--from").unwrap_err().contains("requires a path"));
+        assert!(install_option(&args("install --from --with-deps"), "--from").is_err());
+    }
+
+    #[test]
+    fn test_versioned_folder() {
+        assert!(is_versioned_folder("chromium-1140"));
+        assert!(is_versioned_folder("chromium_headless_shell-1140"));
+        assert!(!is_versioned_folder("chromium"));
+        assert!(!is_versioned_folder("chromium-"));
+        assert!(!is_versioned_folder("chromium-latest"));
+        assert!(!is_versioned_folder(".links"));
+    }
+
+    /// A fake Playwright cache with one chromium revision
+    fn fake_cache(root: &Path) -> PathBuf {
+        let cache = root.join("source-cache");
+        fs::create_dir_all(cache.join("chromium-1140/chrome-linux")).unwrap();
+        fs::write(cache.join("chromium-1140/chrome-linux/chrome"), "binary").unwrap();
+        fs::write(cache.join("chromium-1140/INSTALLATION_COMPLETE"), "").unwrap();
+        fs::create_dir_all(cache.join(".links")).unwrap();
+        cache
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bundle_round_trip() {
+        let root = temp_dir("bundle");
+        let bundle = create_bundle(&fake_cache(&root), &root.join("out"), "linux-x86_64").unwrap();
+        assert!(bundle.ends_with("z-agent-browser-browsers-linux-x86_64.tar.gz"));
+        assert_eq!(archive_kind(&bundle).unwrap(), ArchiveKind::TarGz);
+
+        let target = root.join("target-cache");
+        fs::create_dir_all(target.join("chromium-1140")).unwrap();
+        fs::write(target.join("chromium-1140/stale"), "").unwrap();
+        assert_eq!(extract_bundle(&bundle, &target, "linux-x86_64").unwrap(), vec!["chromium-1140"]);
+        assert_eq!(fs::read_to_string(target.join("chromium-1140/chrome-linux/chrome")).unwrap(), "binary");
+        assert!(!target.join("chromium-1140/stale").exists());
+        // Only the browsers land in the cache, and the staging dir is gone
+        let mut entries: Vec<String> =
+            fs::read_dir(&target).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        entries.sort();
+        assert_eq!(entries, vec!["chromium-1140"]);
+
+        let err = extract_bundle(&bundle, &root.join("other"), "darwin-aarch64").unwrap_err();
+        assert!(err.contains("built for linux-x86_64") && err.contains("darwin-aarch64"), "{}", err);
+    }
+
+    #[test]
+    fn test_archive_kind_rejects_other_files() {
+        let root = temp_dir("archive-kind");
+        for content in ["", "PK", "plain text"] {
+            let err = archive_kind(&root.write("other.bin", content)).unwrap_err();
+            assert!(err.contains("is not a tar.gz or zip archive"), "{}", err);
+        }
+        assert!(archive_kind(&root.join("missing.tgz")).unwrap_err().starts_with("Cannot read"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bundle_from_zip() {
+        if !which_exists("zip") || !which_exists("unzip") {
+            return;
+        }
+        let root = temp_dir("bundle-zip");
+        let cache = fake_cache(&root);
+        fs::write(cache.join(BUNDLE_MANIFEST), r#"{"platform":"linux-x86_64","browsers":["chromium-1140"]}"#).unwrap();
+        let zip = root.join("bundle.zip");
+        let status = Command::new("zip")
+            .current_dir(&cache)
+            .args(["-qr", zip.to_str().unwrap(), BUNDLE_MANIFEST, "chromium-1140"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(archive_kind(&zip).unwrap(), ArchiveKind::Zip);
+        let target = root.join("target-cache");
+        assert_eq!(extract_bundle(&zip, &target, "linux-x86_64").unwrap(), vec!["chromium-1140"]);
+        assert!(target.join("chromium-1140/INSTALLATION_COMPLETE").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bad_bundles() {
+        let root = temp_dir("bad-bundle");
+        let target = root.join("target-cache");
+
+        let text = root.join("notes.tar.gz");
+        fs::write(&text, "just text").unwrap();
+        assert!(extract_bundle(&text, &target, "linux-x86_64").unwrap_err().contains("not a tar.gz or zip"));
+        assert!(archive_kind(&root.join("missing.tar.gz")).unwrap_err().starts_with("Cannot read"));
+
+        let bundle = create_bundle(&fake_cache(&root), &root.join("out"), "linux-x86_64").unwrap();
+        let bytes = fs::read(&bundle).unwrap();
+        let truncated = root.join("truncated.tar.gz");
+        fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        let err = extract_bundle(&truncated, &target, "linux-x86_64").unwrap_err();
+        assert!(err.contains("corrupt or truncated"), "{}", err);
+
+        // A plain tarball of the cache, without the manifest
+        let plain = root.join("plain.tar.gz");
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(&plain)
+            .arg("-C")
+            .arg(root.join("source-cache"))
+            .arg("chromium-1140")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let err = extract_bundle(&plain, &target, "linux-x86_64").unwrap_err();
+        assert!(err.contains("not a browser bundle"), "{}", err);
+
+        // A manifest naming a folder that isn't there
+        let cache = root.join("source-cache");
+        fs::write(cache.join(BUNDLE_MANIFEST), r#"{"platform":"linux-x86_64","browsers":["firefox-1450"]}"#).unwrap();
+        let lying = root.join("lying.tar.gz");
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(&lying)
+            .arg("-C")
+            .arg(&cache)
+            .args([BUNDLE_MANIFEST, "chromium-1140"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let err = extract_bundle(&lying, &target, "linux-x86_64").unwrap_err();
+        assert!(err.contains("firefox-1450") && err.contains("doesn't contain"), "{}", err);
+        assert!(!target.join("chromium-1140").exists());
+    }
+
+
+    /// Chromium's stderr as Playwright reports it when libnss3 is absent
+    const NSS_LAUNCH_ERROR: &str = "browserType.launch: Target page, context or browser has been closed
+Browser logs:
+
+<launching> /root/.cache/ms-playwright/chromium-1140/chrome-linux/chrome --disable-field-trial-config --headless
+<launched> pid=4242
+[pid=4242][err] /root/.cache/ms-playwright/chromium-1140/chrome-linux/chrome: error while loading shared libraries: libnss3.so: cannot open shared object file: No such file or directory
+Call log:
+  - <launching> /root/.cache/ms-playwright/chromium-1140/chrome-linux/chrome --headless";
+
+    /// Playwright's own dependency check
+    const DEPS_BOX_ERROR: &str = "browserType.launch: 
+╔══════════════════════════════════════════════════════╗
+║ Host system is missing dependencies to run browsers. ║
+║ Missing libraries:                                   ║
+║     libgtk-3.so.0                                    ║
+║     libgdk-3.so.0                                    ║
+║     libatk-1.0.so.0                                  ║
+║     libgbm.so.1                                      ║
+╚══════════════════════════════════════════════════════╝";
+
+    #[test]
+    fn test_missing_libraries() {
+        assert_eq!(missing_libraries(NSS_LAUNCH_ERROR), Some(vec!["libnss3.so".to_string()]));
+        assert_eq!(
+            missing_libraries(DEPS_BOX_ERROR).unwrap(),
+            vec!["libgtk-3.so.0", "libgdk-3.so.0", "libatk-1.0.so.0", "libgbm.so.1"]
+        );
+        // Playwright's install-deps prompt names no libraries
+        let prompt = "║ Host system is missing dependencies to run browsers. ║\n║     sudo npx playwright install-deps ║";
+        assert_eq!(missing_libraries(prompt), Some(vec![]));
+        assert_eq!(missing_libraries("page.goto: net::ERR_NAME_NOT_RESOLVED at https://nope.invalid/"), None);
+        assert_eq!(missing_libraries("Element not found: libfoo.so.1"), None);
+    }
+
+    #[test]
+    fn test_packages_for_libraries() {
+        let libs = missing_libraries(DEPS_BOX_ERROR).unwrap();
+        let (apt, unknown) = packages_for_libraries("apt-get", &libs);
+        assert_eq!(apt, vec!["libgtk-3-0", "libatk1.0-0", "libgbm1"]);
+        assert!(unknown.is_empty());
+        let (dnf, _) = packages_for_libraries("dnf", &libs);
+        assert_eq!(dnf, vec!["gtk3", "atk", "mesa-libgbm"]);
+        let (yum, _) = packages_for_libraries("yum", &["libnss3.so".to_string(), "libnssutil3.so".to_string()]);
+        assert_eq!(yum, vec!["nss"]);
+
+        let (packages, unknown) = packages_for_libraries("apt-get", &["libwhatever.so.9".to_string()]);
+        assert!(packages.is_empty());
+        assert_eq!(unknown, vec!["libwhatever.so.9"]);
+        // Every table entry's apt package is one --with-deps would install
+        let all = system_deps("apt-get");
+        for (lib, apt, _) in LIBRARY_PACKAGES {
+            assert!(all.contains(apt), "{} → {}", lib, apt);
+        }
+    }
+
+    #[test]
+    fn test_shared_library_hint() {
+        assert!(shared_library_hint(&["libnss3.so".to_string()]).starts_with("The browser is missing libnss3.so."));
+        let hint = shared_library_hint(&["libgtk-3.so.0".to_string(), "libgbm.so.1".to_string()]);
+        assert!(hint.contains("libgtk-3.so.0 and 1 more") && hint.contains("--with-deps") && hint.contains("--fix"));
+        assert!(shared_library_hint(&[]).contains("missing system libraries"));
+    }
+}
@@ -0,0 +1,172 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::connection::get_log_path;
+use crate::output::{fail, ErrorCode};
+
+const USAGE: &str = "daemon logs [--follow] [--lines <n>]";
+
+/// Lines shown when `--lines` isn't given
+const DEFAULT_LINES: usize = 50;
+
+/// How often `--follow` checks the file for new output
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The last `n` lines of `text`
+pub fn last_lines(text: &str, n: usize) -> Vec<&str> {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(n)..].to_vec()
+}
+
+/// Read whatever was appended to `path` since `offset`, returning it and the
+/// new offset. A file that shrank was truncated or rotated, so reading
+/// starts over from the beginning.
+pub fn read_new(path: &Path, offset: u64) -> io::Result<(String, u64)> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = if len < offset { 0 } else { offset };
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok((String::from_utf8_lossy(&bytes).into_owned(), start + bytes.len() as u64))
+}
+
+/// `daemon logs [--follow] [--lines <n>]`. Only reads the log file, so it
+/// works when the daemon is dead.
+pub fn run_daemon_logs(args: &[String], session: &str, json_mode: bool) {
+    match args.get(1).map(|s| s.as_str()) {
+        Some("logs") => {}
+        Some(other) => fail(
+            ErrorCode::ParseError,
+            &format!("Unknown subcommand: {}\nValid options: logs, restart", other),
+            json_mode,
+        ),
+        None => fail(ErrorCode::ParseError, &format!("Missing subcommand\nUsage: {}", USAGE), json_mode),
+    }
+
+    let mut follow = false;
+    let mut lines = DEFAULT_LINES;
+    let mut i = 2;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--follow" => follow = true,
+            "--lines" | "-n" => {
+                let value = args.get(i + 1).map(|s| s.as_str()).unwrap_or("");
+                lines = match value.parse() {
+                    Ok(n) => n,
+                    Err(_) => fail(
+                        ErrorCode::ParseError,
+                        &format!("daemon logs: invalid --lines '{}' (expected a number)\nUsage: {}", value, USAGE),
+                        json_mode,
+                    ),
+                };
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let path = get_log_path(session);
+    let (text, offset) = match read_new(&path, 0) {
+        Ok(read) => read,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => fail(
+            ErrorCode::CommandFailed,
+            &format!("No daemon log for session '{}' yet (expected {})", session, path.display()),
+            json_mode,
+        ),
+        Err(e) => fail(ErrorCode::CommandFailed, &format!("Failed to read {}: {}", path.display(), e), json_mode),
+    };
+    let tail = last_lines(&text, lines);
+
+    if json_mode {
+        println!("{}", json!({ "success": true, "data": { "path": path, "lines": tail } }));
+        return;
+    }
+    for line in &tail {
+        println!("{}", line);
+    }
+    if follow {
+        follow_log(&path, offset);
+    }
+}
+
+/// Print new output as it is appended, until interrupted
+fn follow_log(path: &Path, mut offset: u64) {
+    let stdout = io::stdout();
+    loop {
+        thread::sleep(FOLLOW_INTERVAL);
+        // The file disappears briefly while a new daemon rotates it
+        if let Ok((text, next)) = read_new(path, offset) {
+            offset = next;
+            if !text.is_empty() {
+                let mut out = stdout.lock();
+                if out.write_all(text.as_bytes()).and_then(|_| out.flush()).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Move an oversized log aside before a new daemon starts writing to it
+pub fn rotate_log(path: &Path, max_bytes: u64) {
+    if fs::metadata(path).map(|m| m.len() > max_bytes).unwrap_or(false) {
+        let mut rotated = path.as_os_str().to_owned();
+        rotated.push(".1");
+        let _ = fs::rename(path, rotated);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_last_lines() {
+        let text = "one\ntwo\nthree\nfour\n";
+        assert_eq!(last_lines(text, 2), vec!["three", "four"]);
+        assert_eq!(last_lines(text, 10), vec!["one", "two", "three", "four"]);
+        assert!(last_lines(text, 0).is_empty());
+        assert!(last_lines("", 5).is_empty());
+    }
+
+    #[test]
+    fn test_read_new_follows_appends_and_truncation() {
+        let dir = TempDir::new("logs");
+        let path = dir.join("follow.log");
+        fs::write(&path, "started\n").unwrap();
+        let (text, offset) = read_new(&path, 0).unwrap();
+        assert_eq!(text, "started\n");
+
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"launch failed\n").unwrap();
+        let (text, offset) = read_new(&path, offset).unwrap();
+        assert_eq!(text, "launch failed\n");
+        assert_eq!(read_new(&path, offset).unwrap().0, "");
+
+        // Rotated / truncated: start over
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(read_new(&path, offset).unwrap().0, "new\n");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_rotate_log_over_cap() {
+        let dir = TempDir::new("logs");
+        let path = dir.join("rotate.log");
+        let rotated = dir.join("rotate.log.1");
+        fs::write(&path, "x".repeat(100)).unwrap();
+        rotate_log(&path, 1000);
+        assert!(path.exists());
+        rotate_log(&path, 10);
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(&rotated).unwrap().len(), 100);
+        let _ = fs::remove_file(&rotated);
+    }
+}
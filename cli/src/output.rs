@@ -1,82 +1,552 @@
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
 use crate::color;
 use crate::connection::Response;
+use crate::flags::{Flags, Stealth};
+use crate::session::format_uptime;
+
+/// How responses are printed: `--json`, `--quiet` and `--debug`
+#[derive(Clone, Copy)]
+pub struct OutputOptions {
+    pub json: bool,
+    /// With `json`: one JSON object per line for each item of a list response
+    pub jsonl: bool,
+    /// Suppress success confirmations ("✓ Done"); data and errors still print
+    pub quiet: bool,
+    /// Echo the raw request/response JSON to stderr
+    pub debug: bool,
+    /// Send "✓" status lines to stderr (stdout is going to a file)
+    pub status_to_stderr: bool,
+    /// How long the command took, reported as `durationMs` with `--json`
+    pub duration_ms: Option<u64>,
+}
+
+impl OutputOptions {
+    pub fn from_flags(flags: &Flags) -> Self {
+        OutputOptions {
+            json: flags.json,
+            jsonl: flags.jsonl,
+            quiet: flags.quiet,
+            debug: flags.debug,
+            status_to_stderr: false,
+            duration_ms: None,
+        }
+    }
+}
+
+/// `--debug` trace line for a request (`→`) or raw response (`←`)
+pub fn debug_line(arrow: &str, value: &impl Serialize) -> String {
+    format!(
+        "{} {}",
+        color::dim(&format!("[debug] {}", arrow)),
+        serde_json::to_string(value).unwrap_or_default()
+    )
+}
+
+/// Fields that hold the items of list responses
+const JSONL_ITEM_FIELDS: &[&str] = &["tabs", "requests", "messages", "errors", "cookies", "entries", "matches"];
+
+/// Items to print one per line in `--jsonl` mode: the data itself if it is an
+/// array, else its list field. `None` for any other response, which is then
+/// printed as a single line.
+fn jsonl_items(resp: &Response) -> Option<&Vec<Value>> {
+    let data = resp.data.as_ref()?;
+    if let Some(items) = data.as_array() {
+        return Some(items);
+    }
+    let map = data.as_object()?;
+    JSONL_ITEM_FIELDS
+        .iter()
+        .find_map(|field| map.get(*field).and_then(|v| v.as_array()))
+}
+
+/// Stable, machine-readable error codes for `--json` output
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The daemon could not be started, connected to, or talked to
+    DaemonUnreachable,
+    /// The command line could not be parsed (unknown command/flag, bad args)
+    ParseError,
+    /// `--cdp` is not a valid port/URL, or the browser at it can't be reached
+    InvalidCdpEndpoint,
+    /// The command ran but failed
+    CommandFailed,
+    /// The daemon did not answer in time
+    Timeout,
+    /// A remote daemon rejected the `--remote-token`
+    AuthFailed,
+}
+
+impl ErrorCode {
+    /// Classify an error from connecting to / talking with the daemon
+    pub fn for_connection_error(msg: &str) -> ErrorCode {
+        if msg.starts_with("Timed out") {
+            ErrorCode::Timeout
+        } else if msg.starts_with("Authentication failed") {
+            ErrorCode::AuthFailed
+        } else {
+            ErrorCode::DaemonUnreachable
+        }
+    }
+
+    /// Process exit status: 1 when a command ran and failed, 2 for usage
+    /// errors, 3 when the daemon couldn't be reached or didn't answer
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorCode::CommandFailed => 1,
+            ErrorCode::ParseError => 2,
+            ErrorCode::DaemonUnreachable | ErrorCode::InvalidCdpEndpoint | ErrorCode::Timeout | ErrorCode::AuthFailed => 3,
+        }
+    }
+
+    /// Classify a failure reported by the daemon (Playwright timeouts read
+    /// "Timeout 30000ms exceeded")
+    pub fn for_command_error(msg: &str) -> ErrorCode {
+        if msg.starts_with("Timeout") || (msg.contains("Timeout ") && msg.contains("exceeded")) {
+            ErrorCode::Timeout
+        } else {
+            ErrorCode::CommandFailed
+        }
+    }
+}
+
+/// Error body printed in `--json` mode
+#[derive(Serialize)]
+pub struct ErrorResponse<'a> {
+    pub success: bool,
+    pub error: &'a str,
+    pub code: ErrorCode,
+}
+
+/// A `--json` body followed by how long the command took
+#[derive(Serialize)]
+struct Timed<T: Serialize> {
+    #[serde(flatten)]
+    body: T,
+    #[serde(rename = "durationMs", skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+}
+
+pub fn error_json(code: ErrorCode, msg: &str) -> String {
+    serde_json::to_string(&ErrorResponse { success: false, error: msg, code }).unwrap_or_default()
+}
 
-pub fn print_response(resp: &Response, json_mode: bool) {
+/// Report an error: JSON on stdout with `--json`, `✗ msg` on stderr otherwise
+pub fn print_error(code: ErrorCode, msg: &str, json_mode: bool) {
     if json_mode {
-        println!("{}", serde_json::to_string(resp).unwrap_or_default());
-        return;
+        println!("{}", error_json(code, msg));
+    } else {
+        eprintln!("{} {}", color::error_indicator(), msg);
+    }
+}
+
+/// [`print_error`], then exit with the code's [`ErrorCode::exit_code`]
+pub fn fail(code: ErrorCode, msg: &str, json_mode: bool) -> ! {
+    print_error(code, msg, json_mode);
+    std::process::exit(code.exit_code());
+}
+
+/// `--dry-run` output: the daemon command, pretty-printed (compact with
+/// `--json`). The random `id` is dropped unless `with_id`, so output is stable.
+pub fn format_dry_run(cmd: &Value, with_id: bool, compact: bool) -> String {
+    let mut cmd = cmd.clone();
+    if !with_id {
+        if let Some(obj) = cmd.as_object_mut() {
+            obj.remove("id");
+        }
+    }
+    if compact {
+        serde_json::to_string(&cmd).unwrap_or_default()
+    } else {
+        serde_json::to_string_pretty(&cmd).unwrap_or_default()
+    }
+}
+
+/// `@ref` for each match (`-` when it has none) and the width to pad them to
+fn match_refs(matches: &[Value]) -> (Vec<String>, usize) {
+    let refs: Vec<String> = matches
+        .iter()
+        .map(|m| match m.get("ref").and_then(|v| v.as_str()) {
+            Some(r) if r.starts_with('@') => r.to_string(),
+            Some(r) => format!("@{}", r),
+            None => "-".to_string(),
+        })
+        .collect();
+    let width = refs.iter().map(|r| r.len()).max().unwrap_or(0);
+    (refs, width)
+}
+
+/// `find-text`: one `<ref>  …context…` line per match, or just the number of
+/// matches with `--count-only`
+fn write_text_matches(out: &mut impl Write, data: &Value, request: &Value) -> io::Result<()> {
+    let empty = Vec::new();
+    let matches = data.get("matches").and_then(|v| v.as_array()).unwrap_or(&empty);
+    if request.get("countOnly").and_then(|v| v.as_bool()).unwrap_or(false) {
+        let count = data.get("count").and_then(|v| v.as_u64()).unwrap_or(matches.len() as u64);
+        return writeln!(out, "{}", count);
+    }
+    if matches.is_empty() {
+        return writeln!(out, "{}", color::dim("No matches"));
+    }
+    let (refs, width) = match_refs(matches);
+    for (r, m) in refs.iter().zip(matches) {
+        let context = m
+            .get("context")
+            .or_else(|| m.get("text"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let context = context.split_whitespace().collect::<Vec<_>>().join(" ");
+        writeln!(out, "{:<width$}  …{}…", r, context, width = width)?;
+    }
+    Ok(())
+}
+
+/// ` + stealth` suffix for `status` and `start`, naming a non-default level
+fn stealth_label(data: &Value) -> String {
+    match data.get("stealth").map(Stealth::from_value).unwrap_or_default() {
+        Stealth::Off => String::new(),
+        Stealth::Full => " + stealth".to_string(),
+        Stealth::Basic => " + stealth (basic)".to_string(),
+        Stealth::Evasions(list) => format!(" + stealth ({})", list.join(", ")),
+    }
+}
+
+/// One `status` field as shown in the key/value block
+enum StatusValue {
+    Flag(bool),
+    Text(String),
+}
+
+/// The `status` fields present in `data`, in display order. Missing fields
+/// are skipped; unexpected shapes are shown as JSON.
+fn status_rows(data: &Value) -> Vec<(&'static str, StatusValue)> {
+    let text = |v: &Value| match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let mut rows = Vec::new();
+    if let Some(headless) = data.get("headless").and_then(|v| v.as_bool()) {
+        rows.push(("Headless", StatusValue::Flag(headless)));
+    }
+    for (key, label) in [("backend", "Backend"), ("browser", "Browser"), ("profile", "Profile")] {
+        if let Some(v) = data.get(key).filter(|v| !v.is_null()) {
+            rows.push((label, StatusValue::Text(text(v))));
+        }
+    }
+    if let Some(proxy) = data.get("proxy").filter(|v| !v.is_null()) {
+        let server = proxy.get("server").unwrap_or(proxy);
+        rows.push(("Proxy", StatusValue::Text(text(server))));
+    }
+    if let Some(stealth) = data.get("stealth") {
+        rows.push((
+            "Stealth",
+            match Stealth::from_value(stealth) {
+                Stealth::Off => StatusValue::Flag(false),
+                Stealth::Full => StatusValue::Flag(true),
+                Stealth::Basic => StatusValue::Text("basic".to_string()),
+                Stealth::Evasions(list) => StatusValue::Text(list.join(", ")),
+            },
+        ));
+    }
+    // A count, or the list of open tabs
+    if let Some(tabs) = data.get("tabs").and_then(|v| v.as_u64().or_else(|| v.as_array().map(|a| a.len() as u64))) {
+        rows.push(("Tabs", StatusValue::Text(tabs.to_string())));
+    }
+    if let Some(ms) = data.get("uptime").and_then(|v| v.as_u64()) {
+        rows.push(("Uptime", StatusValue::Text(format_uptime(ms / 1000))));
+    }
+    // Time left before an idle daemon shuts itself down
+    if let Some(ms) = data.get("idleRemaining").and_then(|v| v.as_u64()) {
+        rows.push(("Idle timeout", StatusValue::Text(format!("in {}", format_uptime(ms.div_ceil(1000))))));
+    }
+    if let Some(pid) = data.get("pid").filter(|v| !v.is_null()) {
+        rows.push(("PID", StatusValue::Text(text(pid))));
+    }
+    if let Some(version) = data.get("version").or_else(|| data.get("daemonVersion")).filter(|v| !v.is_null()) {
+        rows.push(("Version", StatusValue::Text(text(version))));
+    }
+    rows
+}
+
+/// `status`: a running/not-running line, then the known fields aligned
+fn write_status(out: &mut impl Write, err_out: &mut impl Write, opts: &OutputOptions, data: &Value) -> io::Result<()> {
+    match data.get("launched").and_then(|v| v.as_bool()) {
+        Some(true) => {
+            let headless = data.get("headless").and_then(|v| v.as_bool()).unwrap_or(true);
+            let mode = if headless { "headless" } else { "headed" };
+            success(out, err_out, opts, format_args!("Browser running ({}{})", mode, stealth_label(data)))?;
+        }
+        Some(false) => writeln!(out, "{} Browser not running", color::dim("○"))?,
+        // An older daemon that doesn't say
+        None => success(out, err_out, opts, "Daemon running")?,
+    }
+    if opts.quiet {
+        return Ok(());
+    }
+    let rows = status_rows(data);
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, value) in rows {
+        let value = match value {
+            StatusValue::Flag(true) => color::green("yes"),
+            StatusValue::Flag(false) => color::red("no"),
+            StatusValue::Text(text) => text,
+        };
+        writeln!(out, "  {}  {}", color::dim(&format!("{:width$}", label, width = width)), value)?;
+    }
+    Ok(())
+}
+
+/// `find ... locate`: match count, then one `@ref  text` line per match
+fn write_located(out: &mut impl Write, data: &Value) -> io::Result<()> {
+    let empty = Vec::new();
+    let matches = data.get("matches").and_then(|v| v.as_array()).unwrap_or(&empty);
+    let count = data.get("count").and_then(|v| v.as_u64()).unwrap_or(matches.len() as u64);
+    if count == 0 {
+        return writeln!(out, "{}", color::dim("No matches"));
+    }
+    writeln!(out, "{} {}", count, if count == 1 { "match" } else { "matches" })?;
+    let (refs, width) = match_refs(matches);
+    for (r, m) in refs.iter().zip(matches) {
+        let text = m.get("text").or_else(|| m.get("name")).and_then(|v| v.as_str()).unwrap_or("");
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        writeln!(out, "  {:<width$}  {}", r, text, width = width)?;
+    }
+    Ok(())
+}
+
+/// `get article`: bold title, then the text (or cleaned HTML with `--html`),
+/// cut to `--max-chars` with a note saying how much was left out
+fn write_article(out: &mut impl Write, data: &Value, request: &Value) -> io::Result<()> {
+    let html = request.get("html").and_then(|v| v.as_bool()).unwrap_or(false);
+    let body = data
+        .get(if html { "content" } else { "textContent" })
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    if let Some(title) = data.get("title").and_then(|v| v.as_str()).filter(|t| !t.is_empty()) {
+        writeln!(out, "{}", color::bold(title))?;
+        if let Some(byline) = data.get("byline").and_then(|v| v.as_str()).filter(|b| !b.is_empty()) {
+            writeln!(out, "{}", color::dim(byline))?;
+        }
+        writeln!(out)?;
+    }
+    let total = body.chars().count();
+    match request.get("maxChars").and_then(|v| v.as_u64()).map(|n| n as usize) {
+        Some(max) if total > max => {
+            let cut: String = body.chars().take(max).collect();
+            writeln!(out, "{}…", cut.trim_end())?;
+            writeln!(out, "{}", color::dim(&format!("(truncated: showing {} of {} characters)", max, total)))
+        }
+        _ => writeln!(out, "{}", body),
+    }
+}
+
+/// Print a daemon response. `request` is the command that produced it, used
+/// for client-side rendering options (e.g. `errors --short`).
+pub fn print_response(resp: &Response, opts: &OutputOptions, request: &Value) {
+    let _ = write_response(&mut io::stdout(), &mut io::stderr(), resp, opts, request);
+}
+
+fn write_response(
+    out: &mut impl Write,
+    err_out: &mut impl Write,
+    resp: &Response,
+    opts: &OutputOptions,
+    request: &Value,
+) -> io::Result<()> {
+    if opts.debug {
+        writeln!(err_out, "{}", debug_line("←", resp))?;
+    }
+
+    if opts.json {
+        let duration_ms = opts.duration_ms;
+        if !resp.success {
+            let error = resp.error.as_deref().unwrap_or("Unknown error");
+            let body = ErrorResponse { success: false, error, code: ErrorCode::for_command_error(error) };
+            writeln!(out, "{}", serde_json::to_string(&Timed { body, duration_ms }).unwrap_or_default())?;
+        } else if let Some(items) = opts.jsonl.then(|| jsonl_items(resp)).flatten() {
+            for item in items {
+                writeln!(out, "{}", serde_json::to_string(item).unwrap_or_default())?;
+            }
+        } else {
+            writeln!(out, "{}", serde_json::to_string(&Timed { body: resp, duration_ms }).unwrap_or_default())?;
+        }
+        return Ok(());
     }
 
     if !resp.success {
-        eprintln!(
-            "{} {}",
-            color::error_indicator(),
-            resp.error.as_deref().unwrap_or("Unknown error")
-        );
-        return;
+        let error = resp.error.as_deref().unwrap_or("Unknown error");
+        if request.get("action").and_then(|v| v.as_str()) == Some("evaluate") {
+            let awaited = request.get("await").and_then(|v| v.as_bool()).unwrap_or(false);
+            if let Some(label) = eval_error_label(error, awaited) {
+                let error = error.strip_prefix(REJECTED_PREFIX).unwrap_or(error);
+                writeln!(err_out, "{} {}: {}", color::error_indicator(), label, error)?;
+                return Ok(());
+            }
+        }
+        writeln!(err_out, "{} {}", color::error_indicator(), error)?;
+        return Ok(());
     }
 
     if let Some(data) = &resp.data {
+        // Readable article: checked first since it also carries a title
+        if request.get("action").and_then(|v| v.as_str()) == Some("article") {
+            return write_article(out, data, request);
+        }
+        // Text search matches
+        if request.get("action").and_then(|v| v.as_str()) == Some("textsearch") {
+            return write_text_matches(out, data, request);
+        }
+        // Daemon health (ping)
+        if request.get("action").and_then(|v| v.as_str()) == Some("ping") {
+            let version = data.get("daemonVersion").and_then(|v| v.as_str()).unwrap_or("unknown version");
+            let mut details = Vec::new();
+            if let Some(pid) = data.get("pid").and_then(|v| v.as_u64()) {
+                details.push(format!("pid {}", pid));
+            }
+            if let Some(ms) = data.get("uptime").and_then(|v| v.as_u64()) {
+                details.push(format!("up {}", format_uptime(ms / 1000)));
+            }
+            let details = if details.is_empty() { String::new() } else { format!(" ({})", details.join(", ")) };
+            return writeln!(out, "daemon {}{}", version, details);
+        }
+        // HTTP response from fetch
+        if request.get("action").and_then(|v| v.as_str()) == Some("fetch") {
+            return write_fetch(out, err_out, opts, data, request);
+        }
+        // Endpoint found by connect --discover
+        if request.get("cdpDiscover").and_then(|v| v.as_bool()) == Some(true) {
+            if let Some(endpoint) = data.get("cdpEndpoint").and_then(|v| v.as_str()) {
+                let browser = data.get("browser").and_then(|v| v.as_str()).map(|b| format!(" ({})", b)).unwrap_or_default();
+                return success(out, err_out, opts, format!("Connected to {}{}", endpoint, browser));
+            }
+        }
+        // Which of several selectors showed up (wait --any/--all)
+        if request.get("action").and_then(|v| v.as_str()) == Some("wait_multi") {
+            match data.get("matched") {
+                Some(Value::String(selector)) => return success(out, err_out, opts, format!("Matched {}", selector)),
+                Some(Value::Array(selectors)) => {
+                    let selectors: Vec<&str> = selectors.iter().filter_map(|s| s.as_str()).collect();
+                    return success(out, err_out, opts, format!("Matched all: {}", selectors.join(", ")));
+                }
+                _ => {}
+            }
+        }
+        // Status: checked early since it may carry a tab list
+        if request.get("action").and_then(|v| v.as_str()) == Some("status") || data.get("launched").is_some() {
+            return write_status(out, err_out, opts, data);
+        }
+        // Located elements (find ... locate)
+        if request.get("subaction").and_then(|v| v.as_str()) == Some("locate") {
+            return write_located(out, data);
+        }
         // Navigation response
         if let Some(url) = data.get("url").and_then(|v| v.as_str()) {
             if let Some(title) = data.get("title").and_then(|v| v.as_str()) {
-                println!("{} {}", color::success_indicator(), color::bold(title));
-                println!("  {}", color::dim(url));
-                return;
+                if !opts.quiet {
+                    success(out, err_out, opts, color::bold(title))?;
+                    if opts.status_to_stderr {
+                        writeln!(err_out, "  {}", color::dim(url))?;
+                    } else {
+                        writeln!(out, "  {}", color::dim(url))?;
+                    }
+                }
+                return Ok(());
             }
-            println!("{}", url);
-            return;
+            writeln!(out, "{}", url)?;
+            return Ok(());
         }
         // Snapshot
-        if let Some(snapshot) = data.get("snapshot").and_then(|v| v.as_str()) {
-            println!("{}", snapshot);
-            return;
+        if let Some(snapshot) = data.get("snapshot") {
+            match snapshot.as_str() {
+                Some(text) => writeln!(out, "{}", text)?,
+                // --json-tree: structured tree, printed as-is
+                None => writeln!(out, "{}", serde_json::to_string_pretty(snapshot).unwrap_or_default())?,
+            }
+            return Ok(());
         }
         // Title
         if let Some(title) = data.get("title").and_then(|v| v.as_str()) {
-            println!("{}", title);
-            return;
+            writeln!(out, "{}", title)?;
+            return Ok(());
+        }
+        // Clipboard contents, exactly as read
+        if let Some(text) = data.get("clipboardText").and_then(|v| v.as_str()) {
+            writeln!(out, "{}", text)?;
+            return Ok(());
         }
         // Text
         if let Some(text) = data.get("text").and_then(|v| v.as_str()) {
-            println!("{}", text);
-            return;
+            writeln!(out, "{}", text)?;
+            return Ok(());
         }
         // HTML
         if let Some(html) = data.get("html").and_then(|v| v.as_str()) {
-            println!("{}", html);
-            return;
+            writeln!(out, "{}", html)?;
+            return Ok(());
+        }
+        // Storage (all keys)
+        if let Some(lines) = format_storage_entries(data) {
+            for line in lines {
+                writeln!(out, "{}", line)?;
+            }
+            return Ok(());
+        }
+        // Storage (single key that is not set)
+        if data.get("key").is_some() && data.get("value").is_some_and(|v| v.is_null()) {
+            writeln!(out, "{}", color::dim("(not set)"))?;
+            return Ok(());
         }
-        // Value
+        // Multi-select: one selected value per line
+        if let Some(values) = data.get("values").and_then(|v| v.as_array()) {
+            for value in values {
+                match value.as_str() {
+                    Some(s) => writeln!(out, "{}", s)?,
+                    None => writeln!(out, "{}", value)?,
+                }
+            }
+            return Ok(());
+        }
+        // Value (checkboxes report their checked state)
         if let Some(value) = data.get("value").and_then(|v| v.as_str()) {
-            println!("{}", value);
-            return;
+            writeln!(out, "{}", value)?;
+            return Ok(());
+        }
+        if let Some(value) = data.get("value").and_then(|v| v.as_bool()) {
+            writeln!(out, "{}", value)?;
+            return Ok(());
         }
         // Count
         if let Some(count) = data.get("count").and_then(|v| v.as_i64()) {
-            println!("{}", count);
-            return;
+            writeln!(out, "{}", count)?;
+            return Ok(());
         }
         // Boolean results
         if let Some(visible) = data.get("visible").and_then(|v| v.as_bool()) {
-            println!("{}", visible);
-            return;
+            writeln!(out, "{}", visible)?;
+            return Ok(());
         }
         if let Some(enabled) = data.get("enabled").and_then(|v| v.as_bool()) {
-            println!("{}", enabled);
-            return;
+            writeln!(out, "{}", enabled)?;
+            return Ok(());
         }
         if let Some(checked) = data.get("checked").and_then(|v| v.as_bool()) {
-            println!("{}", checked);
-            return;
+            writeln!(out, "{}", checked)?;
+            return Ok(());
         }
-        // Eval result
+        // Eval result: a string bare, like text; anything else as JSON
         if let Some(result) = data.get("result") {
-            println!(
-                "{}",
-                serde_json::to_string_pretty(result).unwrap_or_default()
-            );
-            return;
+            match result.as_str() {
+                Some(s) => writeln!(out, "{}", s)?,
+                None => writeln!(out, "{}", serde_json::to_string_pretty(result).unwrap_or_default())?,
+            }
+            return Ok(());
         }
         // Tabs
         if let Some(tabs) = data.get("tabs").and_then(|v| v.as_array()) {
@@ -88,114 +558,376 @@ pub fn print_response(resp: &Response, json_mode: bool) {
                 let url = tab.get("url").and_then(|v| v.as_str()).unwrap_or("");
                 let active = tab.get("active").and_then(|v| v.as_bool()).unwrap_or(false);
                 let marker = if active { "→" } else { " " };
-                println!("{} [{}] {} - {}", marker, i, title, url);
+                writeln!(out, "{} [{}] {} - {}", marker, i, title, url)?;
+            }
+            return Ok(());
+        }
+        // History
+        if let Some(entries) = data.get("entries").and_then(|v| v.as_array()) {
+            let current = data.get("current").and_then(|v| v.as_u64());
+            for (i, entry) in entries.iter().enumerate() {
+                let title = entry
+                    .get("title")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Untitled");
+                let url = entry.get("url").and_then(|v| v.as_str()).unwrap_or("");
+                let marker = if current == Some(i as u64) { "→" } else { " " };
+                writeln!(out, "{} [{}] {} - {}", marker, i, title, url)?;
             }
-            return;
+            return Ok(());
         }
         // Console logs
         if let Some(logs) = data.get("messages").and_then(|v| v.as_array()) {
             for log in logs {
                 let level = log.get("type").and_then(|v| v.as_str()).unwrap_or("log");
                 let text = log.get("text").and_then(|v| v.as_str()).unwrap_or("");
-                println!("{} {}", color::console_level_prefix(level), text);
+                writeln!(out, "{} {}", color::console_level_prefix(level), text)?;
             }
-            return;
+            return Ok(());
         }
         // Errors
         if let Some(errors) = data.get("errors").and_then(|v| v.as_array()) {
+            // --stack wins over --short
+            let short = request.get("short").and_then(|v| v.as_bool()).unwrap_or(false)
+                && request.get("includeStack").is_none();
             for err in errors {
-                let msg = err.get("message").and_then(|v| v.as_str()).unwrap_or("");
-                println!("{} {}", color::error_indicator(), msg);
+                for line in format_page_error(err, short) {
+                    writeln!(out, "{}", line)?;
+                }
             }
-            return;
+            return Ok(());
         }
         // Cookies
         if let Some(cookies) = data.get("cookies").and_then(|v| v.as_array()) {
             for cookie in cookies {
                 let name = cookie.get("name").and_then(|v| v.as_str()).unwrap_or("");
                 let value = cookie.get("value").and_then(|v| v.as_str()).unwrap_or("");
-                println!("{}={}", name, value);
+                writeln!(out, "{}={}", name, value)?;
             }
-            return;
+            return Ok(());
         }
         // Bounding box
         if let Some(box_data) = data.get("box") {
-            println!(
-                "{}",
-                serde_json::to_string_pretty(box_data).unwrap_or_default()
-            );
-            return;
+            match format_box(box_data) {
+                Some(line) => writeln!(out, "{}", line)?,
+                None => writeln!(
+                    out,
+                    "{}",
+                    serde_json::to_string_pretty(box_data).unwrap_or_default()
+                )?,
+            }
+            return Ok(());
         }
         // Closed
         if data.get("closed").is_some() {
-            println!("{} Browser closed", color::success_indicator());
-            return;
-        }
-        // Status
-        if data.get("launched").is_some() {
-            let launched = data.get("launched").and_then(|v| v.as_bool()).unwrap_or(false);
-            if !launched {
-                println!("{} Browser not running", color::dim("○"));
-                return;
-            }
-            let headless = data.get("headless").and_then(|v| v.as_bool()).unwrap_or(true);
-            let stealth = data.get("stealth").and_then(|v| v.as_bool()).unwrap_or(false);
-            let mode = if headless { "headless" } else { "headed" };
-            let stealth_str = if stealth { " + stealth" } else { "" };
-            println!("{} Browser running ({}{})", color::success_indicator(), mode, stealth_str);
-            return;
+            success(out, err_out, opts, "Browser closed")?;
+            return Ok(());
         }
         // Configured (from start command)
         if data.get("configured").is_some() {
             let headless = data.get("headless").and_then(|v| v.as_bool()).unwrap_or(true);
-            let stealth = data.get("stealth").and_then(|v| v.as_bool()).unwrap_or(false);
             let mode = if headless { "headless" } else { "headed" };
-            let stealth_str = if stealth { " + stealth" } else { "" };
-            println!("{} Browser started ({}{})", color::success_indicator(), mode, stealth_str);
-            return;
+            success(out, err_out, opts, format_args!("Browser started ({}{})", mode, stealth_label(data)))?;
+            return Ok(());
         }
         // Recording start (has "started" field)
         if let Some(started) = data.get("started").and_then(|v| v.as_bool()) {
             if started {
                 if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
-                    println!("{} Recording started: {}", color::success_indicator(), path);
+                    success(out, err_out, opts, format_args!("Recording started: {}", path))?;
                 } else {
-                    println!("{} Recording started", color::success_indicator());
+                    success(out, err_out, opts, "Recording started")?;
                 }
-                return;
+                return Ok(());
             }
         }
         // Recording restart (has "stopped" field - from recording_restart action)
         if data.get("stopped").is_some() {
             let path = data.get("path").and_then(|v| v.as_str()).unwrap_or("unknown");
             if let Some(prev_path) = data.get("previousPath").and_then(|v| v.as_str()) {
-                println!("{} Recording restarted: {} (previous saved to {})", color::success_indicator(), path, prev_path);
+                success(out, err_out, opts, format_args!("Recording restarted: {} (previous saved to {})", path, prev_path))?;
             } else {
-                println!("{} Recording started: {}", color::success_indicator(), path);
+                success(out, err_out, opts, format_args!("Recording started: {}", path))?;
             }
-            return;
+            return Ok(());
         }
         // Recording stop (has "frames" field - from recording_stop action)
         if data.get("frames").is_some() {
             if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
                 if let Some(error) = data.get("error").and_then(|v| v.as_str()) {
-                    println!("{} Recording saved to {} - {}", color::warning_indicator(), path, error);
+                    writeln!(out, "{} Recording saved to {} - {}", color::warning_indicator(), path, error)?;
                 } else {
-                    println!("{} Recording saved to {}", color::success_indicator(), path);
+                    success(out, err_out, opts, format_args!("Recording saved to {}", path))?;
+                }
+                if let Some(exported) = data.get("exported").and_then(|v| v.as_str()) {
+                    success(out, err_out, opts, format_args!("Exported to {}", exported))?;
                 }
             } else {
-                println!("{} Recording stopped", color::success_indicator());
+                success(out, err_out, opts, "Recording stopped")?;
+            }
+            return Ok(());
+        }
+        // Saved page (save <path>)
+        if matches!(request.get("action").and_then(|v| v.as_str()), Some("savemhtml") | Some("savehtml")) {
+            let path = data.get("path").and_then(|v| v.as_str()).unwrap_or("unknown");
+            match data.get("size").and_then(|v| v.as_u64()) {
+                Some(size) => success(out, err_out, opts, format_args!("Page saved to {} ({} bytes)", path, size))?,
+                None => success(out, err_out, opts, format_args!("Page saved to {}", path))?,
             }
-            return;
+            return Ok(());
+        }
+        // Screenshot data (no path given)
+        if let Some(base64) = data.get("base64").and_then(|v| v.as_str()) {
+            writeln!(out, "{}", base64)?;
+            return Ok(());
         }
         // Screenshot path (no "started" or "frames" field)
         if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
-            println!("{} Screenshot saved to {}", color::success_indicator(), path);
-            return;
+            success(out, err_out, opts, format_args!("Screenshot saved to {}", path))?;
+            return Ok(());
         }
         // Default success
-        println!("{} Done", color::success_indicator());
+        success(out, err_out, opts, "Done")?;
+    }
+    Ok(())
+}
+
+/// A `✓` confirmation line, dropped under `--quiet` and sent to stderr when
+/// stdout is redirected to a file with `-o`
+fn success(
+    out: &mut impl Write,
+    err_out: &mut impl Write,
+    opts: &OutputOptions,
+    msg: impl Display,
+) -> io::Result<()> {
+    if opts.quiet {
+        return Ok(());
+    }
+    if opts.status_to_stderr {
+        writeln!(err_out, "{} {}", color::success_indicator(), msg)
+    } else {
+        writeln!(out, "{} {}", color::success_indicator(), msg)
+    }
+}
+
+/// `fetch`: the status line on stderr so the body can be piped, headers
+/// with `--include`, then the body (JSON pretty-printed unless `--raw`)
+fn write_fetch(
+    out: &mut impl Write,
+    err_out: &mut impl Write,
+    opts: &OutputOptions,
+    data: &Value,
+    request: &Value,
+) -> io::Result<()> {
+    let status = data.get("status").and_then(|v| v.as_u64()).unwrap_or(0);
+    let status_text = data.get("statusText").and_then(|v| v.as_str()).unwrap_or("");
+    if !opts.quiet {
+        let line = format!("HTTP {} {}", status, status_text);
+        let line = if is_http_success(status) { color::green(line.trim_end()) } else { color::red(line.trim_end()) };
+        writeln!(err_out, "{}", line)?;
+    }
+    if request.get("include").and_then(|v| v.as_bool()) == Some(true) {
+        if let Some(headers) = data.get("headers").and_then(|v| v.as_object()) {
+            for (name, value) in headers {
+                writeln!(out, "{}: {}", name, value.as_str().map(String::from).unwrap_or_else(|| value.to_string()))?;
+            }
+        }
+        writeln!(out)?;
+    }
+    let body = data.get("body").and_then(|v| v.as_str()).unwrap_or("");
+    let raw = request.get("raw").and_then(|v| v.as_bool()) == Some(true);
+    let pretty = (!raw).then(|| serde_json::from_str::<Value>(body).ok()).flatten();
+    match pretty.and_then(|v| serde_json::to_string_pretty(&v).ok()) {
+        Some(json) => writeln!(out, "{}", json),
+        None if body.is_empty() || body.ends_with('\n') => write!(out, "{}", body),
+        None => writeln!(out, "{}", body),
+    }
+}
+
+fn is_http_success(status: u64) -> bool {
+    (200..300).contains(&status)
+}
+
+/// Whether a `fetch` got a non-2xx status and wasn't given `--no-fail`
+pub fn fetch_failed(request: &Value, resp: &Response) -> bool {
+    if request.get("action").and_then(|v| v.as_str()) != Some("fetch") || request.get("noFail").is_some() {
+        return false;
+    }
+    let status = resp.data.as_ref().and_then(|d| d.get("status")).and_then(|v| v.as_u64());
+    status.is_some_and(|s| !is_http_success(s))
+}
+
+/// `-o <path>`: write what would go to stdout into `path`. Screenshot base64
+/// is decoded to raw bytes when the target is a .png/.jpg/.jpeg file.
+pub fn write_response_to_file(
+    path: &str,
+    resp: &Response,
+    opts: &OutputOptions,
+    request: &Value,
+) -> Result<(), String> {
+    if !resp.success {
+        // Nothing to save; report the error as usual and leave any file alone
+        print_response(resp, opts, request);
+        return Ok(());
+    }
+    let io_err = |e: io::Error| format!("Failed to write {}: {}", path, e);
+    let image_target = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| matches!(e.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg"));
+    let base64 = resp
+        .data
+        .as_ref()
+        .and_then(|d| d.get("base64"))
+        .and_then(|v| v.as_str());
+
+    if let (true, Some(encoded)) = (image_target, base64) {
+        let bytes = decode_base64(encoded).ok_or_else(|| "Screenshot data is not valid base64".to_string())?;
+        fs::write(path, bytes).map_err(io_err)?;
+    } else {
+        let mut file = fs::File::create(path).map_err(io_err)?;
+        let opts = OutputOptions { status_to_stderr: true, ..*opts };
+        write_response(&mut file, &mut io::stderr(), resp, &opts, request).map_err(io_err)?;
+    }
+
+    if !opts.quiet && !opts.json {
+        eprintln!("{} Output written to {}", color::success_indicator(), path);
+    }
+    Ok(())
+}
+
+/// Standard (RFC 4648) base64 decoding; whitespace is ignored.
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buf: u32 = 0;
+    let mut bits = 0;
+    for c in input.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        buf = (buf << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buf >> bits) as u8);
+        }
     }
+    Some(bytes)
+}
+
+/// Format a page error: the message line, then (unless `short`) the
+/// `url:line:column` location and the dimmed, indented stack when present.
+fn format_page_error(err: &Value, short: bool) -> Vec<String> {
+    let msg = err.get("message").and_then(|v| v.as_str()).unwrap_or("");
+    let mut lines = vec![format!("{} {}", color::error_indicator(), msg)];
+    if short {
+        return lines;
+    }
+
+    if let Some(url) = err.get("url").and_then(|v| v.as_str()).filter(|u| !u.is_empty()) {
+        let mut location = url.to_string();
+        if let Some(line) = err.get("line").and_then(|v| v.as_u64()) {
+            location.push_str(&format!(":{}", line));
+            if let Some(column) = err.get("column").and_then(|v| v.as_u64()) {
+                location.push_str(&format!(":{}", column));
+            }
+        }
+        lines.push(format!("    at {}", location));
+    }
+
+    if let Some(stack) = err.get("stack").and_then(|v| v.as_str()) {
+        for frame in stack.lines() {
+            let frame = frame.trim();
+            // Playwright stacks repeat the message as their first line
+            if frame.is_empty() || frame == msg || frame.ends_with(&format!(": {}", msg)) {
+                continue;
+            }
+            lines.push(format!("    {}", color::dim(frame)));
+        }
+    }
+
+    lines
+}
+
+/// Prefix the daemon puts on an awaited `eval` whose promise rejected
+const REJECTED_PREFIX: &str = "Promise rejected: ";
+
+/// Label an `eval` failure so a script that never ran (syntax error) is easy
+/// to tell apart from an awaited promise that rejected or a script that threw.
+fn eval_error_label(error: &str, awaited: bool) -> Option<&'static str> {
+    if error.starts_with(REJECTED_PREFIX) {
+        Some("Promise rejected")
+    } else if error.contains("SyntaxError") {
+        Some("Syntax error in script")
+    } else if awaited {
+        Some("Script threw")
+    } else {
+        None
+    }
+}
+
+/// Format a bounding box as `x=.. y=.. width=.. height=..`, or None if any
+/// of the fields is missing or non-numeric.
+fn format_box(box_data: &Value) -> Option<String> {
+    let field = |name: &str| box_data.get(name).and_then(|v| v.as_f64());
+    Some(format!(
+        "x={} y={} width={} height={}",
+        field("x")?,
+        field("y")?,
+        field("width")?,
+        field("height")?
+    ))
+}
+
+/// Format an all-keys storage dump as `key=value` lines.
+/// Accepts either a map (`{"k": "v"}`) or an array of `{key, value}` entries
+/// under `storage` (or `data`, as returned by the daemon's storage_get).
+fn format_storage_entries(data: &Value) -> Option<Vec<String>> {
+    let entries = data.get("storage").or_else(|| data.get("data"))?;
+    let value_str = |v: &Value| match v {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    };
+
+    if let Some(map) = entries.as_object() {
+        if map.is_empty() {
+            return Some(vec![color::dim("(empty)")]);
+        }
+        return Some(
+            map.iter()
+                .map(|(k, v)| format!("{}={}", k, value_str(v)))
+                .collect(),
+        );
+    }
+
+    if let Some(items) = entries.as_array() {
+        if items.is_empty() {
+            return Some(vec![color::dim("(empty)")]);
+        }
+        return Some(
+            items
+                .iter()
+                .map(|item| {
+                    let key = item
+                        .get("key")
+                        .or_else(|| item.get("name"))
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    let value = item.get("value").map(value_str).unwrap_or_default();
+                    format!("{}={}", key, value)
+                })
+                .collect(),
+        );
+    }
+
+    None
 }
 
 /// Print command-specific help. Returns true if help was printed, false if command unknown.
@@ -205,13 +937,17 @@ pub fn print_command_help(command: &str) -> bool {
         "open" | "goto" | "navigate" => r##"
 z-agent-browser open - Navigate to a URL
 
-Usage: z-agent-browser open <url>
+Usage: z-agent-browser open <url> [options]
 
 Navigates the browser to the specified URL. If no protocol is provided,
 https:// is automatically prepended.
 
 Aliases: goto, navigate
 
+Options:
+  --wait-until <state>  Wait for load, domcontentloaded, networkidle or commit
+  --referer <url>       Send this Referer with the navigation
+
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
@@ -222,16 +958,17 @@ Examples:
   z-agent-browser open example.com
   z-agent-browser open https://github.com
   z-agent-browser open localhost:3000
+  z-agent-browser open app.example.com --wait-until networkidle
   z-agent-browser open api.example.com --headers '{"Authorization": "Bearer token"}'
     # ^ Headers only sent to api.example.com, not other domains
 "##,
         "back" => r##"
 z-agent-browser back - Navigate back in history
 
-Usage: z-agent-browser back
+Usage: z-agent-browser back [n]
 
-Goes back one page in the browser history, equivalent to clicking
-the browser's back button.
+Goes back n pages (default 1) in the browser history, equivalent to
+clicking the browser's back button n times.
 
 Global Options:
   --json               Output as JSON
@@ -239,14 +976,15 @@ Global Options:
 
 Examples:
   z-agent-browser back
+  z-agent-browser back 3
 "##,
         "forward" => r##"
 z-agent-browser forward - Navigate forward in history
 
-Usage: z-agent-browser forward
+Usage: z-agent-browser forward [n]
 
-Goes forward one page in the browser history, equivalent to clicking
-the browser's forward button.
+Goes forward n pages (default 1) in the browser history, equivalent to
+clicking the browser's forward button n times.
 
 Global Options:
   --json               Output as JSON
@@ -254,21 +992,43 @@ Global Options:
 
 Examples:
   z-agent-browser forward
+  z-agent-browser forward 2
 "##,
         "reload" => r##"
 z-agent-browser reload - Reload the current page
 
-Usage: z-agent-browser reload
+Usage: z-agent-browser reload [--hard]
 
 Reloads the current page, equivalent to pressing F5 or clicking
 the browser's reload button.
 
+Options:
+  --hard               Bypass the cache (like Shift+F5)
+
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
   z-agent-browser reload
+  z-agent-browser reload --hard
+"##,
+        "history" => r##"
+z-agent-browser history - Inspect and move through navigation history
+
+Usage: z-agent-browser history [back|forward] [n]
+
+Lists the current tab's navigation entries, marking the current one with →.
+With back/forward, jumps n entries (default 1) in one step.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser history
+  z-agent-browser history back 3
+  z-agent-browser history forward 2
 "##,
 
         // === Core Actions ===
@@ -314,6 +1074,9 @@ Usage: z-agent-browser fill <selector> <text>
 Clears the input field and fills it with the specified text.
 This replaces any existing content in the field.
 
+Pass - as the text to read it from stdin instead, which keeps secrets
+out of the process list and avoids shell quoting.
+
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
@@ -322,14 +1085,52 @@ Examples:
   z-agent-browser fill "#email" "user@example.com"
   z-agent-browser fill @e3 "Hello World"
   z-agent-browser fill "input[name='search']" "query"
+  echo "$PASSWORD" | z-agent-browser fill "#password" -
+"##,
+        "form" => r##"
+z-agent-browser form - Fill a form from a JSON file
+
+Usage: z-agent-browser form fill <file.json>
+
+Reads a JSON object mapping fields to values and runs one command per
+field, in file order, over a single connection. Every field is attempted;
+the command exits 1 if any failed.
+
+Keys:
+  <selector>           CSS selector or @ref
+  label:<text>         Field by its label
+  placeholder:<text>   Field by its placeholder
+  testid:<id>          Field by data-testid
+
+Values:
+  "text" or 42         fill
+  true / false         check / uncheck
+  ["a", "b"]           select option(s)
+
+Global Options:
+  --dry-run            Print the commands without running them
+  --json               Output {"fields":[{"field","action","success","error"}]}
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser form fill signup.json
+  z-agent-browser form fill signup.json --dry-run
+
+  signup.json:
+    {"label:Email": "ada@example.com", "#age": 36,
+     "testid:terms": true, "select[name=langs]": ["en", "fr"]}
 "##,
         "type" => r##"
 z-agent-browser type - Type text into an element
 
-Usage: z-agent-browser type <selector> <text>
+Usage: z-agent-browser type <selector> <text> [--clear]
 
 Types text into the specified element character by character.
 Unlike fill, this does not clear existing content first.
+Pass - as the text to read it from stdin.
+
+Options:
+  --clear              Clear the field first, then type key by key
 
 Global Options:
   --json               Output as JSON
@@ -338,6 +1139,8 @@ Global Options:
 Examples:
   z-agent-browser type "#search" "hello"
   z-agent-browser type @e2 "additional text"
+  z-agent-browser type "#search" "new query" --clear
+  cat message.txt | z-agent-browser type "#body" -
 "##,
         "hover" => r##"
 z-agent-browser hover - Hover over an element
@@ -418,10 +1221,14 @@ Examples:
         "drag" => r##"
 z-agent-browser drag - Drag and drop
 
-Usage: z-agent-browser drag <source> <target>
+Usage: z-agent-browser drag <source> <target> [--steps <n>] [--hold <ms>]
 
 Drags an element from source to target location.
 
+Options:
+  --steps <n>          Move in n steps, firing dragover along the path
+  --hold <ms>          Pause over the target before dropping
+
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
@@ -429,13 +1236,19 @@ Global Options:
 Examples:
   z-agent-browser drag "#draggable" "#drop-zone"
   z-agent-browser drag @e1 @e2
+  z-agent-browser drag @e1 @e2 --steps 10 --hold 500
 "##,
         "upload" => r##"
 z-agent-browser upload - Upload files
 
-Usage: z-agent-browser upload <selector> <files...>
+Usage: z-agent-browser upload <selector> <files...|dir> [--glob <pattern>]
 
-Uploads one or more files to a file input element.
+Uploads one or more files to a file input element. A single directory
+argument uploads the files directly inside it. Paths are checked before
+anything is sent.
+
+Options:
+  --glob <pattern>     Add files matching a pattern (* and ? in the file name)
 
 Global Options:
   --json               Output as JSON
@@ -444,6 +1257,8 @@ Global Options:
 Examples:
   z-agent-browser upload "#file-input" ./document.pdf
   z-agent-browser upload @e3 ./image1.png ./image2.png
+  z-agent-browser upload @e3 ./fixtures/
+  z-agent-browser upload @e3 --glob "./screenshots/*.png"
 "##,
 
         // === Keyboard ===
@@ -563,6 +1378,11 @@ Modes:
   --load <state>       Wait for load state (load, domcontentloaded, networkidle)
   --fn <expression>    Wait for JavaScript expression to be truthy
   --text <text>        Wait for text to appear on page
+  --any <selector>...  Wait until one of the selectors appears; prints which
+  --all <selector>...  Wait until every selector has appeared
+
+Options:
+  --timeout <ms>       Give up on the condition after this long (-t is --text)
 
 Global Options:
   --json               Output as JSON
@@ -570,11 +1390,13 @@ Global Options:
 
 Examples:
   z-agent-browser wait "#loading-spinner"
+  z-agent-browser wait "#results" --timeout 10000
   z-agent-browser wait 2000
   z-agent-browser wait --url "**/dashboard"
   z-agent-browser wait --load networkidle
   z-agent-browser wait --fn "window.appReady === true"
   z-agent-browser wait --text "Welcome back"
+  z-agent-browser wait --any "#toast-success" "#toast-error" --timeout 10000
 "##,
 
         // === Screenshot/PDF ===
@@ -613,6 +1435,25 @@ Examples:
   z-agent-browser pdf ./page.pdf
   z-agent-browser pdf ~/Documents/report.pdf
 "##,
+        "save" => r##"
+z-agent-browser save - Save an archival copy of the page
+
+Usage: z-agent-browser save <path>
+
+The file extension picks the format:
+  .mhtml               MHTML archive (page plus resources)
+  .html                Single HTML file with resources inlined
+
+Relative paths are resolved against the current directory.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser save ./page.mhtml
+  z-agent-browser save ~/archive/article.html
+"##,
 
         // === Snapshot ===
         "snapshot" => r##"
@@ -629,6 +1470,7 @@ Options:
   -c, --compact        Remove empty structural elements
   -d, --depth <n>      Limit tree depth
   -s, --selector <sel> Scope snapshot to CSS selector
+  --json-tree          Return a nested JSON tree of roles/names/refs
 
 Global Options:
   --json               Output as JSON
@@ -639,15 +1481,23 @@ Examples:
   z-agent-browser snapshot -i
   z-agent-browser snapshot --compact --depth 5
   z-agent-browser snapshot -s "#main-content"
+  z-agent-browser snapshot -i --json-tree
 "##,
 
         // === Eval ===
         "eval" => r##"
 z-agent-browser eval - Execute JavaScript
 
-Usage: z-agent-browser eval <script>
+Usage: z-agent-browser eval <script> [--await|--no-await]
 
 Executes JavaScript code in the browser context and returns the result.
+Scripts that use await or Promises are awaited automatically, so the
+settled value is returned and rejections are reported as such.
+Pass - as the script to read it from stdin.
+
+Options:
+  --await              Always await the result
+  --no-await           Never await the result
 
 Global Options:
   --json               Output as JSON
@@ -657,6 +1507,9 @@ Examples:
   z-agent-browser eval "document.title"
   z-agent-browser eval "window.location.href"
   z-agent-browser eval "document.querySelectorAll('a').length"
+  z-agent-browser eval "await fetch('/api/me').then(r => r.json())"
+  z-agent-browser eval --await "navigator.clipboard.readText()"
+  z-agent-browser eval - < script.js
 "##,
 
         // === Browser Lifecycle ===
@@ -670,8 +1523,11 @@ If browser is already running with different settings, it will restart.
 
 Options:
   --headed             Show browser window (default: headless)
-  --stealth            Enable anti-detection mode (for strict sites)
+  --stealth [level]    Enable anti-detection mode (for strict sites). Level is
+                       full (default), basic, or a comma list of evasions
+                       such as navigator.webdriver,webgl.vendor
   --profile <path>     Use Chrome profile directory
+  --user-data-dir <path>  Persistent Chromium user data directory
 
 Global Options:
   --json               Output as JSON
@@ -689,7 +1545,9 @@ z-agent-browser status - Check browser status and configuration
 
 Usage: z-agent-browser status
 
-Shows current browser state: whether running, headless/headed, stealth mode.
+Shows current browser state: whether running, then whichever of headless,
+backend, profile, proxy, stealth, tab count, uptime, pid and version the
+daemon reports. With --json the daemon's object is printed as-is.
 
 Global Options:
   --json               Output as JSON (recommended for parsing)
@@ -734,6 +1592,23 @@ Examples:
   z-agent-browser close --session mysession
 "##,
 
+        "ping" => r##"
+z-agent-browser ping - Check that the daemon is alive
+
+Usage: z-agent-browser ping
+
+Prints the daemon's version, pid and uptime. A warning is shown whenever
+the daemon was started by a different CLI version; run 'close' to restart it.
+
+Global Options:
+  --json               Output as JSON (data includes daemonVersion)
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser ping
+  z-agent-browser ping --session scraper --json
+"##,
+
         // === Get ===
         "get" => r##"
 z-agent-browser get - Retrieve information from elements or page
@@ -745,12 +1620,17 @@ Retrieves various types of information from elements or the page.
 Subcommands:
   text <selector>            Get text content of element
   html <selector>            Get inner HTML of element
-  value <selector>           Get value of input element
+    [--outer]                Include the element's own tag and attributes
+  value <selector>           Get value of input element (checked state for
+                             checkboxes, one line per option for multi-selects)
   attr <selector> <name>     Get attribute value
   title                      Get page title
   url                        Get current URL
   count <selector>           Count matching elements
   box <selector>             Get bounding box (x, y, width, height)
+  article                    Main readable content (title + text)
+    [--html]                 Print the cleaned HTML instead of text
+    [--max-chars <n>]        Truncate to n characters
 
 Global Options:
   --json               Output as JSON
@@ -759,12 +1639,14 @@ Global Options:
 Examples:
   z-agent-browser get text @e1
   z-agent-browser get html "#content"
+  z-agent-browser get html "#content" --outer
   z-agent-browser get value "#email-input"
   z-agent-browser get attr "#link" href
   z-agent-browser get title
   z-agent-browser get url
   z-agent-browser get count "li.item"
   z-agent-browser get box "#header"
+  z-agent-browser get article --max-chars 2000
 "##,
 
         // === Is ===
@@ -791,6 +1673,29 @@ Examples:
 "##,
 
         // === Find ===
+        "find-text" => r##"
+z-agent-browser find-text - Search the page for text
+
+Usage: z-agent-browser find-text <needle> [options]
+
+Lists every place the text appears, with surrounding context and the
+ref of the nearest element, so it can be clicked or read next.
+
+Options:
+  --case-sensitive     Match case exactly (default: ignore case)
+  --regex              Treat the needle as a regular expression
+  --count-only         Print only the number of matches
+
+Global Options:
+  --json               Output as JSON (matches array as returned)
+  --jsonl              One match per line
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser find-text refund
+  z-agent-browser find-text "refunds? within \d+ days" --regex
+  z-agent-browser find-text Checkout --case-sensitive --count-only
+"##,
         "find" => r##"
 z-agent-browser find - Find and interact with elements by locator
 
@@ -810,12 +1715,17 @@ Locators:
   last <selector>          Last matching element
   nth <index> <selector>   Nth matching element (0-based)
 
-Actions (default: click):
+Actions (default: click, or locate with --locate):
   click, fill, type, hover, focus, check, uncheck
+  locate                   List matches (count and refs) without interacting
 
 Options:
   --name <name>        Filter role by accessible name
   --exact              Require exact text match
+  --nth <index>        Pick the nth match (0-based, negative counts from end)
+  --has-text <text>    Only matches containing text
+  --within <selector>  Only matches inside a container
+  --locate             Make locate the default action
 
 Global Options:
   --json               Output as JSON
@@ -829,6 +1739,9 @@ Examples:
   z-agent-browser find testid "login-form" click
   z-agent-browser find first "li.item" click
   z-agent-browser find nth 2 ".card" hover
+  z-agent-browser find role button click --name Save --nth 1
+  z-agent-browser find role listitem click --has-text "In cart" --within "#cart"
+  z-agent-browser find text "Log in" --locate
 "##,
 
         // === Mouse ===
@@ -841,9 +1754,10 @@ Performs low-level mouse operations for precise control.
 
 Subcommands:
   move <x> <y>         Move mouse to coordinates
+  click <x> <y>        Click at coordinates (--button left|right|middle)
   down [button]        Press mouse button (left, right, middle)
   up [button]          Release mouse button
-  wheel <dy> [dx]      Scroll mouse wheel
+  wheel <dy> [dx]      Scroll mouse wheel (--steps <n> splits it into n events)
 
 Global Options:
   --json               Output as JSON
@@ -851,11 +1765,14 @@ Global Options:
 
 Examples:
   z-agent-browser mouse move 100 200
+  z-agent-browser mouse click 100 200
+  z-agent-browser mouse click 10 10 --button right
   z-agent-browser mouse down
   z-agent-browser mouse up
   z-agent-browser mouse down right
   z-agent-browser mouse wheel 100
   z-agent-browser mouse wheel -50 0
+  z-agent-browser mouse wheel 1000 0 --steps 10
 "##,
 
         // === Set ===
@@ -868,13 +1785,19 @@ Configures various browser settings and emulation options.
 
 Settings:
   viewport <w> <h>           Set viewport size
+    [--dsf <n>] [--mobile]   Device scale factor / mobile (touch) emulation
   device <name>              Emulate device (e.g., "iPhone 12")
+    [--allow-unknown]        Skip the check against Playwright's device list
   geo <lat> <lng>            Set geolocation
   offline [on|off]           Toggle offline mode
+  headless <on|off>          Hide or show the window of the running browser,
+                             without a restart where the backend supports it
   headers <json>             Set extra HTTP headers
   credentials <user> <pass>  Set HTTP authentication
   media [dark|light]         Set color scheme preference
         [reduced-motion]     Enable reduced motion
+        [forced-colors [active|none]]  Emulate forced colors (high contrast)
+        [print|screen]       Emulate print media type, or back to screen
 
 Global Options:
   --json               Output as JSON
@@ -882,13 +1805,17 @@ Global Options:
 
 Examples:
   z-agent-browser set viewport 1920 1080
+  z-agent-browser set viewport 390 844 --dsf 3 --mobile
   z-agent-browser set device "iPhone 12"
   z-agent-browser set geo 37.7749 -122.4194
   z-agent-browser set offline on
+  z-agent-browser set headless off
   z-agent-browser set headers '{"X-Custom": "value"}'
   z-agent-browser set credentials admin secret123
   z-agent-browser set media dark
   z-agent-browser set media light reduced-motion
+  z-agent-browser set media print
+  z-agent-browser set media forced-colors
 "##,
 
         // === Network ===
@@ -903,6 +1830,10 @@ Subcommands:
   route <url> [options]      Intercept requests matching URL pattern
     --abort                  Abort matching requests
     --body <json>            Respond with custom body
+    --continue               Let requests through, optionally rewritten:
+      --set-url <url>        Send to this URL instead
+      --set-method <METHOD>  Use this HTTP method
+      --set-header <k:v>     Add or replace a header (repeatable)
   unroute [url]              Remove route (all if no URL)
   requests [options]         List captured requests
     --clear                  Clear request log
@@ -915,15 +1846,47 @@ Global Options:
 Examples:
   z-agent-browser network route "**/api/*" --abort
   z-agent-browser network route "**/data.json" --body '{"mock": true}'
+  z-agent-browser network route "**/api/*" --continue --set-header "X-Debug: 1"
   z-agent-browser network unroute
   z-agent-browser network requests
   z-agent-browser network requests --filter "api"
   z-agent-browser network requests --clear
 "##,
 
-        // === Storage ===
-        "storage" => r##"
-z-agent-browser storage - Manage web storage
+        "fetch" => r##"
+z-agent-browser fetch - HTTP request with the browser's cookies
+
+Usage: z-agent-browser fetch <url> [options]
+
+Makes the request from the browser context without loading a page, so it
+carries the session's cookies. Relative URLs resolve against the current
+page. Prints the status line to stderr and the body to stdout; JSON bodies
+are pretty-printed. A non-2xx status exits 1.
+
+Options:
+  --method, -X <m>     HTTP method (default GET, or POST with --data)
+  --data, -d <body>    Request body; @file reads it from a file
+  --header, -H "K: V"  Request header (repeatable)
+  --include, -i        Print the response headers before the body
+  --raw                Print the body as received
+  --no-fail            Exit 0 whatever the status
+  -o, --output <path>  Write the body to a file (the global --output flag)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser fetch /api/me
+  z-agent-browser fetch https://api.example.com/items -X POST -d @item.json \
+    -H "Content-Type: application/json"
+  z-agent-browser fetch /export.csv --raw --output export.csv
+  z-agent-browser fetch /health --include --no-fail
+"##,
+
+        // === Storage ===
+        "storage" => r##"
+z-agent-browser storage - Manage web storage
 
 Usage: z-agent-browser storage <type> [operation] [key] [value]
 
@@ -949,6 +1912,29 @@ Examples:
   z-agent-browser storage local clear
   z-agent-browser storage session get userId
 "##,
+        "clipboard" => r##"
+z-agent-browser clipboard - Read or write the clipboard
+
+Usage: z-agent-browser clipboard read
+       z-agent-browser clipboard write <text>
+       z-agent-browser clipboard write --stdin
+
+Reads the page's clipboard (printed verbatim) or replaces its contents.
+With --stdin the text comes from standard input, minus one trailing newline.
+
+Note: the browser may refuse clipboard access unless the page has been
+granted the clipboard-read / clipboard-write permissions.
+
+Global Options:
+  --json               Output as JSON ({"clipboardText": ...} on read)
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser click "#copy-link"
+  z-agent-browser clipboard read
+  z-agent-browser clipboard write "hello world"
+  printf 'multi\nline' | z-agent-browser clipboard write --stdin
+"##,
 
         // === Cookies ===
         "cookies" => r##"
@@ -960,6 +1946,7 @@ Manage browser cookies for the current context.
 
 Operations:
   get                  Get all cookies (default)
+  get --url <url>      Only cookies that would be sent to <url>
   set <name> <value>   Set a cookie
   clear                Clear all cookies
 
@@ -970,6 +1957,7 @@ Global Options:
 Examples:
   z-agent-browser cookies
   z-agent-browser cookies get
+  z-agent-browser cookies get --url https://example.com/account
   z-agent-browser cookies set session_id "abc123"
   z-agent-browser cookies clear
 "##,
@@ -986,6 +1974,8 @@ Operations:
   list                 List all tabs (default)
   new [url]            Open new tab
   close [index]        Close tab (current if no index)
+  close --all          Close every tab except the active one
+  close --title <text> Close tabs whose title contains text
   <index>              Switch to tab by index
 
 Global Options:
@@ -1000,6 +1990,8 @@ Examples:
   z-agent-browser tab 2
   z-agent-browser tab close
   z-agent-browser tab close 1
+  z-agent-browser tab close --all
+  z-agent-browser tab close --title Gmail
 "##,
 
         // === Window ===
@@ -1076,6 +2068,13 @@ Record a trace for debugging with Playwright Trace Viewer.
 Operations:
   start [path]         Start recording trace
   stop [path]          Stop recording and save trace
+  view <path>          Open a saved trace in the trace viewer (needs npx)
+
+Start Options:
+  --screenshots        Capture screenshots as the page changes
+  --snapshots          Capture DOM snapshots for each action
+  --sources            Include source files of the test/script
+  (Without any of these the daemon's defaults apply)
 
 Global Options:
   --json               Output as JSON
@@ -1084,8 +2083,10 @@ Global Options:
 Examples:
   z-agent-browser trace start
   z-agent-browser trace start ./my-trace
+  z-agent-browser trace start --screenshots --snapshots
   z-agent-browser trace stop
   z-agent-browser trace stop ./debug-trace.zip
+  z-agent-browser trace view ./debug-trace.zip
 "##,
 
         // === Record (video) ===
@@ -1103,8 +2104,15 @@ If no URL is provided, automatically navigates to your current page.
 Operations:
   start <path> [url]     Start recording (defaults to current URL if omitted)
   stop                   Stop recording and save video
+    --gif <out.gif>      Also convert the video to an animated GIF (needs ffmpeg)
+    --frames <dir>       Also extract PNG frames into dir (needs ffmpeg)
   restart <path> [url]   Stop current recording (if any) and start a new one
 
+Options (start/restart):
+  --size <WxH>           Video dimensions (e.g., 390x844 for mobile pages)
+  --fps <n>              Frames per second
+  --no-preserve-state    Don't carry cookies/localStorage into the new context
+
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
@@ -1122,18 +2130,26 @@ Examples:
 
   # Restart recording with a new file (stops previous, starts new)
   z-agent-browser record restart ./take2.webm
+
+  # Export a GIF for embedding in GitHub comments
+  z-agent-browser record stop --gif ./demo.gif
+
+  # Match the video size to a mobile viewport
+  z-agent-browser record start ./mobile.webm --size 390x844 --fps 30
 "##,
 
         // === Console/Errors ===
         "console" => r##"
 z-agent-browser console - View console logs
 
-Usage: z-agent-browser console [--clear]
+Usage: z-agent-browser console [--clear] [--level <level>]...
 
 View browser console output (log, warn, error, info).
 
 Options:
   --clear              Clear console log buffer
+  --level <level>      Only log, info, warning or error messages
+                       (repeatable, or comma-separated)
 
 Global Options:
   --json               Output as JSON
@@ -1142,16 +2158,20 @@ Global Options:
 Examples:
   z-agent-browser console
   z-agent-browser console --clear
+  z-agent-browser console --level warning --level error
 "##,
         "errors" => r##"
 z-agent-browser errors - View page errors
 
-Usage: z-agent-browser errors [--clear]
+Usage: z-agent-browser errors [--clear] [--short] [--stack]
 
-View JavaScript errors and uncaught exceptions.
+View JavaScript errors and uncaught exceptions. Each error shows its
+source location and stack trace when available.
 
 Options:
   --clear              Clear error buffer
+  --short              One line per error (message only)
+  --stack              Request complete stack traces (dimmed under each message)
 
 Global Options:
   --json               Output as JSON
@@ -1159,16 +2179,26 @@ Global Options:
 
 Examples:
   z-agent-browser errors
+  z-agent-browser errors --short
+  z-agent-browser errors --stack
   z-agent-browser errors --clear
 "##,
 
         // === Highlight ===
         "highlight" => r##"
-z-agent-browser highlight - Highlight an element
+z-agent-browser highlight - Highlight elements
+
+Usage: z-agent-browser highlight <selector>... [options]
+       z-agent-browser highlight --clear
 
-Usage: z-agent-browser highlight <selector>
+Visually highlights elements on the page for debugging or screenshots.
+Several selectors are highlighted together.
 
-Visually highlights an element on the page for debugging.
+Options:
+  --color <css-color>  Overlay color: a hex code, a color name, or
+                       rgb()/hsl() (e.g. red, #ff0, rgba(0,0,255,.4))
+  --duration <ms>      Remove the highlight after this long
+  --clear              Remove all highlights
 
 Global Options:
   --json               Output as JSON
@@ -1177,19 +2207,29 @@ Global Options:
 Examples:
   z-agent-browser highlight "#target-element"
   z-agent-browser highlight @e5
+  z-agent-browser highlight "#buy" --color red --duration 2000
+  z-agent-browser highlight "#old-price" "#new-price" --color "#f80"
+  z-agent-browser highlight --clear
 "##,
 
         // === State ===
         "state" => r##"
 z-agent-browser state - Save/load browser state
 
-Usage: z-agent-browser state <operation> <path>
+Usage: z-agent-browser state <operation> <path> [options]
 
 Save or restore browser state (cookies, localStorage, sessionStorage).
 
 Operations:
   save <path>          Save current state to file
-  load <path>          Load state from file
+  load <path>          Load state from file (validated before sending)
+
+Options:
+  --cookies-only       Only save/apply cookies
+  --storage-only       Only save/apply localStorage/sessionStorage
+  --origin <url>       Only include this origin (repeatable)
+  --no-cookies         Same as --storage-only
+  --include-cookies    Keep cookies after all (undoes --no-cookies)
 
 Global Options:
   --json               Output as JSON
@@ -1198,6 +2238,9 @@ Global Options:
 Examples:
   z-agent-browser state save ./auth-state.json
   z-agent-browser state load ./auth-state.json
+  z-agent-browser state save ./fixture.json --cookies-only --origin https://app.example.com
+  z-agent-browser state load ./auth-state.json --storage-only
+  z-agent-browser state save ./login.json --origin https://app.example.com --no-cookies
 "##,
 
         // === Session ===
@@ -1210,11 +2253,18 @@ Manage isolated browser sessions. Each session has its own browser
 instance with separate cookies, storage, and state.
 
 Operations:
-  (none)               Show current session name
-  list                 List all active sessions
+  (none), current      Show current session name
+  current --check      Exit 0 if the current session's daemon is running, else 1
+  list [--filter <s>]  List sessions with pid, state and socket path; --filter
+                       keeps names containing <s>
+  info [name]          Show pid, uptime, socket path and current URL
+  kill [name]          Stop a session's daemon (SIGTERM, then SIGKILL)
+  clean                Remove pid/socket files left by dead daemons
 
 Environment:
-  AGENT_BROWSER_SESSION    Default session name
+  AGENT_BROWSER_SESSION      Default session name
+  AGENT_BROWSER_RUNTIME_DIR  Where pid and socket files live
+                             (default: ~/.z-agent-browser/run)
 
 Global Options:
   --json               Output as JSON
@@ -1223,23 +2273,239 @@ Global Options:
 Examples:
   z-agent-browser session
   z-agent-browser session list
+  z-agent-browser session list --filter scraper
+  z-agent-browser --session work session current --check && echo up
+  z-agent-browser session info test
+  z-agent-browser session kill test
+  z-agent-browser session clean
   z-agent-browser --session test open example.com
 "##,
 
+        "daemon" | "restart" => r##"
+z-agent-browser daemon - Inspect the session's daemon
+
+Usage: z-agent-browser daemon logs [--follow] [--lines <n>]
+       z-agent-browser daemon restart [launch flags]
+
+restart closes the daemon and starts a new one with the launch options the
+old one was started with (--profile, --extension, --proxy, ...). Launch flags
+given with restart take precedence. Starts a daemon if none is running.
+Alias: restart
+
+The daemon writes its output (including browser launch errors) to
+$TMPDIR/z-agent-browser-<session>.log. The log is rotated to .log.1 when
+it passes 1 MB at daemon start. Works even when the daemon is not running.
+
+Options:
+  --follow             Keep printing new output (Ctrl+C to stop)
+  --lines <n>          Number of lines to show (default: 50)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser daemon logs
+  z-agent-browser daemon logs --lines 200
+  z-agent-browser --session scraper daemon logs --follow
+  z-agent-browser daemon restart --headed
+"##,
+
+        // === Config ===
+        "config" => r##"
+z-agent-browser config - Show per-session configuration
+
+Usage: z-agent-browser config show
+
+Prints the flags in effect for the current session after merging the
+config file, environment variables and command-line flags.
+
+Config file: ~/.config/agent-browser/config.toml (or AGENT_BROWSER_CONFIG)
+
+  [sessions.scraper]
+  stealth = true
+  proxy = "http://proxy:8080"
+  user-agent = "Mozilla/5.0 ..."
+  extensions = ["./my-extension"]
+
+Keys match the global flags without the leading dashes. Precedence:
+CLI flags > environment variables > config file > built-in defaults.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser config show
+  z-agent-browser --session scraper config show --json
+"##,
+        "assert" => r##"
+z-agent-browser assert - Check page state (for CI)
+
+Usage: z-agent-browser assert <text|visible|url|title> [args] [options]
+
+Queries the page, compares in the CLI, prints ✓ or ✗ with the actual
+value, and exits 1 on mismatch.
+
+Assertions:
+  text <sel> <expected>  Element text equals expected
+  visible <sel>          Element is visible
+  url <pattern>          Page URL equals pattern (* matches anything)
+  title <expected>       Page title equals expected
+
+Options:
+  --contains           Pass if the value contains expected
+  --regex              Treat expected as a regular expression
+  --timeout <ms>       Keep retrying until the assertion passes or time runs out
+
+Global Options:
+  --json               Output {"success","data":{"pass","actual","expected"}}
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser assert text "#status" Ready --timeout 5000
+  z-agent-browser assert text h1 Welcome --contains
+  z-agent-browser assert visible "#checkout"
+  z-agent-browser assert url "https://example.com/orders/*"
+  z-agent-browser assert title "^Order #\d+$" --regex
+"##,
+        "pipe" => r##"
+z-agent-browser pipe - Drive the daemon with JSON lines on stdin
+
+Usage: z-agent-browser pipe [--strict]
+
+Reads one JSON command object per line from stdin (the same objects
+--dry-run prints), sends each over a single daemon connection, and
+writes one response line per command to stdout. The response carries
+the command's "id"; commands without one get a generated id.
+
+Lines that aren't a JSON object produce an error line with the
+original text in "input"; the stream keeps going.
+
+Options:
+  --strict             Exit 1 at EOF if any command failed (default: 0)
+
+Examples:
+  echo '{"id":"1","action":"navigate","url":"https://example.com"}' | z-agent-browser pipe
+  z-agent-browser --json --dry-run open example.com | z-agent-browser pipe
+"##,
+        "run" => r##"
+z-agent-browser run - Run a script of commands
+
+Usage: z-agent-browser run <file> [options]
+
+Runs one command per line over a single daemon connection. Blank lines
+and lines starting with # are skipped. Stops at the first failing line
+and reports its line number. ${KEY} is replaced outside 'single quotes'
+(write \${KEY} to keep it); the value is never split into words.
+
+Options:
+  --continue-on-error  Keep going after a failure (exit status is still 1)
+  --var KEY=VALUE      Replace ${KEY} in script lines (repeatable)
+
+Global Options:
+  --json               Print {"success":..,"data":{"steps":[...]}} at the end
+  --session <name>     Use specific session
+
+Examples:
+  z-agent-browser run login.txt
+  z-agent-browser run login.txt --var USER=alice --var PASS=secret
+  z-agent-browser --json run smoke.txt --continue-on-error
+"##,
+        "script" => r##"
+z-agent-browser script - Convert recorded scripts
+
+Usage: z-agent-browser script export <file> [--format playwright-ts]
+
+Converts a script in 'run' format, such as one written with
+--record-script, to a Playwright TypeScript test. open, click, fill,
+type, press, wait and find become Playwright calls; anything else (and
+element refs like @e3, which only exist in a live session) is kept as a
+comment. ${NAME} placeholders from --redact read process.env.NAME.
+
+Options:
+  --format <format>    Output format (default: playwright-ts)
+
+Global Options:
+  --output <path>      Write the test to a file instead of stdout
+  --json               Output as JSON
+
+Examples:
+  export AGENT_BROWSER_RECORD_SCRIPT=flow.txt
+  z-agent-browser open example.com/login
+  z-agent-browser --redact fill "#password" hunter2
+  z-agent-browser run flow.txt --var PASSWORD=hunter2
+  z-agent-browser script export flow.txt -o tests/login.spec.ts
+"##,
+        "repl" => r##"
+z-agent-browser repl - Interactive command prompt
+
+Usage: z-agent-browser repl
+
+Reads commands line by line and sends them over a single daemon
+connection, so each command skips process startup. Lines use shell-style
+quoting ("double", 'single', backslash escapes). Global options given to
+repl apply to every line; options on a line apply to that line only.
+
+Errors are printed and the prompt continues. History is appended to
+~/.agent-browser_history.
+
+Leaving:
+  exit, quit, Ctrl-D   Leave the prompt (browser keeps running)
+  exit --close         Close the browser and leave
+
+Examples:
+  z-agent-browser repl
+  > open example.com
+  > snapshot -i
+  > fill @e3 "hello world"
+  > exit
+  z-agent-browser --session work --json repl
+"##,
+        "completions" => r##"
+z-agent-browser completions - Print a shell completion script
+
+Usage: z-agent-browser completions <bash|zsh|fish|powershell>
+
+Completes commands, their subcommands (get, is, find, set, network,
+storage, tab, trace, record, state, ...) and flags.
+
+Examples:
+  eval "$(z-agent-browser completions bash)"            # ~/.bashrc
+  source <(z-agent-browser completions zsh)             # ~/.zshrc
+  z-agent-browser completions fish > ~/.config/fish/completions/z-agent-browser.fish
+  z-agent-browser completions powershell | Out-String | Invoke-Expression
+"##,
+
         // === Install ===
         "install" => r##"
 z-agent-browser install - Install browser binaries
 
-Usage: z-agent-browser install [--with-deps]
+Usage: z-agent-browser install [browser...] [--with-deps]
+       z-agent-browser install [browser...] --download-only <dir>
+       z-agent-browser install --from <bundle>
 
 Downloads and installs browser binaries required for automation.
+Browsers: chromium (default), firefox, webkit.
 
 Options:
   -d, --with-deps      Also install system dependencies (Linux only)
+  --check              Only report what's missing (node/npx, browsers, and
+                       on Linux the shared libraries Chromium needs); exits 1
+                       if anything is
+  --download-only <dir>
+                       Download into a bundle (tar.gz) in <dir> instead of
+                       installing, for machines without network access
+  --from <bundle>      Install from a bundle made by --download-only (tar.gz
+                       or zip) without downloading
 
 Examples:
   z-agent-browser install
+  z-agent-browser install --check
+  z-agent-browser install firefox webkit
   z-agent-browser install --with-deps
+  z-agent-browser install --download-only ./bundles
+  z-agent-browser install --from ./bundles/z-agent-browser-browsers-linux-x86_64.tar.gz
 "##,
 
         _ => return false,
@@ -1248,9 +2514,7 @@ Examples:
     true
 }
 
-pub fn print_help() {
-    println!(
-        r#"
+pub const HELP: &str = r#"
 z-agent-browser - fast browser automation CLI for AI agents
 
 Usage: z-agent-browser <command> [args] [options]
@@ -1272,6 +2536,7 @@ Core Commands:
   check <sel>                Check checkbox
   uncheck <sel>              Uncheck checkbox
   select <sel> <val>         Select dropdown option
+  form fill <file.json>      Fill many fields from a JSON object
   drag <src> <dst>           Drag and drop
   upload <sel> <files...>    Upload files
   scroll <dir> [px]          Scroll (up/down/left/right)
@@ -1279,60 +2544,85 @@ Core Commands:
   wait <sel|ms>              Wait for element or time
   screenshot [path]          Take screenshot
   pdf <path>                 Save as PDF
+  save <path>                Save page as .mhtml or single-file .html
   snapshot                   Accessibility tree with refs (for AI)
   eval <js>                  Run JavaScript
   connect <port>             Connect to browser via CDP (e.g., connect 9222)
+  connect --discover         Find a local browser on the usual debugging ports
   close                      Close browser
+  ping                       Daemon version, pid and uptime
 
 Navigation:
-  back                       Go back
-  forward                    Go forward
-  reload                     Reload page
+  back [n]                   Go back (n entries)
+  forward [n]                Go forward (n entries)
+  reload [--hard]            Reload page (--hard bypasses cache)
+  history [back|forward] [n] List history / jump n entries
 
 Get Info:  z-agent-browser get <what> [selector]
-  text, html, value, attr <name>, title, url, count, box
+  text, html, value, attr <name>, title, url, count, box, article
 
 Check State:  z-agent-browser is <what> <selector>
   visible, enabled, checked
 
 Find Elements:  z-agent-browser find <locator> <value> <action> [text]
   role, text, label, placeholder, alt, title, testid, first, last, nth
+  find-text <needle>         Where text appears (--regex, --case-sensitive, --count-only)
 
 Mouse:  z-agent-browser mouse <action> [args]
-  move <x> <y>, down [btn], up [btn], wheel <dy> [dx]
+  move <x> <y>, down [btn], up [btn], wheel <dy> [dx] [--steps n]
 
 Browser Settings:  z-agent-browser set <setting> [value]
   viewport <w> <h>, device <name>, geo <lat> <lng>
-  offline [on|off], headers <json>, credentials <user> <pass>
+  offline [on|off], headless <on|off>, headers <json>, credentials <user> <pass>
   media [dark|light] [reduced-motion]
 
 Network:  z-agent-browser network <action>
-  route <url> [--abort|--body <json>]
+  route <url> [--abort|--body <json>|--continue [--set-url|--set-method|--set-header]]
   unroute [url]
   requests [--clear] [--filter <pattern>]
+  fetch <url>                HTTP request with the session's cookies
 
 Storage:
   cookies [get|set|clear]    Manage cookies
   storage <local|session>    Manage web storage
+  clipboard read|write       Read or write the clipboard
 
 Tabs:
   tab [new|list|close|<n>]   Manage tabs
 
 Debug:
   trace start|stop [path]    Record trace
+  trace view <path>          Open trace in Playwright trace viewer
   record start <path> [url]  Start video recording (WebM)
   record stop                Stop and save video
   console [--clear]          View console logs
-  errors [--clear]           View page errors
-  highlight <sel>            Highlight element
+  errors [--clear] [--short] View page errors
+  highlight <sel>...         Highlight elements (--color, --duration, --clear)
+
+Assertions (exit 1 on mismatch):
+  assert text <sel> <expected>  Element text (--contains, --regex)
+  assert visible <sel>       Element is visible
+  assert url <pattern>       Page URL (* wildcards, --contains, --regex)
+  assert title <expected>    Page title (--timeout <ms> retries any assertion)
 
 Sessions:
   session                    Show current session name
   session list               List active sessions
+  session info [name]        Show session details
+  session kill [name]        Stop a session's daemon
+  session clean              Remove stale session files
+  daemon logs [--follow]     Show the daemon log (--lines <n>, default 50)
+  daemon restart             Restart the daemon, keeping its launch options
+  repl                       Interactive prompt (exit, or exit --close)
+  run <file>                 Run one command per line (--var K=V, --continue-on-error)
+  script export <file>       Convert a script to a Playwright test (--format playwright-ts)
+  pipe [--strict]            Read JSON commands from stdin, write JSON responses
 
 Setup:
   install                    Install browser binaries
   install --with-deps        Also install system dependencies (Linux)
+  config show                Show effective flags for the current session
+  completions <shell>        Print shell completions (bash, zsh, fish, powershell)
 
 Snapshot Options:
   -i, --interactive          Only interactive elements
@@ -1345,18 +2635,74 @@ Options:
   --headers <json>           HTTP headers scoped to URL's origin (for auth)
   --executable-path <path>   Custom browser executable (or AGENT_BROWSER_EXECUTABLE_PATH)
   --extension <path>         Load browser extensions (repeatable).
-  --proxy <url>              Proxy server (http://[user:pass@]host:port)
+  --proxy <url>              Proxy server (http://[user:pass@]host:port); percent-encode
+                             @ and : in credentials, add ?bypass=a,b to skip hosts.
+                             A comma list rotates, one proxy per invocation
+  --browser <name>           Browser engine: chromium (default), firefox, webkit
+                             (or AGENT_BROWSER_BROWSER)
+  --proxy-file <path>        Proxies to rotate through, one per line (# comments)
+  --proxy-strategy <s>       round-robin (default, per session) or random
+  --proxy-bypass <hosts>     Hosts that skip --proxy, comma separated
+                             (or AGENT_BROWSER_PROXY_BYPASS)
+  --user-data-dir <path>     Persistent Chromium user data directory
   --json                     JSON output
-  --full, -f                 Full page screenshot
+  --jsonl                    JSON lines: one object per list item (tabs, requests, ...)
+  --full, -f                 Full page screenshot (-f only before the command)
   --headed                   Show browser window (not headless)
   --cdp <port|url>           Connect via CDP (port or ws:// URL for playwriter)
-  --debug                    Debug output
+  --no-daemon                One-shot: launch a throwaway browser, run, then close
+  --session-timeout <secs>   Daemon exits after this long without commands (0 = never)
+  --idle-timeout <secs>      Same as --session-timeout (or AGENT_BROWSER_IDLE_TIMEOUT)
+  --connect-timeout <ms>     Wait this long for a daemon response (default: 30000)
+  --remote <host:port>       Drive a daemon on another machine over TCP
+  --remote-token <token>     Shared secret sent with every command to that daemon
+  --listen <host:port>       Started daemon also accepts TCP clients here
+                             (--remote-token is required unless host is loopback)
+  --quiet                    Suppress success messages (errors still shown)
+  --retry <n>                Retry up to n times on timeouts / missing elements
+  --retry-delay <ms>         Pause before the first retry, doubled after (default: 500)
+  -o, --output <path>        Write output to a file (status lines go to stderr)
+  --dry-run                  Print the command JSON instead of sending it
+  --with-id                  Include the request id in --dry-run output
+  --timing                   Print how long the command took (stderr)
+  --timing-log               Append action, duration and outcome to
+                             $TMPDIR/z-agent-browser-<session>.timings
+                             (the runtime dir if $TMPDIR isn't writable)
+  --record-script <path>     Append each successful command to a script for 'run'
+                             (or AGENT_BROWSER_RECORD_SCRIPT)
+  --redact                   Record fill/type text as ${NAME} placeholders
+  --fix                      Install missing system libraries if the browser
+                             can't start without them (Linux)
+  --debug                    Print raw request/response JSON to stderr
+  --color <mode>             auto (default: color when stdout is a terminal),
+                             always or never; before the command word
   --version, -V              Show version
 
 Environment:
   AGENT_BROWSER_SESSION          Session name (default: "default")
   AGENT_BROWSER_EXECUTABLE_PATH  Custom browser executable path
   AGENT_BROWSER_STREAM_PORT      Enable WebSocket streaming on port (e.g., 9223)
+  AGENT_BROWSER_CONFIG           Config file (default: ~/.config/agent-browser/config.toml)
+  AGENT_BROWSER_TIMEOUT          Daemon response timeout in ms (like --connect-timeout)
+  AGENT_BROWSER_REMOTE           Remote daemon host:port (like --remote)
+  AGENT_BROWSER_REMOTE_TOKEN     Remote daemon token (like --remote-token)
+  AGENT_BROWSER_RUNTIME_DIR      Socket and pid file directory (default: ~/.z-agent-browser/run)
+  AGENT_BROWSER_RECORD_SCRIPT    Script file to record commands to (like --record-script)
+  NO_COLOR                       Disable color (unless --color always)
+  CLICOLOR_FORCE                 Color even when stdout isn't a terminal (unless --color never)
+
+Exit Codes:
+  0  Success
+  1  The command ran but failed (element not found, assertion failed, ...)
+  2  Usage error: unknown command or flag, bad arguments
+  3  Daemon error: could not start, connect, authenticate, or no response
+
+Config:
+  [sessions.<name>] tables set per-session default flags, e.g.
+    [sessions.scraper]
+    stealth = true
+    proxy = "http://proxy:8080"
+  Precedence: CLI flags > environment > config file > built-in defaults
 
 Examples:
   z-agent-browser open example.com
@@ -1367,10 +2713,760 @@ Examples:
   z-agent-browser get text @e1
   z-agent-browser screenshot --full
   z-agent-browser --cdp 9222 snapshot      # Connect via CDP port
-"#
-    );
+"#;
+
+pub fn print_help() {
+    println!("{}", HELP);
 }
 
 pub fn print_version() {
     println!("z-agent-browser {}", env!("CARGO_PKG_VERSION"));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+
+    // === Errors ===
+
+    #[test]
+    fn test_page_error_with_stack_and_location() {
+        let err = json!({
+            "message": "x is not defined",
+            "url": "https://example.com/app.js",
+            "line": 12,
+            "column": 5,
+            "stack": "ReferenceError: x is not defined\n    at foo (https://example.com/app.js:12:5)\n    at https://example.com/app.js:20:1"
+        });
+        let lines = format_page_error(&err, false);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("x is not defined"));
+        assert_eq!(lines[1], "    at https://example.com/app.js:12:5");
+        assert!(lines[2].contains("at foo (https://example.com/app.js:12:5)"));
+        assert!(lines[3].contains("app.js:20:1"));
+    }
+
+    #[test]
+    fn test_page_error_without_stack_or_location() {
+        let err = json!({ "message": "boom", "timestamp": 1 });
+        let lines = format_page_error(&err, false);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].ends_with("boom"));
+    }
+
+    #[test]
+    fn test_page_error_location_without_line() {
+        let err = json!({ "message": "boom", "url": "https://example.com/" });
+        let lines = format_page_error(&err, false);
+        assert_eq!(lines[1], "    at https://example.com/");
+    }
+
+    #[test]
+    fn test_errors_response_with_stack() {
+        let resp = Response {
+            success: true,
+            data: Some(json!({ "errors": [{
+                "message": "boom",
+                "stack": "Error: boom\n    at render (app.js:3:9)\n    at main (app.js:10:1)"
+            }] })),
+            error: None,
+        };
+        let lines = |request: Value| {
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            write_response(&mut out, &mut err, &resp, &opts(false, false, false), &request).unwrap();
+            String::from_utf8(out).unwrap().lines().map(String::from).collect::<Vec<_>>()
+        };
+        let full = lines(json!({ "action": "errors", "short": true, "includeStack": true }));
+        assert_eq!(full.len(), 3);
+        assert!(full[0].ends_with("boom"));
+        assert!(full[1].contains("at render (app.js:3:9)"));
+        assert!(full[2].contains("at main (app.js:10:1)"));
+
+        assert_eq!(lines(json!({ "action": "errors", "short": true })).len(), 1);
+    }
+
+    #[test]
+    fn test_page_error_short() {
+        let err = json!({ "message": "boom", "url": "https://example.com/", "stack": "Error: boom\n    at x" });
+        let lines = format_page_error(&err, true);
+        assert_eq!(lines.len(), 1);
+    }
+
+    // === Storage ===
+
+    #[test]
+    fn test_storage_entries_map() {
+        let data = json!({ "data": { "theme": "dark", "token": "abc123", "count": 3 } });
+        let lines = format_storage_entries(&data).unwrap();
+        assert_eq!(lines, vec!["count=3", "theme=dark", "token=abc123"]);
+    }
+
+    #[test]
+    fn test_storage_entries_array() {
+        let data = json!({ "storage": [
+            { "key": "theme", "value": "dark" },
+            { "key": "empty", "value": null }
+        ] });
+        let lines = format_storage_entries(&data).unwrap();
+        assert_eq!(lines, vec!["theme=dark", "empty="]);
+    }
+
+    #[test]
+    fn test_storage_entries_single_key_not_matched() {
+        let data = json!({ "key": "theme", "value": "dark" });
+        assert!(format_storage_entries(&data).is_none());
+    }
+
+
+    // === Bounding box ===
+
+    #[test]
+    fn test_format_box_text_mode() {
+        let data = json!({ "box": { "x": 10, "y": 20.5, "width": 300, "height": 40 } });
+        assert_eq!(
+            format_box(&data["box"]).unwrap(),
+            "x=10 y=20.5 width=300 height=40"
+        );
+    }
+
+    #[test]
+    fn test_format_box_json_mode_keeps_object() {
+        let resp = Response {
+            success: true,
+            data: Some(json!({ "box": { "x": 1, "y": 2, "width": 3, "height": 4 } })),
+            error: None,
+        };
+        let out: Value = serde_json::from_str(&serde_json::to_string(&resp).unwrap()).unwrap();
+        assert_eq!(out["data"]["box"]["width"], 3);
+    }
+
+    #[test]
+    fn test_format_box_missing_fields() {
+        assert!(format_box(&json!({ "x": 1, "y": 2 })).is_none());
+        assert!(format_box(&Value::Null).is_none());
+    }
+
+
+    // === Eval ===
+
+    #[test]
+    fn test_eval_result_output() {
+        let render_eval = |result: Value, json: bool| {
+            let resp = Response { success: true, data: Some(json!({ "result": result })), error: None };
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            write_response(&mut out, &mut err, &resp, &opts(json, false, false), &json!({ "action": "evaluate" })).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(render_eval(json!("My \"Page\""), false), "My \"Page\"\n");
+        assert_eq!(render_eval(json!({ "a": [1, 2] }), false), "{\n  \"a\": [\n    1,\n    2\n  ]\n}\n");
+        assert_eq!(render_eval(json!(42), false), "42\n");
+        assert_eq!(render_eval(json!(null), false), "null\n");
+        assert!(render_eval(json!("My Page"), true).contains(r#""result":"My Page""#));
+    }
+
+    #[test]
+    fn test_eval_error_label() {
+        assert_eq!(
+            eval_error_label("SyntaxError: Unexpected token ')'", true),
+            Some("Syntax error in script")
+        );
+        assert_eq!(eval_error_label("Promise rejected: Error: nope", true), Some("Promise rejected"));
+        assert_eq!(eval_error_label("Error: nope", true), Some("Script threw"));
+        assert_eq!(eval_error_label("Error: nope", false), None);
+    }
+
+
+    // === Output levels ===
+
+    fn render(resp: &Response, opts: &OutputOptions) -> (String, String) {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, resp, opts, &json!({ "action": "click" })).unwrap();
+        (String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    fn opts(json: bool, quiet: bool, debug: bool) -> OutputOptions {
+        OutputOptions { json, jsonl: false, quiet, debug, status_to_stderr: false, duration_ms: None }
+    }
+
+    #[test]
+    fn test_connect_discover_endpoint() {
+        let resp = Response {
+            success: true,
+            data: Some(json!({ "cdpEndpoint": "ws://127.0.0.1:9222/devtools/browser/abc", "browser": "Chrome/126.0" })),
+            error: None,
+        };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let request = json!({ "action": "launch", "cdpDiscover": true });
+        write_response(&mut out, &mut err, &resp, &opts(false, false, false), &request).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Connected to ws://127.0.0.1:9222/devtools/browser/abc (Chrome/126.0)"), "{}", out);
+    }
+
+    #[test]
+    fn test_wait_multi_matched() {
+        let render_wait = |data: Value| {
+            let resp = Response { success: true, data: Some(data), error: None };
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let request = json!({ "action": "wait_multi", "selectors": ["#ok", "#error"] });
+            write_response(&mut out, &mut err, &resp, &opts(false, false, false), &request).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert!(render_wait(json!({ "matched": "#error" })).ends_with("Matched #error\n"));
+        assert!(render_wait(json!({ "matched": ["#ok", "#error"] })).ends_with("Matched all: #ok, #error\n"));
+    }
+
+    fn render_fetch(data: Value, request: Value, opts: &OutputOptions) -> (String, String) {
+        let resp = Response { success: true, data: Some(data), error: None };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &resp, opts, &request).unwrap();
+        (String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_fetch_output() {
+        let data = json!({
+            "status": 200,
+            "statusText": "OK",
+            "headers": { "content-type": "application/json", "x-id": "7" },
+            "body": "{\"a\":1}"
+        });
+        let (out, err) = render_fetch(data.clone(), json!({ "action": "fetch" }), &opts(false, false, false));
+        assert_eq!(out, "{\n  \"a\": 1\n}\n");
+        assert!(err.contains("HTTP 200 OK"));
+
+        let (out, _) = render_fetch(data.clone(), json!({ "action": "fetch", "raw": true, "include": true }), &opts(false, false, false));
+        assert_eq!(out, "content-type: application/json\nx-id: 7\n\n{\"a\":1}\n");
+
+        // Quiet drops the status line, not the body
+        let (out, err) = render_fetch(data, json!({ "action": "fetch" }), &opts(false, true, false));
+        assert!(out.contains("\"a\": 1"));
+        assert!(err.is_empty());
+
+        let text = json!({ "status": 404, "statusText": "Not Found", "body": "missing\n" });
+        let (out, err) = render_fetch(text, json!({ "action": "fetch" }), &opts(false, false, false));
+        assert_eq!(out, "missing\n");
+        assert!(err.contains("HTTP 404 Not Found"));
+    }
+
+    #[test]
+    fn test_fetch_failed() {
+        let resp = |status: u64| Response { success: true, data: Some(json!({ "status": status })), error: None };
+        let request = json!({ "action": "fetch" });
+        assert!(!fetch_failed(&request, &resp(204)));
+        assert!(fetch_failed(&request, &resp(404)));
+        assert!(fetch_failed(&request, &resp(500)));
+        assert!(!fetch_failed(&json!({ "action": "fetch", "noFail": true }), &resp(500)));
+        assert!(!fetch_failed(&json!({ "action": "click" }), &resp(500)));
+    }
+
+    #[test]
+    fn test_json_duration_ms() {
+        let timed = OutputOptions { duration_ms: Some(132), ..opts(true, false, false) };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &done(), &timed, &json!({ "action": "click" })).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().trim(), r#"{"success":true,"data":{},"error":null,"durationMs":132}"#);
+
+        let failed = Response { success: false, data: None, error: Some("boom".to_string()) };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &failed, &timed, &json!({ "action": "click" })).unwrap();
+        let parsed: Value = serde_json::from_str(String::from_utf8(out).unwrap().trim()).unwrap();
+        assert_eq!(parsed["error"], "boom");
+        assert_eq!(parsed["durationMs"], 132);
+
+        // Not measured: no field
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &done(), &opts(true, false, false), &json!({ "action": "click" })).unwrap();
+        assert!(!String::from_utf8(out).unwrap().contains("durationMs"));
+    }
+
+    fn done() -> Response {
+        Response { success: true, data: Some(json!({})), error: None }
+    }
+
+    #[test]
+    fn test_output_default_prints_done() {
+        let (out, err) = render(&done(), &opts(false, false, false));
+        assert!(out.contains("Done"));
+        assert!(err.is_empty());
+    }
+
+    #[test]
+    fn test_output_quiet_suppresses_success_only() {
+        let (out, err) = render(&done(), &opts(false, true, false));
+        assert!(out.is_empty());
+        assert!(err.is_empty());
+
+        let text = Response { success: true, data: Some(json!({ "text": "hello" })), error: None };
+        let (out, _) = render(&text, &opts(false, true, false));
+        assert_eq!(out, "hello\n");
+
+        let failed = Response { success: false, data: None, error: Some("boom".to_string()) };
+        let (out, err) = render(&failed, &opts(false, true, false));
+        assert!(out.is_empty());
+        assert!(err.contains("boom"));
+    }
+
+    #[test]
+    fn test_output_clipboard_text_verbatim() {
+        let resp = Response { success: true, data: Some(json!({ "clipboardText": "  a \"b\"\n\tc" })), error: None };
+        let (out, _) = render(&resp, &opts(false, false, false));
+        assert_eq!(out, "  a \"b\"\n\tc\n");
+        let (out, _) = render(&resp, &opts(false, true, false));
+        assert_eq!(out, "  a \"b\"\n\tc\n");
+    }
+
+    fn render_matches(data: Value, request: Value) -> String {
+        let resp = Response { success: true, data: Some(data), error: None };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &resp, &opts(false, false, false), &request).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn matches() -> Value {
+        json!({ "matches": [
+            { "text": "refund", "context": "the refund\n  policy states", "ref": "e12" },
+            { "text": "Refund", "context": "Refund requests", "ref": "@e7" },
+            { "text": "refund", "context": "no refund after 30 days" },
+        ] })
+    }
+
+    #[test]
+    fn test_output_text_matches() {
+        let out = render_matches(matches(), json!({ "action": "textsearch" }));
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines, vec!["@e12  …the refund policy states…", "@e7   …Refund requests…", "-     …no refund after 30 days…"]);
+    }
+
+    #[test]
+    fn test_output_text_matches_count_only() {
+        let out = render_matches(matches(), json!({ "action": "textsearch", "countOnly": true }));
+        assert_eq!(out, "3\n");
+        let out = render_matches(json!({ "matches": [] }), json!({ "action": "textsearch", "countOnly": true }));
+        assert_eq!(out, "0\n");
+    }
+
+    #[test]
+    fn test_output_text_matches_json_passthrough() {
+        let resp = Response { success: true, data: Some(matches()), error: None };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &resp, &opts(true, false, false), &json!({ "action": "textsearch" })).unwrap();
+        let value: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value["data"], matches());
+    }
+
+    fn render_article(request: Value) -> String {
+        let resp = Response {
+            success: true,
+            data: Some(json!({
+                "title": "Rust 2.0",
+                "byline": "Ferris",
+                "content": "<p>Hello <b>crab</b> world</p>",
+                "textContent": "Hello crab world",
+                "length": 16,
+            })),
+            error: None,
+        };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &resp, &opts(false, false, false), &request).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_output_article_text() {
+        let out = render_article(json!({ "action": "article" }));
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].contains("Rust 2.0"));
+        assert!(lines[1].contains("Ferris"));
+        assert_eq!(lines[3], "Hello crab world");
+        assert!(!out.contains("<p>"));
+    }
+
+    #[test]
+    fn test_output_article_html_and_truncation() {
+        let out = render_article(json!({ "action": "article", "html": true }));
+        assert!(out.contains("<p>Hello <b>crab</b> world</p>"));
+
+        let out = render_article(json!({ "action": "article", "maxChars": 10 }));
+        assert!(out.contains("Hello crab…"));
+        assert!(!out.contains("world"));
+        assert!(out.contains("showing 10 of 16 characters"));
+
+        let out = render_article(json!({ "action": "article", "maxChars": 100 }));
+        assert!(out.contains("Hello crab world\n"));
+        assert!(!out.contains("truncated"));
+    }
+
+    #[test]
+    fn test_output_article_json_is_full_object() {
+        let resp = Response { success: true, data: Some(json!({ "title": "T", "textContent": "x".repeat(50) })), error: None };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let request = json!({ "action": "article", "maxChars": 5 });
+        write_response(&mut out, &mut err, &resp, &opts(true, false, false), &request).unwrap();
+        let value: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value["data"]["textContent"].as_str().unwrap().len(), 50);
+    }
+
+    #[test]
+    fn test_output_saved_page() {
+        let resp = Response { success: true, data: Some(json!({ "path": "/tmp/a.mhtml", "size": 48213 })), error: None };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &resp, &opts(false, false, false), &json!({ "action": "savemhtml" })).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Page saved to /tmp/a.mhtml (48213 bytes)"));
+    }
+
+    #[test]
+    fn test_output_debug_echoes_raw_response() {
+        let (out, err) = render(&done(), &opts(false, false, true));
+        assert!(out.contains("Done"));
+        assert!(err.contains("[debug] ←"));
+        assert!(err.contains(r#"{"success":true,"data":{},"error":null}"#));
+    }
+
+    #[test]
+    fn test_output_json_ignores_quiet() {
+        let (out, _) = render(&done(), &opts(true, true, false));
+        assert_eq!(out.trim(), r#"{"success":true,"data":{},"error":null}"#);
+    }
+
+    #[test]
+    fn test_debug_line_request() {
+        let line = debug_line("→", &json!({ "action": "click", "selector": "#a" }));
+        assert!(line.contains("[debug] →"));
+        assert!(line.ends_with(r##"{"action":"click","selector":"#a"}"##));
+    }
+
+
+    // === Output file (-o) ===
+
+    fn temp_output(dir: &TempDir, name: &str) -> String {
+        dir.join(name).to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_output_file_text_is_verbatim() {
+        let dir = TempDir::new("output");
+        let path = temp_output(&dir, "page.html");
+        let resp = Response { success: true, data: Some(json!({ "html": "<p>\"hi\"</p>" })), error: None };
+        write_response_to_file(&path, &resp, &opts(false, true, false), &json!({})).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<p>\"hi\"</p>\n");
+    }
+
+    #[test]
+    fn test_output_file_keeps_status_out_of_file() {
+        let dir = TempDir::new("output");
+        let path = temp_output(&dir, "done.txt");
+        write_response_to_file(&path, &done(), &opts(false, false, false), &json!({})).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[test]
+    fn test_output_file_json_mode() {
+        let dir = TempDir::new("output");
+        let path = temp_output(&dir, "resp.json");
+        let resp = Response { success: true, data: Some(json!({ "text": "hello" })), error: None };
+        write_response_to_file(&path, &resp, &opts(true, false, false), &json!({})).unwrap();
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["data"]["text"], "hello");
+    }
+
+    #[test]
+    fn test_output_file_decodes_screenshot() {
+        let dir = TempDir::new("output");
+        let path = temp_output(&dir, "shot.png");
+        // "\x89PNG\r\n" followed by one arbitrary byte
+        let resp = Response { success: true, data: Some(json!({ "base64": "iVBORw0K/w==" })), error: None };
+        write_response_to_file(&path, &resp, &opts(false, true, false), &json!({})).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0xff]);
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("aGVsbG8=").unwrap(), b"hello");
+        assert_eq!(decode_base64("aGk=").unwrap(), b"hi");
+        assert!(decode_base64("not base64!").is_none());
+    }
+
+
+    // === Structured errors ===
+
+    #[test]
+    fn test_error_json_escapes_quotes_and_newlines() {
+        let msg = "Element \"#submit\" not found\nTry: snapshot -i";
+        let line = error_json(ErrorCode::CommandFailed, msg);
+        assert!(!line.contains('\n'));
+        let parsed: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed["success"], false);
+        assert_eq!(parsed["error"], msg);
+        assert_eq!(parsed["code"], "command_failed");
+    }
+
+    #[test]
+    fn test_error_json_escapes_backslashes_and_control_chars() {
+        let msg = "CDP connection failed: C:\\Users\\me\\chrome.exe exited\t(code \"1\")\r\n";
+        let parsed: Value = serde_json::from_str(&error_json(ErrorCode::InvalidCdpEndpoint, msg)).unwrap();
+        assert_eq!(parsed["error"], msg);
+        assert_eq!(parsed["code"], "invalid_cdp_endpoint");
+    }
+
+    #[test]
+    fn test_exit_codes_by_category() {
+        assert_eq!(ErrorCode::CommandFailed.exit_code(), 1);
+        assert_eq!(ErrorCode::ParseError.exit_code(), 2);
+        for code in [ErrorCode::DaemonUnreachable, ErrorCode::InvalidCdpEndpoint, ErrorCode::Timeout, ErrorCode::AuthFailed] {
+            assert_eq!(code.exit_code(), 3, "{:?}", code);
+        }
+        assert_eq!(ErrorCode::for_connection_error("Failed to connect: refused").exit_code(), 3);
+        assert!(HELP.contains("Exit Codes:"));
+    }
+
+    #[test]
+    fn test_error_codes_are_snake_case() {
+        let codes = [
+            (ErrorCode::DaemonUnreachable, "daemon_unreachable"),
+            (ErrorCode::ParseError, "parse_error"),
+            (ErrorCode::InvalidCdpEndpoint, "invalid_cdp_endpoint"),
+            (ErrorCode::CommandFailed, "command_failed"),
+            (ErrorCode::Timeout, "timeout"),
+        ];
+        for (code, expected) in codes {
+            let parsed: Value = serde_json::from_str(&error_json(code, "x")).unwrap();
+            assert_eq!(parsed["code"], expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_error_json() {
+        let err = crate::commands::ParseError::UnknownSubcommand {
+            subcommand: "\"bogus\"".to_string(),
+            valid_options: &["a", "b"],
+        };
+        let parsed: Value = serde_json::from_str(&error_json(ErrorCode::ParseError, &err.format())).unwrap();
+        assert_eq!(parsed["code"], "parse_error");
+        assert_eq!(parsed["error"], err.format());
+    }
+
+    #[test]
+    fn test_connection_error_classification() {
+        assert_eq!(
+            ErrorCode::for_connection_error("Timed out waiting for the daemon to respond"),
+            ErrorCode::Timeout
+        );
+        assert_eq!(
+            ErrorCode::for_connection_error("Failed to connect: No such file or directory"),
+            ErrorCode::DaemonUnreachable
+        );
+    }
+
+    #[test]
+    fn test_output_json_failure_has_code() {
+        let failed = Response {
+            success: false,
+            data: None,
+            error: Some("locator.click: Timeout 30000ms exceeded.\n  waiting for \"#a\"".to_string()),
+        };
+        let (out, _) = render(&failed, &opts(true, false, false));
+        let parsed: Value = serde_json::from_str(out.trim()).unwrap();
+        assert_eq!(parsed["code"], "timeout");
+        assert_eq!(parsed["error"], failed.error.unwrap());
+
+        let failed = Response { success: false, data: None, error: Some("boom".to_string()) };
+        let (out, _) = render(&failed, &opts(true, false, false));
+        let parsed: Value = serde_json::from_str(out.trim()).unwrap();
+        assert_eq!(parsed["code"], "command_failed");
+    }
+
+
+    // === JSON lines ===
+
+    fn render_jsonl(data: Value) -> Vec<Value> {
+        let resp = Response { success: true, data: Some(data), error: None };
+        let opts = OutputOptions { jsonl: true, ..opts(true, false, false) };
+        let (out, _) = render(&resp, &opts);
+        out.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_jsonl_tabs() {
+        let lines = render_jsonl(json!({
+            "tabs": [
+                { "index": 0, "url": "https://a.com", "title": "A", "active": true },
+                { "index": 1, "url": "https://b.com", "title": "B", "active": false }
+            ]
+        }));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["url"], "https://a.com");
+        assert_eq!(lines[1]["active"], false);
+    }
+
+    #[test]
+    fn test_jsonl_requests() {
+        let lines = render_jsonl(json!({
+            "requests": [
+                { "url": "https://a.com/api", "method": "GET" },
+                { "url": "https://a.com/post", "method": "POST" },
+                { "url": "https://a.com/img.png", "method": "GET" }
+            ]
+        }));
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["method"], "POST");
+    }
+
+    #[test]
+    fn test_jsonl_console() {
+        let lines = render_jsonl(json!({
+            "messages": [
+                { "type": "log", "text": "line \"one\"\nwith newline" },
+                { "type": "error", "text": "boom" }
+            ]
+        }));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["text"], "line \"one\"\nwith newline");
+        assert_eq!(lines[1]["type"], "error");
+    }
+
+    #[test]
+    fn test_jsonl_scalar_is_single_line() {
+        let lines = render_jsonl(json!({ "url": "https://a.com", "title": "A" }));
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["success"], true);
+        assert_eq!(lines[0]["data"]["title"], "A");
+    }
+
+    #[test]
+    fn test_jsonl_other_arrays_stay_on_one_line() {
+        let lines = render_jsonl(json!({ "selectors": ["#a", "#b"], "count": 2 }));
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["data"]["count"], 2);
+    }
+
+
+    #[test]
+    fn test_get_value_multi_select_and_checkbox() {
+        let resp = Response { success: true, data: Some(json!({ "values": ["en", "fr", 3] })), error: None };
+        let (out, _) = render(&resp, &opts(false, false, false));
+        assert_eq!(out, "en\nfr\n3\n");
+
+        let resp = Response { success: true, data: Some(json!({ "values": [] })), error: None };
+        let (out, _) = render(&resp, &opts(false, false, false));
+        assert_eq!(out, "");
+
+        let resp = Response { success: true, data: Some(json!({ "value": true })), error: None };
+        let (out, _) = render(&resp, &opts(false, false, false));
+        assert_eq!(out, "true\n");
+    }
+
+
+    #[test]
+    fn test_find_locate_lists_refs() {
+        let request = json!({ "action": "getbytext", "text": "Log in", "subaction": "locate" });
+        let data = json!({ "count": 2, "matches": [
+            { "ref": "e3", "text": "Log in" },
+            { "ref": "e12", "text": "Log in\n  with SSO" }
+        ] });
+        let out = render_matches(data, request.clone());
+        assert_eq!(out, "2 matches\n  @e3   Log in\n  @e12  Log in with SSO\n");
+
+        let out = render_matches(json!({ "count": 0, "matches": [] }), request);
+        assert!(out.contains("No matches"));
+    }
+
+
+    #[test]
+    fn test_ping_output() {
+        let resp = Response {
+            success: true,
+            data: Some(json!({ "daemonVersion": "0.5.0", "pid": 4242, "uptime": 192000 })),
+            error: None,
+        };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &resp, &opts(false, false, false), &json!({ "action": "ping" })).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "daemon 0.5.0 (pid 4242, up 3m 12s)\n");
+    }
+
+
+    #[test]
+    fn test_status_shows_idle_time_left() {
+        let resp = Response {
+            success: true,
+            data: Some(json!({ "launched": true, "headless": true, "stealth": false, "idleRemaining": 251500 })),
+            error: None,
+        };
+        let (out, _) = render(&resp, &opts(false, false, false));
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].ends_with("Browser running (headless)"));
+        assert!(lines.last().unwrap().contains("in 4m 12s"));
+
+        let resp = Response { success: true, data: Some(json!({ "launched": true })), error: None };
+        assert_eq!(render(&resp, &opts(false, false, false)).0.lines().count(), 1);
+    }
+
+    fn render_status(data: Value, opts: &OutputOptions) -> String {
+        let resp = Response { success: true, data: Some(data), error: None };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &resp, opts, &json!({ "action": "status" })).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn row_labels(data: &Value) -> Vec<&'static str> {
+        status_rows(data).into_iter().map(|(label, _)| label).collect()
+    }
+
+    #[test]
+    fn test_status_full_payload() {
+        let data = json!({
+            "launched": true,
+            "headless": false,
+            "backend": "playwright",
+            "profile": "/home/me/.z-agent-browser/work",
+            "proxy": { "server": "http://proxy.local:8080" },
+            "stealth": { "level": "basic" },
+            "tabs": [{ "index": 0 }, { "index": 1 }],
+            "uptime": 192000,
+            "pid": 4242,
+            "version": "0.5.0",
+            "somethingNew": 1
+        });
+        assert_eq!(
+            row_labels(&data),
+            vec!["Headless", "Backend", "Profile", "Proxy", "Stealth", "Tabs", "Uptime", "PID", "Version"]
+        );
+        let out = render_status(data, &opts(false, false, false));
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].ends_with("Browser running (headed + stealth (basic))"), "{}", lines[0]);
+        assert_eq!(lines.len(), 10);
+        assert!(lines[1].contains("Headless") && lines[1].contains("no"));
+        assert!(lines[4].contains("http://proxy.local:8080"));
+        assert!(lines[6].ends_with("  2"));
+        assert!(lines[7].ends_with("3m 12s"));
+        assert!(lines[9].ends_with("0.5.0"));
+        // Values line up after the longest label
+        let column = |line: &str| line.find("playwright").or_else(|| line.find("4242"));
+        assert_eq!(column(lines[2]), column(lines[8]));
+
+        // --json passes the payload through untouched
+        let resp = Response { success: true, data: Some(json!({ "launched": true, "tabs": 3 })), error: None };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_response(&mut out, &mut err, &resp, &opts(true, false, false), &json!({ "action": "status" })).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().trim(), r#"{"success":true,"data":{"launched":true,"tabs":3},"error":null}"#);
+    }
+
+    #[test]
+    fn test_status_sparse_payload() {
+        let out = render_status(json!({ "launched": false }), &opts(false, false, false));
+        assert_eq!(out.lines().count(), 1);
+        assert!(out.contains("Browser not running"));
+
+        let data = json!({ "launched": true, "headless": true, "stealth": false, "proxy": null, "tabs": "many" });
+        assert_eq!(row_labels(&data), vec!["Headless", "Stealth"]);
+        let out = render_status(data, &opts(false, false, false));
+        assert_eq!(out.lines().count(), 3);
+
+        let out = render_status(json!({ "pid": 7 }), &opts(false, false, false));
+        assert!(out.lines().next().unwrap().ends_with("Daemon running"));
+        assert!(out.contains("PID") && out.contains('7'));
+        assert!(render_status(json!({ "launched": true, "pid": 7 }), &opts(false, true, false)).is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::{self, BufRead, Write};
+use std::process::exit;
+
+use serde_json::{json, Value};
+
+use crate::commands::gen_id;
+use crate::connection::{persistent_sender, Response};
+use crate::output::{ErrorCode, ErrorResponse};
+
+/// `pipe [--strict]`: forward JSON command objects from stdin, one response
+/// line per command on stdout.
+pub fn run_pipe(args: &[String], session: &str) {
+    let strict = args.iter().any(|a| a == "--strict");
+    let mut send = persistent_sender(session);
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let failed = match pipe_loop(stdin.lock(), stdout.lock(), &mut send) {
+        Ok(failed) => failed,
+        Err(_) => exit(1),
+    };
+    if strict && failed {
+        exit(1);
+    }
+}
+
+/// Run every input line through `send` and write one response per line.
+/// Returns whether any command failed.
+pub fn pipe_loop<R, W, F>(reader: R, mut writer: W, send: &mut F) -> io::Result<bool>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(Value) -> Result<Response, String>,
+{
+    let mut any_failed = false;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (output, ok) = handle_line(&line, send);
+        any_failed |= !ok;
+        writeln!(writer, "{}", output)?;
+        writer.flush()?;
+    }
+    Ok(any_failed)
+}
+
+fn handle_line<F>(line: &str, send: &mut F) -> (Value, bool)
+where
+    F: FnMut(Value) -> Result<Response, String>,
+{
+    let mut cmd = match serde_json::from_str::<Value>(line) {
+        Ok(value @ Value::Object(_)) => value,
+        Ok(_) => return (input_error("Expected a JSON object", line), false),
+        Err(e) => return (input_error(&format!("Invalid JSON: {}", e), line), false),
+    };
+    if !cmd.get("id").is_some_and(|id| id.is_string() || id.is_number()) {
+        cmd["id"] = json!(gen_id());
+    }
+    // The daemon only takes string ids; the caller gets theirs back as sent
+    let id = cmd["id"].clone();
+    if let Some(n) = id.as_number() {
+        cmd["id"] = json!(n.to_string());
+    }
+
+    let mut output = match send(cmd) {
+        Ok(resp) => {
+            let mut value = serde_json::to_value(&resp).unwrap_or_default();
+            if !resp.success {
+                let error = resp.error.as_deref().unwrap_or("Unknown error");
+                value["code"] = json!(ErrorCode::for_command_error(error));
+            }
+            value
+        }
+        Err(e) => {
+            serde_json::to_value(ErrorResponse { success: false, error: &e, code: ErrorCode::for_connection_error(&e) })
+                .unwrap_or_default()
+        }
+    };
+    output["id"] = id;
+    let ok = output["success"] == true;
+    (output, ok)
+}
+
+fn input_error(msg: &str, line: &str) -> Value {
+    let mut value = serde_json::to_value(ErrorResponse { success: false, error: msg, code: ErrorCode::ParseError })
+        .unwrap_or_default();
+    value["input"] = json!(line);
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &str, send: &mut impl FnMut(Value) -> Result<Response, String>) -> (Vec<Value>, bool) {
+        let mut out = Vec::new();
+        let failed = pipe_loop(input.as_bytes(), &mut out, send).unwrap();
+        let lines = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        (lines, failed)
+    }
+
+    fn echo(cmd: Value) -> Result<Response, String> {
+        Ok(Response { success: true, data: Some(json!({ "action": cmd["action"] })), error: None })
+    }
+
+    #[test]
+    fn test_pipe_preserves_ids() {
+        let mut sent = Vec::new();
+        let mut send = |cmd: Value| {
+            sent.push(cmd.clone());
+            echo(cmd)
+        };
+        let (lines, failed) = run(
+            "{\"id\":\"a1\",\"action\":\"navigate\",\"url\":\"https://example.com\"}\n\n{\"id\":7,\"action\":\"snapshot\"}\n",
+            &mut send,
+        );
+        assert!(!failed);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["id"], "a1");
+        assert_eq!(lines[0]["data"]["action"], "navigate");
+        assert_eq!(lines[1]["id"], 7);
+        assert_eq!(sent[0]["url"], "https://example.com");
+        assert_eq!(sent[1]["id"], "7");
+    }
+
+    #[test]
+    fn test_pipe_generates_missing_id() {
+        let mut sent = Vec::new();
+        let mut send = |cmd: Value| {
+            sent.push(cmd.clone());
+            echo(cmd)
+        };
+        let (lines, _) = run("{\"action\":\"title\"}\n", &mut send);
+        let id = sent[0]["id"].as_str().unwrap().to_string();
+        assert!(!id.is_empty());
+        assert_eq!(lines[0]["id"], id);
+    }
+
+    #[test]
+    fn test_pipe_malformed_line_continues() {
+        let mut send = echo;
+        let (lines, failed) = run("{not json\n[1,2]\n{\"id\":\"ok\",\"action\":\"url\"}\n", &mut send);
+        assert!(failed);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["success"], false);
+        assert_eq!(lines[0]["code"], "parse_error");
+        assert_eq!(lines[0]["input"], "{not json");
+        assert_eq!(lines[1]["input"], "[1,2]");
+        assert_eq!(lines[2]["success"], true);
+    }
+
+    #[test]
+    fn test_pipe_command_failures() {
+        let mut send = |cmd: Value| {
+            if cmd["action"] == "click" {
+                Ok(Response { success: false, data: None, error: Some("Element not found".to_string()) })
+            } else {
+                Err("Failed to connect: refused".to_string())
+            }
+        };
+        let (lines, failed) = run("{\"id\":\"1\",\"action\":\"click\"}\n{\"id\":\"2\",\"action\":\"url\"}\n", &mut send);
+        assert!(failed);
+        assert_eq!(lines[0]["id"], "1");
+        assert_eq!(lines[0]["error"], "Element not found");
+        assert_eq!(lines[0]["code"], "command_failed");
+        assert_eq!(lines[1]["id"], "2");
+        assert_eq!(lines[1]["code"], "daemon_unreachable");
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::connection::get_runtime_dir;
+use crate::flags::Flags;
+
+/// How one proxy is picked from a pool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    /// The next one after last time's pick, per session
+    RoundRobin,
+    Random,
+}
+
+impl Strategy {
+    /// `--proxy-strategy`; round-robin when not given
+    pub fn parse(value: Option<&str>) -> Result<Strategy, String> {
+        match value {
+            None | Some("round-robin") => Ok(Strategy::RoundRobin),
+            Some("random") => Ok(Strategy::Random),
+            Some(other) => Err(format!("--proxy-strategy: unknown strategy '{}' (expected round-robin or random)", other)),
+        }
+    }
+}
+
+/// Split `--proxy a,b`. A piece without a port or scheme continues the
+/// previous proxy, so `?bypass=a,b` lists stay intact.
+pub fn split_proxy_list(list: &str) -> Vec<String> {
+    let mut proxies: Vec<String> = Vec::new();
+    for piece in list.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let starts_proxy = piece.contains("://") || piece.contains(':');
+        match proxies.last_mut() {
+            Some(last) if !starts_proxy => {
+                last.push(',');
+                last.push_str(piece);
+            }
+            _ => proxies.push(piece.to_string()),
+        }
+    }
+    proxies
+}
+
+/// Proxies in a `--proxy-file`: one per line, blank lines and `#` comments
+/// skipped
+pub fn parse_proxy_file(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// Where a session keeps its round-robin position
+pub fn get_rotation_path(session: &str) -> PathBuf {
+    get_runtime_dir().join(format!("{}.proxy-index", session))
+}
+
+/// Next round-robin index into a pool of `len`, advancing the counter in
+/// `state`. A missing or unreadable counter starts at the first proxy.
+pub fn next_round_robin(state: &Path, len: usize) -> usize {
+    let count: usize = fs::read_to_string(state).ok().and_then(|s| s.trim().parse().ok()).unwrap_or(0);
+    let _ = fs::write(state, (count.wrapping_add(1) % len.max(1)).to_string());
+    count % len.max(1)
+}
+
+fn random_index(len: usize) -> usize {
+    // RandomState is keyed from the OS random source
+    (RandomState::new().build_hasher().finish() % len.max(1) as u64) as usize
+}
+
+/// The proxy for this invocation: `--proxy` entries followed by
+/// `--proxy-file` lines, one of them picked when there are several
+pub fn resolve_proxy(flags: &Flags) -> Result<Option<String>, String> {
+    let strategy = Strategy::parse(flags.proxy_strategy.as_deref())?;
+    let mut pool = flags.proxy.as_deref().map(split_proxy_list).unwrap_or_default();
+    if let Some(path) = &flags.proxy_file {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read --proxy-file {}: {}", path, e))?;
+        let proxies = parse_proxy_file(&content);
+        if proxies.is_empty() {
+            return Err(format!("--proxy-file {} lists no proxies", path));
+        }
+        pool.extend(proxies);
+    }
+    let index = match (pool.len(), strategy) {
+        (0, _) => return Ok(None),
+        (1, _) => 0,
+        (len, Strategy::RoundRobin) => next_round_robin(&get_rotation_path(&flags.session), len),
+        (len, Strategy::Random) => random_index(len),
+    };
+    Ok(pool.into_iter().nth(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_split_proxy_list() {
+        assert_eq!(split_proxy_list("http://a:8080"), vec!["http://a:8080"]);
+        assert_eq!(
+            split_proxy_list("http://a:8080, socks5://u:p@b:1080,c.example:3128"),
+            vec!["http://a:8080", "socks5://u:p@b:1080", "c.example:3128"]
+        );
+        // Bypass lists belong to their proxy
+        assert_eq!(
+            split_proxy_list("http://a:8080?bypass=*.internal,localhost,http://b:8080"),
+            vec!["http://a:8080?bypass=*.internal,localhost", "http://b:8080"]
+        );
+        assert_eq!(split_proxy_list("a:1,,b:2"), vec!["a:1", "b:2"]);
+        assert!(split_proxy_list("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_proxy_file() {
+        let content = "# pool A\nhttp://a:8080\n\n  http://b:8080  \n# http://disabled:8080\nsocks5://c:1080\n";
+        assert_eq!(parse_proxy_file(content), vec!["http://a:8080", "http://b:8080", "socks5://c:1080"]);
+        assert!(parse_proxy_file("# nothing yet\n").is_empty());
+    }
+
+    #[test]
+    fn test_round_robin_persists_between_runs() {
+        let dir = TempDir::new("proxy");
+        let state = dir.join("s.proxy-index");
+        let _ = fs::remove_file(&state);
+        let picks: Vec<usize> = (0..5).map(|_| next_round_robin(&state, 3)).collect();
+        assert_eq!(picks, vec![0, 1, 2, 0, 1]);
+        assert_eq!(fs::read_to_string(&state).unwrap(), "2");
+
+        // A smaller pool wraps the saved position
+        assert_eq!(next_round_robin(&state, 2), 0);
+        fs::write(&state, "garbage").unwrap();
+        assert_eq!(next_round_robin(&state, 4), 0);
+    }
+
+    #[test]
+    fn test_strategy_flag() {
+        assert_eq!(Strategy::parse(None).unwrap(), Strategy::RoundRobin);
+        assert_eq!(Strategy::parse(Some("round-robin")).unwrap(), Strategy::RoundRobin);
+        assert_eq!(Strategy::parse(Some("random")).unwrap(), Strategy::Random);
+        assert!(Strategy::parse(Some("sticky")).unwrap_err().contains("sticky"));
+        for _ in 0..20 {
+            assert!(random_index(3) < 3);
+        }
+    }
+
+    #[test]
+    fn test_resolve_single_and_file() {
+        let mut flags = crate::flags::parse_flags(&["--proxy".to_string(), "http://only:8080".to_string()]);
+        assert_eq!(resolve_proxy(&flags).unwrap().as_deref(), Some("http://only:8080"));
+
+        let dir = TempDir::new("proxy-file");
+        let file = dir.write("proxies.txt", "# none\n");
+        flags.proxy = None;
+        flags.proxy_file = Some(file.display().to_string());
+        assert!(resolve_proxy(&flags).unwrap_err().contains("lists no proxies"));
+
+        fs::write(&file, "http://a:8080\nhttp://b:8080\n").unwrap();
+        flags.proxy_strategy = Some("random".to_string());
+        let picked = resolve_proxy(&flags).unwrap().unwrap();
+        assert!(picked == "http://a:8080" || picked == "http://b:8080");
+    }
+}
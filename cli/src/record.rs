@@ -0,0 +1,178 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde_json::Value;
+
+use crate::install::which_exists;
+
+/// Artifact derived from a finished WebM recording (`record stop --gif|--frames`)
+#[derive(Debug, PartialEq)]
+pub enum Export {
+    /// Animated GIF written to this path
+    Gif(String),
+    /// PNG frame sequence written into this directory
+    Frames(String),
+}
+
+impl Export {
+    /// Read the export target from a parsed `recording_stop` command
+    pub fn from_command(cmd: &Value) -> Option<Export> {
+        if let Some(path) = cmd.get("gif").and_then(|v| v.as_str()) {
+            return Some(Export::Gif(path.to_string()));
+        }
+        cmd.get("framesDir")
+            .and_then(|v| v.as_str())
+            .map(|dir| Export::Frames(dir.to_string()))
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            Export::Gif(p) | Export::Frames(p) => p,
+        }
+    }
+
+    fn ffmpeg_args(&self, webm: &Path) -> Vec<String> {
+        let input = webm.to_string_lossy().to_string();
+        match self {
+            Export::Gif(out) => vec![
+                "-y".to_string(),
+                "-i".to_string(),
+                input,
+                "-vf".to_string(),
+                "fps=10,scale=800:-1:flags=lanczos,split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse"
+                    .to_string(),
+                "-loop".to_string(),
+                "0".to_string(),
+                out.clone(),
+            ],
+            Export::Frames(dir) => vec![
+                "-y".to_string(),
+                "-i".to_string(),
+                input,
+                Path::new(dir).join("frame-%04d.png").to_string_lossy().to_string(),
+            ],
+        }
+    }
+}
+
+/// Convert the recording at `webm` into `export` using ffmpeg.
+pub fn export_recording(webm: &str, export: &Export) -> Result<(), String> {
+    convert(webm, export, || which_exists("ffmpeg"), run_ffmpeg)
+}
+
+/// Conversion with the ffmpeg lookup and spawn injected so tests can stub them.
+fn convert<D, R>(webm: &str, export: &Export, has_ffmpeg: D, run: R) -> Result<(), String>
+where
+    D: FnOnce() -> bool,
+    R: FnOnce(&[String]) -> io::Result<bool>,
+{
+    if !has_ffmpeg() {
+        return Err(format!(
+            "ffmpeg not found: install ffmpeg to export {} (recording saved to {})",
+            match export {
+                Export::Gif(_) => "GIFs",
+                Export::Frames(_) => "frames",
+            },
+            webm
+        ));
+    }
+
+    let webm_path = Path::new(webm);
+    if !webm_path.is_file() {
+        return Err(format!("Recording not found: {}", webm));
+    }
+
+    if let Export::Frames(dir) = export {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create frames directory {}: {}", dir, e))?;
+    }
+
+    match run(&export.ffmpeg_args(webm_path)) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("ffmpeg failed to convert {} to {}", webm, export.path())),
+        Err(e) => Err(format!("Failed to run ffmpeg: {}", e)),
+    }
+}
+
+fn run_ffmpeg(args: &[String]) -> io::Result<bool> {
+    Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error"])
+        .args(args)
+        .stdin(Stdio::null())
+        .status()
+        .map(|s| s.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+
+    fn temp_webm(dir: &TempDir, name: &str) -> String {
+        dir.write(name, b"webm").to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_export_from_command() {
+        let gif = json!({ "action": "recording_stop", "gif": "out.gif" });
+        assert_eq!(Export::from_command(&gif), Some(Export::Gif("out.gif".to_string())));
+        let frames = json!({ "action": "recording_stop", "framesDir": "./frames" });
+        assert_eq!(Export::from_command(&frames), Some(Export::Frames("./frames".to_string())));
+        let plain = json!({ "action": "recording_stop" });
+        assert_eq!(Export::from_command(&plain), None);
+    }
+
+    #[test]
+    fn test_convert_ffmpeg_missing() {
+        let mut ran = false;
+        let result = convert("demo.webm", &Export::Gif("out.gif".to_string()), || false, |_| {
+            ran = true;
+            Ok(true)
+        });
+        let err = result.unwrap_err();
+        assert!(err.contains("ffmpeg"));
+        assert!(err.contains("demo.webm"));
+        assert!(!ran);
+    }
+
+    #[test]
+    fn test_convert_gif_args() {
+        let tmp = TempDir::new("record");
+        let webm = temp_webm(&tmp, "gif.webm");
+        let mut seen: Vec<String> = Vec::new();
+        let result = convert(&webm, &Export::Gif("out.gif".to_string()), || true, |args| {
+            seen = args.to_vec();
+            Ok(true)
+        });
+        assert!(result.is_ok());
+        assert!(seen.contains(&webm));
+        assert_eq!(seen.last().unwrap(), "out.gif");
+    }
+
+    #[test]
+    fn test_convert_frames_creates_dir() {
+        let tmp = TempDir::new("record");
+        let webm = temp_webm(&tmp, "frames.webm");
+        let dir = tmp.join("frames");
+        let dir_str = dir.to_string_lossy().to_string();
+        let mut seen: Vec<String> = Vec::new();
+        let result = convert(&webm, &Export::Frames(dir_str), || true, |args| {
+            seen = args.to_vec();
+            Ok(true)
+        });
+        assert!(result.is_ok());
+        assert!(dir.is_dir());
+        assert!(seen.last().unwrap().ends_with("frame-%04d.png"));
+    }
+
+    #[test]
+    fn test_convert_ffmpeg_failure() {
+        let tmp = TempDir::new("record");
+        let webm = temp_webm(&tmp, "fail.webm");
+        let result = convert(&webm, &Export::Gif("out.gif".to_string()), || true, |_| Ok(false));
+        assert!(result.unwrap_err().contains("ffmpeg failed"));
+    }
+}
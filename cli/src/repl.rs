@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::iter::Peekable;
+use std::path::PathBuf;
+use std::str::Chars;
+
+use serde_json::{json, Value};
+
+use crate::color;
+use crate::commands::{gen_id, parse_command};
+use crate::connection::{persistent_sender, Response};
+use crate::flags::{check_unknown_flags, clean_args, load_flags, Flags, GLOBAL_FLAGS_WITH_VALUE};
+use crate::output::{print_error, print_response, ErrorCode, OutputOptions};
+
+/// What the REPL loop should do after a line
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    Continue,
+    Exit,
+}
+
+/// `~/.agent-browser_history`
+fn history_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".agent-browser_history"))
+}
+
+/// Global args to apply to every REPL line: everything on the command line
+/// except the `repl` word itself.
+pub fn base_args(args: &[String]) -> Vec<String> {
+    let mut base = Vec::new();
+    let mut skipped = false;
+    for (i, arg) in args.iter().enumerate() {
+        let is_value = i > 0 && GLOBAL_FLAGS_WITH_VALUE.contains(&args[i - 1].as_str());
+        if !skipped && !is_value && arg == "repl" {
+            skipped = true;
+            continue;
+        }
+        base.push(arg.clone());
+    }
+    base
+}
+
+/// Read commands from stdin until `exit`, `quit` or EOF, sending each over a
+/// single daemon connection.
+pub fn run_repl(base: &[String], session: &str) {
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut history = history_path()
+        .and_then(|path| OpenOptions::new().create(true).append(true).open(path).ok());
+    let mut send = persistent_sender(session);
+
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            print!("{} ", color::dim(">"));
+            let _ = io::stdout().flush();
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        if let Some(file) = history.as_mut() {
+            if !line.trim().is_empty() {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+        if run_line(&line, base, &mut send) == Step::Exit {
+            break;
+        }
+    }
+}
+
+/// Parse and run one REPL line. Errors are printed, never fatal.
+pub fn run_line<F>(line: &str, base: &[String], send: &mut F) -> Step
+where
+    F: FnMut(Value) -> Result<Response, String>,
+{
+    let json_mode = base.iter().any(|a| a == "--json" || a == "--jsonl");
+    let tokens = match tokenize(line) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            print_error(ErrorCode::ParseError, &e, json_mode);
+            return Step::Continue;
+        }
+    };
+    match tokens.first().map(|s| s.as_str()) {
+        None => return Step::Continue,
+        Some("exit") | Some("quit") => {
+            if tokens.iter().any(|t| t == "--close") {
+                if let Err(e) = send(json!({ "id": gen_id(), "action": "close" })) {
+                    print_error(ErrorCode::for_connection_error(&e), &e, json_mode);
+                }
+            }
+            return Step::Exit;
+        }
+        _ => {}
+    }
+
+    let json_mode = json_mode || tokens.iter().any(|a| a == "--json" || a == "--jsonl");
+    let (cmd, flags) = match parse_line(base, tokens) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            print_error(ErrorCode::ParseError, &e, json_mode);
+            return Step::Continue;
+        }
+    };
+    match send(cmd.clone()) {
+        Ok(resp) => print_response(&resp, &OutputOptions::from_flags(&flags), &cmd),
+        Err(e) => print_error(ErrorCode::for_connection_error(&e), &e, flags.json),
+    }
+    Step::Continue
+}
+
+/// Parse one line's words (with `base` global args in front) into a daemon
+/// command and the flags that apply to it
+pub fn parse_line(base: &[String], tokens: Vec<String>) -> Result<(Value, Flags), String> {
+    let mut args = base.to_vec();
+    args.extend(tokens);
+    check_unknown_flags(&args)?;
+    let flags = load_flags(&args)?;
+    let cmd = parse_command(&clean_args(&args), &flags).map_err(|e| e.format())?;
+    Ok((cmd, flags))
+}
+
+/// Split a line into words the way a POSIX shell would for quoting:
+/// 'single quotes' are literal, "double quotes" allow \" and \\ escapes,
+/// and a backslash outside quotes escapes the next character.
+pub fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    tokenize_with_vars(line, None)
+}
+
+/// [`tokenize`], also replacing `${KEY}` from `vars` outside single quotes
+/// (`\$` keeps a literal `$`). A value goes into the word as-is, so spaces
+/// or quotes in it never split the word or start a quote. An unknown key is
+/// an error.
+pub fn tokenize_with_vars(line: &str, vars: Option<&HashMap<String, String>>) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            '\'' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("Unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('$') => match take_var(&mut chars, vars, line)? {
+                            Some(value) => current.push_str(&value),
+                            None => current.push('$'),
+                        },
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err("Unterminated double quote".to_string()),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err("Unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_token = true;
+                match chars.next() {
+                    Some(c) => current.push(c),
+                    None => return Err("Trailing backslash".to_string()),
+                }
+            }
+            '$' => {
+                in_token = true;
+                match take_var(&mut chars, vars, line)? {
+                    Some(value) => current.push_str(&value),
+                    None => current.push('$'),
+                }
+            }
+            c => {
+                in_token = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+/// The value for a `${KEY}` whose `$` was just read, or None (nothing
+/// consumed) when there are no vars or no `{` follows
+fn take_var(chars: &mut Peekable<Chars>, vars: Option<&HashMap<String, String>>, line: &str) -> Result<Option<String>, String> {
+    let Some(vars) = vars else { return Ok(None) };
+    if chars.next_if_eq(&'{').is_none() {
+        return Ok(None);
+    }
+    let mut key = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => key.push(c),
+            None => return Err(format!("Unterminated placeholder in: {}", line)),
+        }
+    }
+    vars.get(&key)
+        .cloned()
+        .map(Some)
+        .ok_or_else(|| format!("Undefined variable ${{{}}} (pass --var {}=...)", key, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tokenize_plain() {
+        assert_eq!(tokenize("  click  @e3 ").unwrap(), words(&["click", "@e3"]));
+        assert!(tokenize("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tokenize_quotes() {
+        assert_eq!(
+            tokenize(r#"fill @e2 "hello world""#).unwrap(),
+            words(&["fill", "@e2", "hello world"])
+        );
+        assert_eq!(
+            tokenize(r#"eval 'document.querySelector("h1").textContent'"#).unwrap(),
+            words(&["eval", r#"document.querySelector("h1").textContent"#])
+        );
+        assert_eq!(tokenize(r#"fill @e1 """#).unwrap(), words(&["fill", "@e1", ""]));
+        assert_eq!(tokenize(r#"type #q "a"'b'c"#).unwrap(), words(&["type", "#q", "abc"]));
+    }
+
+    #[test]
+    fn test_tokenize_escapes() {
+        assert_eq!(
+            tokenize(r#"fill @e1 "say \"hi\" \\ now""#).unwrap(),
+            words(&["fill", "@e1", r#"say "hi" \ now"#])
+        );
+        assert_eq!(tokenize(r"type @e1 hello\ world").unwrap(), words(&["type", "@e1", "hello world"]));
+        assert_eq!(tokenize(r#"eval "a\nb""#).unwrap(), words(&["eval", r"a\nb"]));
+    }
+
+    #[test]
+    fn test_tokenize_unterminated() {
+        assert!(tokenize(r#"fill @e1 "oops"#).is_err());
+        assert!(tokenize("fill @e1 'oops").is_err());
+        assert!(tokenize(r"click \").is_err());
+    }
+
+    #[test]
+    fn test_base_args_drops_repl_word() {
+        assert_eq!(base_args(&words(&["--session", "a", "repl", "--json"])), words(&["--session", "a", "--json"]));
+        assert_eq!(base_args(&words(&["--session", "repl", "repl"])), words(&["--session", "repl"]));
+    }
+
+    fn ok() -> Result<Response, String> {
+        Ok(Response { success: true, data: Some(json!({})), error: None })
+    }
+
+    #[test]
+    fn test_run_line_dispatches_commands() {
+        let mut sent: Vec<Value> = Vec::new();
+        let mut send = |cmd: Value| {
+            sent.push(cmd);
+            ok()
+        };
+        let base = words(&["--quiet"]);
+        assert_eq!(run_line("open example.com", &base, &mut send), Step::Continue);
+        assert_eq!(run_line(r#"fill @e3 "a b""#, &base, &mut send), Step::Continue);
+        assert_eq!(run_line("", &base, &mut send), Step::Continue);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0]["action"], "navigate");
+        assert_eq!(sent[0]["url"], "https://example.com");
+        assert_eq!(sent[1]["action"], "fill");
+        assert_eq!(sent[1]["value"], "a b");
+    }
+
+    #[test]
+    fn test_run_line_errors_do_not_exit() {
+        let mut sent = 0;
+        let mut send = |_cmd: Value| {
+            sent += 1;
+            Err("Failed to connect: gone".to_string())
+        };
+        let base = Vec::new();
+        assert_eq!(run_line("bogus-command", &base, &mut send), Step::Continue);
+        assert_eq!(run_line(r#"fill @e1 "unterminated"#, &base, &mut send), Step::Continue);
+        assert_eq!(run_line("snapshot -i", &base, &mut send), Step::Continue);
+        assert_eq!(sent, 1);
+    }
+
+    #[test]
+    fn test_run_line_exit() {
+        let mut sent: Vec<Value> = Vec::new();
+        let mut send = |cmd: Value| {
+            sent.push(cmd);
+            ok()
+        };
+        assert_eq!(run_line("exit", &[], &mut send), Step::Exit);
+        assert_eq!(run_line("quit", &[], &mut send), Step::Exit);
+        assert_eq!(run_line("exit --close", &[], &mut send), Step::Exit);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0]["action"], "close");
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::connection::Response;
+
+/// Upper bound for a single backoff pause
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Actions that change the page. These are only retried when the failure
+/// happened before anything was done (element missing, daemon unreachable).
+const NON_IDEMPOTENT: &[&str] = &[
+    "click", "dblclick", "fill", "type", "press", "check", "uncheck", "select", "drag", "upload",
+    "keydown", "keyup", "mouseclick", "mousedown", "mouseup", "mousewheel", "scroll", "history_go",
+    "tab_new", "tab_close", "window_new", "close", "evaluate", "clipboard_write",
+];
+
+/// Why a command failed, as far as retrying is concerned
+#[derive(Debug, PartialEq)]
+pub enum Failure {
+    /// The target element wasn't there (yet); nothing was done
+    NotFound,
+    /// The command or the daemon ran out of time; it may have acted
+    Timeout,
+    /// The daemon couldn't be reached; the command was never sent
+    Unreachable,
+    /// Anything else: not worth retrying
+    Other,
+}
+
+/// Classify a daemon or connection error message
+pub fn classify(error: &str) -> Failure {
+    let lower = error.to_lowercase();
+    if lower.contains("not found") || lower.contains("no element") || lower.contains("unknown ref") {
+        Failure::NotFound
+    } else if error.starts_with("Failed to connect") {
+        Failure::Unreachable
+    } else if error.starts_with("Timeout")
+        || error.starts_with("Timed out")
+        || (error.contains("Timeout ") && error.contains("exceeded"))
+    {
+        Failure::Timeout
+    } else {
+        Failure::Other
+    }
+}
+
+fn is_idempotent(cmd: &Value) -> bool {
+    let action = cmd.get("action").and_then(|v| v.as_str()).unwrap_or("");
+    // find ... click / fill / check act through a locator command
+    let acts = cmd.get("subaction").and_then(|v| v.as_str()).is_some_and(|s| !matches!(s, "hover" | "text" | "locate"));
+    !NON_IDEMPOTENT.contains(&action) && !acts
+}
+
+/// Whether `cmd`, having failed with `error`, is safe and worth sending again
+pub fn should_retry(cmd: &Value, error: &str) -> bool {
+    match classify(error) {
+        Failure::NotFound | Failure::Unreachable => true,
+        Failure::Timeout => is_idempotent(cmd),
+        Failure::Other => false,
+    }
+}
+
+/// Pause before retry number `retry` (0-based): `base`, doubling each time
+pub fn backoff(base: Duration, retry: u32) -> Duration {
+    base.checked_mul(2u32.saturating_pow(retry)).unwrap_or(MAX_BACKOFF).min(MAX_BACKOFF)
+}
+
+/// Send `cmd`, retrying up to `retries` times on retryable failures. The
+/// final failure says how many attempts were made.
+pub fn send_with_retry<F, S, N>(
+    cmd: &Value,
+    retries: u32,
+    delay: Duration,
+    mut send: F,
+    mut sleep: S,
+    mut notice: N,
+) -> Result<Response, String>
+where
+    F: FnMut(Value) -> Result<Response, String>,
+    S: FnMut(Duration),
+    N: FnMut(&str),
+{
+    let mut attempt = 0;
+    loop {
+        let result = send(cmd.clone());
+        let error = match &result {
+            Ok(resp) if resp.success => return result,
+            Ok(resp) => resp.error.clone().unwrap_or_else(|| "Unknown error".to_string()),
+            Err(e) => e.clone(),
+        };
+        attempt += 1;
+        if attempt > retries || !should_retry(cmd, &error) {
+            if attempt == 1 {
+                return result;
+            }
+            let note = |e: String| format!("{} (after {} attempts)", e, attempt);
+            return match result {
+                Ok(resp) => Ok(Response { error: resp.error.map(note), ..resp }),
+                Err(e) => Err(note(e)),
+            };
+        }
+        let pause = backoff(delay, attempt - 1);
+        notice(&format!("Retrying in {}ms (attempt {} of {}): {}", pause.as_millis(), attempt + 1, retries + 1, error));
+        sleep(pause);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("Element \"#buy\" not found or not visible. Run 'snapshot' to see current page elements."), Failure::NotFound);
+        assert_eq!(classify("Unknown ref: e42"), Failure::NotFound);
+        assert_eq!(classify("Timeout 30000ms exceeded."), Failure::Timeout);
+        assert_eq!(classify("page.goto: Timeout 30000ms exceeded."), Failure::Timeout);
+        assert_eq!(classify("Timed out waiting for the daemon to respond"), Failure::Timeout);
+        assert_eq!(classify("Failed to connect: Connection refused"), Failure::Unreachable);
+        assert_eq!(classify("Selector \"a\" matched 3 elements."), Failure::Other);
+    }
+
+    #[test]
+    fn test_should_retry_respects_idempotency() {
+        let click = json!({ "action": "click", "selector": "#buy" });
+        let title = json!({ "action": "title" });
+        let find_click = json!({ "action": "getbyrole", "role": "button", "subaction": "click" });
+        assert!(should_retry(&click, "Element \"#buy\" not found or not visible."));
+        assert!(should_retry(&click, "Failed to connect: refused"));
+        assert!(!should_retry(&click, "Timeout 30000ms exceeded."));
+        assert!(!should_retry(&find_click, "Timed out waiting for the daemon to respond"));
+        assert!(should_retry(&title, "Timeout 30000ms exceeded."));
+        assert!(!should_retry(&title, "Browser not launched"));
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let base = Duration::from_millis(500);
+        assert_eq!(backoff(base, 0), Duration::from_millis(500));
+        assert_eq!(backoff(base, 1), Duration::from_millis(1000));
+        assert_eq!(backoff(base, 3), Duration::from_millis(4000));
+        assert_eq!(backoff(base, 10), MAX_BACKOFF);
+        assert_eq!(backoff(base, 40), MAX_BACKOFF);
+    }
+
+    fn failing(error: &str) -> Result<Response, String> {
+        Ok(Response { success: false, data: None, error: Some(error.to_string()) })
+    }
+
+    #[test]
+    fn test_retry_until_success() {
+        let mut calls = 0;
+        let mut slept = Vec::new();
+        let mut notices = Vec::new();
+        let result = send_with_retry(
+            &json!({ "action": "click", "selector": "#late" }),
+            3,
+            Duration::from_millis(100),
+            |_| {
+                calls += 1;
+                if calls < 3 {
+                    failing("Element \"#late\" not found or not visible.")
+                } else {
+                    Ok(Response { success: true, data: None, error: None })
+                }
+            },
+            |d| slept.push(d),
+            |n| notices.push(n.to_string()),
+        );
+        assert!(result.unwrap().success);
+        assert_eq!(calls, 3);
+        assert_eq!(slept, vec![Duration::from_millis(100), Duration::from_millis(200)]);
+        assert!(notices[0].contains("attempt 2 of 4"));
+    }
+
+    #[test]
+    fn test_retry_reports_attempts_on_final_failure() {
+        let mut calls = 0;
+        let result = send_with_retry(
+            &json!({ "action": "gettext", "selector": "#x" }),
+            2,
+            Duration::ZERO,
+            |_| {
+                calls += 1;
+                Err("Failed to connect: refused".to_string())
+            },
+            |_| {},
+            |_| {},
+        );
+        assert_eq!(calls, 3);
+        assert_eq!(result.err().as_deref(), Some("Failed to connect: refused (after 3 attempts)"));
+    }
+
+    #[test]
+    fn test_no_retry_for_unsafe_or_disabled() {
+        let mut calls = 0;
+        let result = send_with_retry(
+            &json!({ "action": "click", "selector": "#pay" }),
+            5,
+            Duration::ZERO,
+            |_| {
+                calls += 1;
+                failing("Timeout 30000ms exceeded.")
+            },
+            |_| panic!("should not sleep"),
+            |_| {},
+        );
+        assert_eq!(calls, 1);
+        assert_eq!(result.unwrap().error.as_deref(), Some("Timeout 30000ms exceeded."));
+
+        let mut calls = 0;
+        let _ = send_with_retry(&json!({ "action": "title" }), 0, Duration::ZERO, |_| {
+            calls += 1;
+            failing("Timeout 30000ms exceeded.")
+        }, |_| {}, |_| {});
+        assert_eq!(calls, 1);
+    }
+}
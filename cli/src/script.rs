@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::exit;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::color;
+use crate::connection::{persistent_sender, Response};
+use crate::flags::{global_args, Flags};
+use crate::output::{fail, print_response, ErrorCode, OutputOptions};
+use crate::repl::{parse_line, tokenize_with_vars};
+
+const USAGE: &str = "run <file> [--continue-on-error] [--var KEY=VALUE]...";
+
+/// A command line from a script file
+#[derive(Debug, PartialEq)]
+pub struct ScriptLine {
+    /// 1-based line number in the file
+    pub line: usize,
+    pub text: String,
+}
+
+/// Outcome of one script line
+#[derive(Serialize)]
+pub struct StepResult {
+    pub line: usize,
+    pub command: String,
+    pub success: bool,
+    pub data: Option<Value>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    request: Option<Value>,
+}
+
+struct RunOptions {
+    path: String,
+    continue_on_error: bool,
+    vars: HashMap<String, String>,
+}
+
+fn parse_run_args(rest: &[String]) -> Result<RunOptions, String> {
+    let mut path: Option<String> = None;
+    let mut continue_on_error = false;
+    let mut vars = HashMap::new();
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--continue-on-error" => continue_on_error = true,
+            "--var" => {
+                let pair = iter.next().ok_or_else(|| format!("Missing value for --var\nUsage: {}", USAGE))?;
+                let (key, value) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid --var '{}': expected KEY=VALUE", pair))?;
+                vars.insert(key.to_string(), value.to_string());
+            }
+            _ if path.is_none() => path = Some(arg.clone()),
+            _ => return Err(format!("Unexpected argument: {}\nUsage: {}", arg, USAGE)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("Missing script file\nUsage: {}", USAGE))?;
+    Ok(RunOptions { path, continue_on_error, vars })
+}
+
+/// Command lines of a script: blank lines and `#` comment lines are skipped
+pub fn parse_script(content: &str) -> Vec<ScriptLine> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, text)| (i + 1, text.trim()))
+        .filter(|(_, text)| !text.is_empty() && !text.starts_with('#'))
+        .map(|(line, text)| ScriptLine { line, text: text.to_string() })
+        .collect()
+}
+
+/// One command in script syntax, quoted so `tokenize` reads it back
+/// unchanged. With `redact`, what `fill`/`type` would enter becomes a
+/// `${NAME}` placeholder named after the target, to be passed to `run`
+/// with `--var NAME=...`.
+pub fn format_script_line(args: &[String], redact: bool) -> String {
+    let mut words: Vec<String> = args.iter().map(|a| quote_word(a)).collect();
+    if redact {
+        if let Some((target, value_at)) = typed_value(args) {
+            let end = args[value_at..].iter().position(|a| a.starts_with("--")).map_or(args.len(), |i| value_at + i);
+            if end > value_at {
+                words.splice(value_at..end, [format!("${{{}}}", placeholder_name(target))]);
+            }
+        }
+    }
+    words.join(" ")
+}
+
+/// For commands that enter text: what the text goes into, and where the
+/// text starts in `args`
+fn typed_value(args: &[String]) -> Option<(&str, usize)> {
+    match args.first()?.as_str() {
+        "fill" | "type" => Some((args.get(1)?, 2)),
+        // find <locator> <value> fill <text>
+        "find" if args.get(3).map(|s| s.as_str()) == Some("fill") => Some((args.get(2)?, 4)),
+        _ => None,
+    }
+}
+
+/// `#password` -> `PASSWORD`, `input[name=pin]` -> `INPUT_NAME_PIN`
+fn placeholder_name(target: &str) -> String {
+    let mut name = String::new();
+    for c in target.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_uppercase());
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_end_matches('_');
+    match name.chars().next() {
+        None => "SECRET".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("SECRET_{}", name),
+        Some(_) => name.to_string(),
+    }
+}
+
+/// A word as-is when it needs no quoting, else single or double quoted
+fn quote_word(word: &str) -> String {
+    let plain = !word.is_empty() && !word.chars().any(|c| c.is_whitespace() || matches!(c, '\'' | '"' | '\\' | '$' | '`'));
+    if plain {
+        word.to_string()
+    } else if !word.contains('\'') {
+        format!("'{}'", word)
+    } else {
+        let mut quoted = String::from("\"");
+        for c in word.chars() {
+            if matches!(c, '"' | '\\' | '$' | '`') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    }
+}
+
+/// Append a line to a `--record-script` file
+pub fn append_script_line(path: &Path, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+/// Run `lines` in order through `send`, calling `on_step` after each one.
+/// Stops after the first failure unless `continue_on_error`.
+pub fn run_lines<F, S>(
+    lines: &[ScriptLine],
+    vars: &HashMap<String, String>,
+    base: &[String],
+    continue_on_error: bool,
+    send: &mut F,
+    mut on_step: S,
+) -> Vec<StepResult>
+where
+    F: FnMut(Value) -> Result<Response, String>,
+    S: FnMut(&StepResult),
+{
+    let mut results = Vec::new();
+    for line in lines {
+        let parsed = tokenize_with_vars(&line.text, Some(vars)).and_then(|tokens| parse_line(base, tokens));
+        let (request, resp) = match parsed {
+            Ok((cmd, _)) => {
+                let resp = send(cmd.clone()).unwrap_or_else(|e| Response {
+                    success: false,
+                    data: None,
+                    error: Some(e),
+                });
+                (Some(cmd), resp)
+            }
+            Err(e) => (None, Response { success: false, data: None, error: Some(e) }),
+        };
+        let result = StepResult {
+            line: line.line,
+            command: line.text.clone(),
+            success: resp.success,
+            data: resp.data,
+            error: resp.error,
+            request,
+        };
+        on_step(&result);
+        let failed = !result.success;
+        results.push(result);
+        if failed && !continue_on_error {
+            break;
+        }
+    }
+    results
+}
+
+/// `run <file>`: execute a script over one daemon connection. `args` is the
+/// full command line; its global flags apply to every script line.
+pub fn run_script(clean: &[String], args: &[String], flags: &Flags) {
+    let json_mode = flags.json;
+    let options = match parse_run_args(&clean[1..]) {
+        Ok(o) => o,
+        Err(e) => fail(ErrorCode::ParseError, &e, json_mode),
+    };
+    let content = match fs::read_to_string(&options.path) {
+        Ok(c) => c,
+        Err(e) => fail(ErrorCode::CommandFailed, &format!("Failed to read {}: {}", options.path, e), json_mode),
+    };
+    let lines = parse_script(&content);
+    let base = global_args(args);
+    let mut send = persistent_sender(&flags.session);
+    let output = OutputOptions::from_flags(flags);
+
+    let results = run_lines(&lines, &options.vars, &base, options.continue_on_error, &mut send, |step| {
+        if json_mode {
+            return;
+        }
+        if step.success {
+            let resp = Response { success: true, data: step.data.clone(), error: None };
+            print_response(&resp, &output, step.request.as_ref().unwrap_or(&Value::Null));
+        } else {
+            eprintln!(
+                "{} Line {}: {}\n  {}",
+                color::error_indicator(),
+                step.line,
+                step.command,
+                step.error.as_deref().unwrap_or("Unknown error")
+            );
+        }
+    });
+
+    let failed = results.iter().filter(|r| !r.success).count();
+    if json_mode {
+        println!("{}", json!({ "success": failed == 0, "data": { "steps": results } }));
+    } else if failed > 0 && options.continue_on_error {
+        eprintln!("{} {} of {} steps failed", color::error_indicator(), failed, results.len());
+    }
+    if let Some(code) = failure_code(&results) {
+        exit(code.exit_code());
+    }
+}
+
+/// How a script failed, after its first failed step: a line that didn't
+/// parse is a parse error, otherwise the command's own failure
+fn failure_code(results: &[StepResult]) -> Option<ErrorCode> {
+    let step = results.iter().find(|r| !r.success)?;
+    Some(match step.request {
+        None => ErrorCode::ParseError,
+        Some(_) => ErrorCode::for_command_error(step.error.as_deref().unwrap_or_default()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repl::tokenize;
+    use crate::test_support::TempDir;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_script_skips_comments_and_blanks() {
+        let lines = parse_script("# login flow\nopen example.com\n\n   # indented comment\n  click #submit  \n");
+        assert_eq!(
+            lines,
+            vec![
+                ScriptLine { line: 2, text: "open example.com".to_string() },
+                ScriptLine { line: 5, text: "click #submit".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_substitute() {
+        let v = vars(&[("USER", "alice"), ("HOST", "example.com")]);
+        let expand = |line: &str| tokenize_with_vars(line, Some(&v));
+        assert_eq!(expand("fill #user ${USER}").unwrap(), words(&["fill", "#user", "alice"]));
+        assert_eq!(expand("open ${HOST}/u/${USER}").unwrap(), words(&["open", "example.com/u/alice"]));
+        assert_eq!(expand("eval 'x = {a: 1}'").unwrap(), words(&["eval", "x = {a: 1}"]));
+        assert!(expand("fill #p ${PASSWORD}").unwrap_err().contains("PASSWORD"));
+        assert!(expand("fill #p ${USER").is_err());
+    }
+
+    #[test]
+    fn test_substitute_keeps_value_in_one_word() {
+        let v = vars(&[("PASS", "a b"), ("QUOTE", "it's \"x\"")]);
+        let expand = |line: &str| tokenize_with_vars(line, Some(&v));
+        assert_eq!(expand("fill #p ${PASS}").unwrap(), words(&["fill", "#p", "a b"]));
+        assert_eq!(expand("fill #q ${QUOTE}").unwrap(), words(&["fill", "#q", "it's \"x\""]));
+        assert_eq!(expand("fill #q \"say ${QUOTE}\"").unwrap(), words(&["fill", "#q", "say it's \"x\""]));
+    }
+
+    #[test]
+    fn test_substitute_skips_single_quotes_and_escapes() {
+        let v = vars(&[]);
+        let expand = |line: &str| tokenize_with_vars(line, Some(&v));
+        // A JS template literal in a single-quoted eval is left alone
+        assert_eq!(expand("eval '`Hi ${name}`'").unwrap(), words(&["eval", "`Hi ${name}`"]));
+        assert_eq!(expand("fill #p \"\\${X}\"").unwrap(), words(&["fill", "#p", "${X}"]));
+        assert_eq!(expand("fill #p cost$5").unwrap(), words(&["fill", "#p", "cost$5"]));
+        // Without vars (the REPL) placeholders are plain text
+        assert_eq!(tokenize("fill #p ${X}").unwrap(), words(&["fill", "#p", "${X}"]));
+    }
+
+    fn words(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_format_script_line_round_trips() {
+        let cases: [&[&str]; 5] = [
+            &["click", "#submit"],
+            &["fill", "#name", "Jane Doe"],
+            &["fill", "#q", "it's \"quoted\" $HOME \\ `x`"],
+            &["eval", "document.title"],
+            &["fill", "#empty", ""],
+        ];
+        for args in cases {
+            let line = format_script_line(&words(args), false);
+            assert_eq!(tokenize(&line).unwrap(), words(args), "{}", line);
+        }
+        assert_eq!(format_script_line(&words(&["fill", "#name", "Jane Doe"]), false), "fill #name 'Jane Doe'");
+    }
+
+    #[test]
+    fn test_format_script_line_redacts() {
+        let redacted = |args: &[&str]| format_script_line(&words(args), true);
+        assert_eq!(redacted(&["fill", "#password", "hunter2"]), "fill #password ${PASSWORD}");
+        assert_eq!(redacted(&["type", "input[name=pin]", "12", "34"]), "type input[name=pin] ${INPUT_NAME_PIN}");
+        assert_eq!(redacted(&["find", "label", "API key", "fill", "sk-123", "--exact"]), "find label 'API key' fill ${API_KEY} --exact");
+        assert_eq!(redacted(&["fill", "@e3", "x"]), "fill @e3 ${E3}");
+        assert_eq!(redacted(&["fill", "#1", "x"]), "fill #1 ${SECRET_1}");
+        // Other commands are recorded as typed
+        assert_eq!(redacted(&["click", "#password"]), "click #password");
+        assert_eq!(redacted(&["find", "label", "Email", "click"]), "find label Email click");
+
+        // The placeholder is filled in again by run --var
+        let line = redacted(&["fill", "#password", "hunter2"]);
+        let tokens = tokenize_with_vars(&line, Some(&vars(&[("PASSWORD", "s3 'cret")]))).unwrap();
+        assert_eq!(tokens, words(&["fill", "#password", "s3 'cret"]));
+    }
+
+    #[test]
+    fn test_append_script_line() {
+        let dir = TempDir::new("record-script");
+        let path = dir.join("steps.txt");
+        append_script_line(&path, "open example.com").unwrap();
+        append_script_line(&path, "click #go").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "open example.com\nclick #go\n");
+        assert_eq!(parse_script(&content).len(), 2);
+    }
+
+    #[test]
+    fn test_parse_run_args() {
+        let args: Vec<String> = ["steps.txt", "--continue-on-error", "--var", "A=1=2"].iter().map(|s| s.to_string()).collect();
+        let options = parse_run_args(&args).unwrap();
+        assert_eq!(options.path, "steps.txt");
+        assert!(options.continue_on_error);
+        assert_eq!(options.vars["A"], "1=2");
+        assert!(parse_run_args(&["--var".to_string(), "NOEQUALS".to_string()]).is_err());
+        assert!(parse_run_args(&[]).is_err());
+    }
+
+    fn script() -> Vec<ScriptLine> {
+        parse_script("open ${HOST}\nclick #missing\nget title\n")
+    }
+
+    fn stub(sent: &mut Vec<Value>) -> impl FnMut(Value) -> Result<Response, String> + '_ {
+        move |cmd: Value| {
+            let ok = cmd["action"] != "click";
+            sent.push(cmd);
+            Ok(Response {
+                success: ok,
+                data: ok.then(|| json!({})),
+                error: (!ok).then(|| "Element not found".to_string()),
+            })
+        }
+    }
+
+    #[test]
+    fn test_run_stops_at_first_failure() {
+        let mut sent = Vec::new();
+        let results = {
+            let mut send = stub(&mut sent);
+            run_lines(&script(), &vars(&[("HOST", "example.com")]), &[], false, &mut send, |_| {})
+        };
+        assert_eq!(results.len(), 2);
+        assert!(results[0].success);
+        assert!(!results[1].success);
+        assert_eq!(results[1].line, 2);
+        assert_eq!(results[1].error.as_deref(), Some("Element not found"));
+        assert_eq!(failure_code(&results).map(ErrorCode::exit_code), Some(1));
+        assert!(failure_code(&results[..1]).is_none());
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0]["url"], "https://example.com");
+    }
+
+    #[test]
+    fn test_run_continue_on_error() {
+        let mut sent = Vec::new();
+        let mut seen = Vec::new();
+        let results = {
+            let mut send = stub(&mut sent);
+            run_lines(&script(), &vars(&[("HOST", "example.com")]), &[], true, &mut send, |step| {
+                seen.push(step.line)
+            })
+        };
+        assert_eq!(results.len(), 3);
+        assert_eq!(seen, vec![1, 2, 3]);
+        assert!(results[2].success);
+    }
+
+    #[test]
+    fn test_run_parse_error_is_step_failure() {
+        let mut sent = Vec::new();
+        let results = {
+            let mut send = stub(&mut sent);
+            run_lines(&script(), &HashMap::new(), &[], false, &mut send, |_| {})
+        };
+        assert_eq!(results.len(), 1);
+        assert!(results[0].error.as_deref().unwrap().contains("HOST"));
+        assert_eq!(failure_code(&results).map(ErrorCode::exit_code), Some(2));
+        assert!(sent.is_empty());
+    }
+
+    #[test]
+    fn test_step_result_json() {
+        let mut sent = Vec::new();
+        let results = {
+            let mut send = stub(&mut sent);
+            run_lines(&script(), &vars(&[("HOST", "a.com")]), &[], false, &mut send, |_| {})
+        };
+        let value = serde_json::to_value(&results).unwrap();
+        assert_eq!(value[0]["line"], 1);
+        assert_eq!(value[0]["command"], "open ${HOST}");
+        assert_eq!(value[1]["success"], false);
+        assert!(value[1].get("request").is_none());
+    }
+}
@@ -0,0 +1,753 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+#[cfg(windows)]
+use windows_sys::Win32::Foundation::CloseHandle;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::{
+    OpenProcess, TerminateProcess, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE,
+};
+
+use crate::commands::gen_id;
+#[cfg(windows)]
+use crate::connection::get_port_for_session;
+use crate::connection::{ensure_daemon, send_command, LaunchOptions};
+use crate::output::{fail, ErrorCode};
+
+/// Files kept in the runtime dir for a session: the daemon's, its saved
+/// launch options and the round-robin proxy counter
+const SESSION_FILE_EXTENSIONS: &[&str] = &["pid", "sock", "port", "launch", "proxy-index"];
+
+/// How long to wait after SIGTERM before escalating to SIGKILL
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// How long `daemon restart` waits for a closed daemon to exit before killing it
+const RESTART_EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `session ...`; `dir` is the runtime dir holding the pid files
+pub fn run_session(args: &[String], dir: &Path, session: &str, json_mode: bool) {
+    let subcommand = args.get(1).map(|s| s.as_str());
+
+    match subcommand {
+        Some("list") => {
+            let filter = match args.iter().position(|a| a == "--filter") {
+                Some(idx) => match args.get(idx + 1) {
+                    Some(filter) => Some(filter.as_str()),
+                    None => fail(ErrorCode::ParseError, "session list --filter needs a name substring", json_mode),
+                },
+                None => None,
+            };
+            let sessions = filter_sessions(list_sessions(dir, is_process_alive), filter);
+            if json_mode {
+                println!("{}", sessions_json(&sessions));
+            } else if sessions.is_empty() {
+                match filter {
+                    Some(filter) => println!("No sessions matching '{}'", filter),
+                    None => println!("No sessions"),
+                }
+            } else {
+                for line in format_session_table(&sessions, session) {
+                    println!("{}", line);
+                }
+                if sessions.iter().any(|s| !s.alive) {
+                    println!();
+                    println!("Stale sessions found; run 'z-agent-browser session clean' to remove them");
+                }
+            }
+        }
+        Some("kill") => {
+            let name = args.get(2).map(|s| s.as_str()).unwrap_or(session);
+            match kill_session(dir, name) {
+                Ok(()) => {
+                    if json_mode {
+                        println!("{}", json!({ "success": true, "data": { "killed": [name] } }));
+                    } else {
+                        println!("Killed session {}", name);
+                    }
+                }
+                Err(e) => fail(ErrorCode::CommandFailed, &e, json_mode),
+            }
+        }
+        Some("info") => {
+            let name = args.get(2).map(|s| s.as_str()).unwrap_or(session);
+            let mut info = match read_session_info(dir, name, is_process_alive) {
+                Ok(info) => info,
+                Err(e) => fail(ErrorCode::CommandFailed, &e, json_mode),
+            };
+            if info.state == "running" {
+                query_daemon(&mut info);
+            }
+            if json_mode {
+                println!("{}", json!({ "success": true, "data": info }));
+            } else {
+                for (key, value) in info.lines() {
+                    println!("{:<9} {}", format!("{}:", key), value);
+                }
+            }
+        }
+        Some("clean") => {
+            let cleaned = clean_stale_sessions(dir, is_process_alive);
+            if json_mode {
+                println!("{}", json!({ "success": true, "data": { "cleaned": cleaned } }));
+            } else if cleaned.is_empty() {
+                println!("No stale sessions");
+            } else {
+                println!("Removed stale sessions:");
+                for s in &cleaned {
+                    println!("  {}", s);
+                }
+            }
+        }
+        Some("current") if args.iter().any(|a| a == "--check") => {
+            // Scripts only look at the exit code
+            let running = is_session_running(dir, session, is_process_alive);
+            if json_mode {
+                println!("{}", json!({ "success": true, "data": { "session": session, "running": running } }));
+            } else {
+                println!("{}: {}", session, if running { "running" } else { "not running" });
+            }
+            std::process::exit(check_exit_code(running));
+        }
+        None | Some(_) => {
+            // Just show current session
+            if json_mode {
+                println!("{}", current_session_json(session));
+            } else {
+                println!("{}", session);
+            }
+        }
+    }
+}
+
+/// List sessions that have a pid or socket file in `dir`, with the pid if the
+/// pid file is readable. Sorted by name.
+fn scan_sessions(dir: &Path) -> Vec<(String, Option<u32>)> {
+    let mut names: Vec<String> = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_session_file = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| SESSION_FILE_EXTENSIONS.contains(&e));
+            if !is_session_file {
+                continue;
+            }
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                if !stem.is_empty() && !names.iter().any(|n| n == stem) {
+                    names.push(stem.to_string());
+                }
+            }
+        }
+    }
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let pid = read_pid(&pid_file(dir, &name));
+            (name, pid)
+        })
+        .collect()
+}
+
+fn pid_file(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.pid", name))
+}
+
+fn launch_file(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.launch", name))
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// One row of `session list`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionEntry {
+    name: String,
+    pid: Option<u32>,
+    alive: bool,
+    pid_file: String,
+    socket_path: String,
+    /// Pid file mtime as seconds since the Unix epoch
+    modified: Option<u64>,
+}
+
+/// Every session with files in `dir`, including stale ones (`alive: false`).
+fn list_sessions<F>(dir: &Path, alive: F) -> Vec<SessionEntry>
+where
+    F: Fn(u32) -> bool,
+{
+    scan_sessions(dir)
+        .into_iter()
+        .map(|(name, pid)| {
+            let pid_path = pid_file(dir, &name);
+            let modified = fs::metadata(&pid_path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs());
+            SessionEntry {
+                alive: pid.is_some_and(&alive),
+                pid,
+                pid_file: pid_path.display().to_string(),
+                socket_path: socket_path(dir, &name),
+                modified,
+                name,
+            }
+        })
+        .collect()
+}
+
+/// Sessions whose name contains `filter` (`session list --filter`)
+fn filter_sessions(sessions: Vec<SessionEntry>, filter: Option<&str>) -> Vec<SessionEntry> {
+    match filter {
+        Some(filter) => sessions.into_iter().filter(|s| s.name.contains(filter)).collect(),
+        None => sessions,
+    }
+}
+
+/// Whether `name` has a pid file naming a live process
+fn is_session_running<F>(dir: &Path, name: &str, alive: F) -> bool
+where
+    F: Fn(u32) -> bool,
+{
+    read_pid(&pid_file(dir, name)).is_some_and(alive)
+}
+
+/// `session current --check`: 0 when the daemon runs, 1 (command failed)
+/// when it doesn't
+fn check_exit_code(running: bool) -> i32 {
+    if running {
+        0
+    } else {
+        ErrorCode::CommandFailed.exit_code()
+    }
+}
+
+/// `session list --json` output
+fn sessions_json(sessions: &[SessionEntry]) -> String {
+    json!({ "success": true, "data": { "sessions": sessions } }).to_string()
+}
+
+/// `session --json` output
+fn current_session_json(session: &str) -> String {
+    json!({ "success": true, "data": { "session": session } }).to_string()
+}
+
+fn format_session_table(sessions: &[SessionEntry], current: &str) -> Vec<String> {
+    let name_width = sessions.iter().map(|s| s.name.len()).max().unwrap_or(0).max(4);
+    let mut lines = vec![format!("  {:<name_width$}  {:<8}  {:<6}  SOCKET", "NAME", "PID", "STATE")];
+    for s in sessions {
+        let marker = if s.name == current { "→" } else { " " };
+        let pid = s.pid.map(|p| p.to_string()).unwrap_or_else(|| "-".to_string());
+        let state = if s.alive { "alive" } else { "stale" };
+        lines.push(format!(
+            "{} {:<name_width$}  {:<8}  {:<6}  {}",
+            marker, s.name, pid, state, s.socket_path
+        ));
+    }
+    lines
+}
+
+/// Where clients connect to a session's daemon
+fn socket_path(dir: &Path, name: &str) -> String {
+    #[cfg(unix)]
+    {
+        dir.join(format!("{}.sock", name)).display().to_string()
+    }
+    #[cfg(windows)]
+    {
+        let _ = dir;
+        format!("127.0.0.1:{}", get_port_for_session(name))
+    }
+}
+
+/// Details shown by `session info`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionInfo {
+    name: String,
+    /// "running", or "stale" when the pid file outlived its process
+    state: &'static str,
+    pid: Option<u32>,
+    /// Seconds since the pid file was written (the daemon's start time)
+    uptime_secs: Option<u64>,
+    socket: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backend: Option<String>,
+}
+
+impl SessionInfo {
+    fn lines(&self) -> Vec<(&'static str, String)> {
+        let mut lines = vec![("name", self.name.clone()), ("state", self.state.to_string())];
+        if let Some(pid) = self.pid {
+            lines.push(("pid", pid.to_string()));
+        }
+        if let Some(secs) = self.uptime_secs {
+            lines.push(("uptime", format_uptime(secs)));
+        }
+        lines.push(("socket", self.socket.clone()));
+        for (key, value) in [("url", &self.url), ("mode", &self.mode), ("backend", &self.backend)] {
+            if let Some(v) = value {
+                lines.push((key, v.clone()));
+            }
+        }
+        lines
+    }
+}
+
+/// Build the file-based part of `session info`; the daemon is not contacted.
+fn read_session_info<F>(dir: &Path, name: &str, alive: F) -> Result<SessionInfo, String>
+where
+    F: Fn(u32) -> bool,
+{
+    let path = pid_file(dir, name);
+    let meta = fs::metadata(&path)
+        .map_err(|_| format!("Session '{}' not found (no pid file at {})", name, path.display()))?;
+    let pid = read_pid(&path);
+    let running = pid.is_some_and(alive);
+    let uptime_secs = meta
+        .modified()
+        .ok()
+        .and_then(|m| SystemTime::now().duration_since(m).ok())
+        .map(|d| d.as_secs())
+        .filter(|_| running);
+
+    let socket = socket_path(dir, name);
+    Ok(SessionInfo {
+        name: name.to_string(),
+        state: if running { "running" } else { "stale" },
+        pid,
+        uptime_secs,
+        socket,
+        url: None,
+        mode: None,
+        backend: None,
+    })
+}
+
+/// Fill in the live details from the daemon. Failures are ignored: the
+/// file-based info is still useful when the daemon doesn't answer.
+fn query_daemon(info: &mut SessionInfo) {
+    let data = |action: &str| {
+        send_command(json!({ "id": gen_id(), "action": action }), &info.name)
+            .ok()
+            .filter(|r| r.success)
+            .and_then(|r| r.data)
+    };
+    if let Some(status) = data("status") {
+        if status.get("launched").and_then(|v| v.as_bool()) == Some(true) {
+            let headless = status.get("headless").and_then(|v| v.as_bool()).unwrap_or(true);
+            info.mode = Some(if headless { "headless" } else { "headed" }.to_string());
+        } else {
+            info.mode = Some("not launched".to_string());
+        }
+        info.backend = status.get("backend").and_then(|v| v.as_str()).map(String::from);
+        if info.mode.as_deref() != Some("not launched") {
+            info.url = data("url")
+                .and_then(|d| d.get("url").and_then(|v| v.as_str()).map(String::from));
+        }
+    }
+}
+
+pub fn format_uptime(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{}h {:02}m {:02}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m {:02}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+/// Remove the runtime files of a session, ignoring ones that don't exist.
+fn remove_session_files(dir: &Path, name: &str) {
+    for ext in SESSION_FILE_EXTENSIONS {
+        let _ = fs::remove_file(dir.join(format!("{}.{}", name, ext)));
+    }
+}
+
+/// Remove files of sessions whose daemon is gone (dead pid or no pid file).
+/// Returns the names of the sessions that were cleaned up.
+fn clean_stale_sessions<F>(dir: &Path, alive: F) -> Vec<String>
+where
+    F: Fn(u32) -> bool,
+{
+    scan_sessions(dir)
+        .into_iter()
+        .filter(|(_, pid)| !pid.is_some_and(&alive))
+        .map(|(name, _)| {
+            remove_session_files(dir, &name);
+            name
+        })
+        .collect()
+}
+
+fn kill_session(dir: &Path, name: &str) -> Result<(), String> {
+    let path = pid_file(dir, name);
+    if !path.exists() {
+        return Err(format!("Session '{}' not found (no pid file at {})", name, path.display()));
+    }
+    if let Some(pid) = read_pid(&path) {
+        if is_process_alive(pid) {
+            terminate(pid)?;
+        }
+    }
+    remove_session_files(dir, name);
+    Ok(())
+}
+
+/// `daemon restart`: restart the session's daemon with the launch options it
+/// was started with, overridden by any given on this command line
+pub fn run_restart(dir: &Path, session: &str, requested: &LaunchOptions, json_mode: bool) {
+    let (old_pid, new_pid) = match restart_daemon(dir, session, requested) {
+        Ok(pids) => pids,
+        Err(e) => fail(ErrorCode::DaemonUnreachable, &e, json_mode),
+    };
+    if json_mode {
+        println!("{}", json!({ "success": true, "data": { "session": session, "oldPid": old_pid, "pid": new_pid } }));
+        return;
+    }
+    let pid = |pid: Option<u32>| pid.map(|p| p.to_string()).unwrap_or_else(|| "?".to_string());
+    match old_pid {
+        Some(old) => println!("Restarted session {} (pid {} -> {})", session, old, pid(new_pid)),
+        None => println!("Started session {} (pid {})", session, pid(new_pid)),
+    }
+}
+
+/// Close the running daemon (if any) and start a new one. Returns the old
+/// and new pids.
+fn restart_daemon(dir: &Path, session: &str, requested: &LaunchOptions) -> Result<(Option<u32>, Option<u32>), String> {
+    let old_pid = read_pid(&pid_file(dir, session)).filter(|pid| is_process_alive(*pid));
+    let mut options = requested.clone();
+    if let Some(pid) = old_pid {
+        let status = send_command(json!({ "id": gen_id(), "action": "status" }), session)
+            .ok()
+            .filter(|r| r.success)
+            .and_then(|r| r.data);
+        options = restart_options(dir, session, status.as_ref(), requested);
+        let _ = send_command(json!({ "id": gen_id(), "action": "close" }), session);
+        let start = Instant::now();
+        while start.elapsed() < RESTART_EXIT_TIMEOUT && is_process_alive(pid) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        if is_process_alive(pid) {
+            terminate(pid)?;
+        }
+        remove_session_files(dir, session);
+    }
+    ensure_daemon(session, &options)?;
+    // A new daemon only reads the environment; the rest comes with a launch
+    if options.needs_launch() {
+        match send_command(options.launch_command(&gen_id()), session) {
+            Ok(resp) if !resp.success => {
+                return Err(format!("Could not configure browser: {}", resp.error.unwrap_or_default()));
+            }
+            Err(e) => return Err(format!("Could not configure browser: {}", e)),
+            Ok(_) => {}
+        }
+    }
+    Ok((old_pid, read_pid(&pid_file(dir, session))))
+}
+
+/// Options to restart a session's daemon with: the ones it was started with
+/// (saved next to its pid file) and what its status reports, overridden by
+/// the ones given for the restart
+fn restart_options(dir: &Path, session: &str, status: Option<&Value>, requested: &LaunchOptions) -> LaunchOptions {
+    let saved = LaunchOptions::load(&launch_file(dir, session)).unwrap_or_default();
+    let reported = status.map(LaunchOptions::from_status).unwrap_or_default();
+    saved.merged_with(&reported).merged_with(requested)
+}
+
+/// SIGTERM the daemon, wait for it to exit, then SIGKILL if it's still around.
+#[cfg(unix)]
+fn terminate(pid: u32) -> Result<(), String> {
+    let pid = pid as i32;
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        return Err(format!("Failed to signal process {}: {}", pid, std::io::Error::last_os_error()));
+    }
+    let start = Instant::now();
+    while start.elapsed() < KILL_GRACE_PERIOD {
+        if !is_process_alive(pid as u32) {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    unsafe {
+        libc::kill(pid, libc::SIGKILL);
+    }
+    Ok(())
+}
+
+/// Windows has no graceful signal for a detached process, so terminate directly.
+#[cfg(windows)]
+fn terminate(pid: u32) -> Result<(), String> {
+    let ok = unsafe {
+        let handle = OpenProcess(PROCESS_TERMINATE, 0, pid);
+        if handle == 0 {
+            return Err(format!("Failed to open process {}", pid));
+        }
+        let ok = TerminateProcess(handle, 1) != 0;
+        CloseHandle(handle);
+        ok
+    };
+    if !ok {
+        return Err(format!("Failed to terminate process {}", pid));
+    }
+    let start = Instant::now();
+    while start.elapsed() < KILL_GRACE_PERIOD && is_process_alive(pid) {
+        thread::sleep(Duration::from_millis(100));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn is_process_alive(pid: u32) -> bool {
+    unsafe { libc::kill(pid as i32, 0) == 0 }
+}
+
+#[cfg(windows)]
+pub fn is_process_alive(pid: u32) -> bool {
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle != 0 {
+            CloseHandle(handle);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn temp_run_dir(name: &str) -> TempDir {
+        TempDir::new(&format!("session-{}", name))
+    }
+
+    #[test]
+    fn test_scan_sessions() {
+        let dir = temp_run_dir("scan");
+        fs::write(dir.join("default.pid"), "123\n").unwrap();
+        fs::write(dir.join("default.sock"), "").unwrap();
+        fs::write(dir.join("orphan.sock"), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        let sessions = scan_sessions(&dir);
+        assert_eq!(
+            sessions,
+            vec![("default".to_string(), Some(123)), ("orphan".to_string(), None)]
+        );
+    }
+
+    #[test]
+    fn test_clean_stale_sessions() {
+        let dir = temp_run_dir("clean");
+        fs::write(dir.join("live.pid"), "1").unwrap();
+        fs::write(dir.join("live.sock"), "").unwrap();
+        fs::write(dir.join("dead.pid"), "2").unwrap();
+        fs::write(dir.join("dead.sock"), "").unwrap();
+        fs::write(dir.join("dead.proxy-index"), "3").unwrap();
+        fs::write(dir.join("orphan.sock"), "").unwrap();
+
+        let cleaned = clean_stale_sessions(&dir, |pid| pid == 1);
+        assert_eq!(cleaned, vec!["dead".to_string(), "orphan".to_string()]);
+        assert!(dir.join("live.pid").exists());
+        assert!(dir.join("live.sock").exists());
+        assert!(!dir.join("dead.pid").exists());
+        assert!(!dir.join("dead.sock").exists());
+        assert!(!dir.join("dead.proxy-index").exists());
+        assert!(!dir.join("orphan.sock").exists());
+    }
+
+    #[test]
+    fn test_kill_missing_session() {
+        let dir = temp_run_dir("kill-missing");
+        let err = kill_session(&dir, "nope").unwrap_err();
+        assert!(err.contains("'nope' not found"));
+    }
+
+    #[test]
+    fn test_kill_dead_session_removes_files() {
+        let dir = temp_run_dir("kill-dead");
+        // Garbage pid: nothing to signal, but the files still get removed
+        fs::write(dir.join("gone.pid"), "not-a-pid").unwrap();
+        fs::write(dir.join("gone.sock"), "").unwrap();
+        kill_session(&dir, "gone").unwrap();
+        assert!(!dir.join("gone.pid").exists());
+        assert!(!dir.join("gone.sock").exists());
+    }
+
+    #[test]
+    fn test_session_info_running() {
+        let dir = temp_run_dir("info-running");
+        fs::write(dir.join("work.pid"), "42").unwrap();
+        let info = read_session_info(&dir, "work", |pid| pid == 42).unwrap();
+        assert_eq!(info.state, "running");
+        assert_eq!(info.pid, Some(42));
+        assert!(info.uptime_secs.is_some());
+        let keys: Vec<_> = info.lines().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec!["name", "state", "pid", "uptime", "socket"]);
+        let value = serde_json::to_value(&info).unwrap();
+        assert!(value["uptimeSecs"].is_u64());
+    }
+
+    #[test]
+    fn test_session_info_stale() {
+        let dir = temp_run_dir("info-stale");
+        fs::write(dir.join("old.pid"), "42").unwrap();
+        let info = read_session_info(&dir, "old", |_| false).unwrap();
+        assert_eq!(info.state, "stale");
+        assert!(info.uptime_secs.is_none());
+    }
+
+    #[test]
+    fn test_session_info_missing() {
+        let dir = temp_run_dir("info-missing");
+        assert!(read_session_info(&dir, "none", |_| true).is_err());
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(7), "7s");
+        assert_eq!(format_uptime(125), "2m 05s");
+        assert_eq!(format_uptime(3723), "1h 02m 03s");
+    }
+
+    #[test]
+    fn test_list_sessions_includes_stale() {
+        let dir = temp_run_dir("list");
+        let live_pid = std::process::id();
+        fs::write(dir.join("live.pid"), live_pid.to_string()).unwrap();
+        fs::write(dir.join("live.sock"), "").unwrap();
+        fs::write(dir.join("dead.pid"), "999999999").unwrap();
+
+        let sessions = list_sessions(&dir, is_process_alive);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].name, "dead");
+        assert!(!sessions[0].alive);
+        assert_eq!(sessions[1].name, "live");
+        assert!(sessions[1].alive);
+        assert_eq!(sessions[1].pid, Some(live_pid));
+        assert!(sessions[1].pid_file.ends_with("live.pid"));
+        assert!(sessions[1].modified.is_some());
+
+        let value = serde_json::to_value(&sessions[1]).unwrap();
+        assert_eq!(value["alive"], true);
+        assert!(value["socketPath"].is_string());
+    }
+
+    #[test]
+    fn test_session_table_marks_current() {
+        let dir = temp_run_dir("table");
+        fs::write(dir.join("default.pid"), "1").unwrap();
+        fs::write(dir.join("other.pid"), "2").unwrap();
+        let sessions = list_sessions(&dir, |pid| pid == 1);
+        let lines = format_session_table(&sessions, "default");
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("NAME"));
+        assert!(lines[1].starts_with("→ default"));
+        assert!(lines[1].contains("alive"));
+        assert!(lines[2].starts_with("  other"));
+        assert!(lines[2].contains("stale"));
+    }
+
+    #[test]
+    fn test_list_sessions_filter() {
+        let dir = temp_run_dir("filter");
+        for name in ["scraper-1", "scraper-2", "default"] {
+            fs::write(dir.join(format!("{}.pid", name)), "1").unwrap();
+        }
+        let names = |filter| -> Vec<String> {
+            filter_sessions(list_sessions(&dir, |_| true), filter).into_iter().map(|s| s.name).collect()
+        };
+        assert_eq!(names(Some("scraper")), vec!["scraper-1", "scraper-2"]);
+        assert_eq!(names(Some("-2")), vec!["scraper-2"]);
+        assert!(names(Some("nope")).is_empty());
+        assert_eq!(names(None).len(), 3);
+    }
+
+    #[test]
+    fn test_current_check() {
+        let dir = temp_run_dir("current-check");
+        fs::write(dir.join("live.pid"), "42").unwrap();
+        fs::write(dir.join("dead.pid"), "43").unwrap();
+        fs::write(dir.join("orphan.sock"), "").unwrap();
+        let alive = |pid| pid == 42;
+        assert!(is_session_running(&dir, "live", alive));
+        assert!(!is_session_running(&dir, "dead", alive));
+        assert!(!is_session_running(&dir, "orphan", alive));
+        assert!(!is_session_running(&dir, "missing", alive));
+
+        assert_eq!(check_exit_code(true), 0);
+        assert_eq!(check_exit_code(false), 1);
+    }
+
+    #[test]
+    fn test_session_json_escapes_names() {
+        let name = r#"qa "nightly" \ run"#;
+        let value: serde_json::Value = serde_json::from_str(&current_session_json(name)).unwrap();
+        assert_eq!(value, json!({ "success": true, "data": { "session": name } }));
+
+        let entry = SessionEntry {
+            name: name.to_string(),
+            pid: Some(42),
+            alive: true,
+            pid_file: format!("/tmp/{}.pid", name),
+            socket_path: format!("/tmp/{}.sock", name),
+            modified: None,
+        };
+        let value: serde_json::Value = serde_json::from_str(&sessions_json(&[entry])).unwrap();
+        assert_eq!(value["success"], true);
+        assert_eq!(value["data"]["sessions"][0]["name"], name);
+        assert_eq!(value["data"]["sessions"][0]["pidFile"], format!("/tmp/{}.pid", name));
+    }
+
+    fn launch_for(cli: &str) -> LaunchOptions {
+        let args: Vec<String> = cli.split_whitespace().map(String::from).collect();
+        LaunchOptions::from(&crate::flags::parse_flags(&args))
+    }
+
+    #[test]
+    fn test_restart_keeps_extension_and_proxy() {
+        let dir = temp_run_dir("restart");
+        launch_for("--extension /ext --proxy http://u:p@proxy:8080 --user-data-dir /data open x")
+            .save(&launch_file(&dir, "s"))
+            .unwrap();
+        // The daemon's status only reports part of its configuration
+        let status = json!({ "launched": true, "headless": true, "stealth": false });
+
+        let opts = restart_options(&dir, "s", Some(&status), &LaunchOptions::default());
+        assert!(opts.daemon_env().contains(&("AGENT_BROWSER_EXTENSIONS", "/ext".to_string())));
+        assert!(opts.needs_launch());
+        let launch = opts.launch_command("1");
+        assert_eq!(launch["proxy"], json!({ "server": "http://proxy:8080", "username": "u", "password": "p" }));
+        assert_eq!(launch["userDataDir"], "/data");
+
+        // Flags given to restart win over the saved ones
+        let requested = launch_for("--extension /other --proxy http://new:3128 open x");
+        let opts = restart_options(&dir, "s", Some(&status), &requested);
+        assert_eq!(opts.extensions, vec!["/other".to_string()]);
+        assert_eq!(opts.launch_command("2")["proxy"], json!({ "server": "http://new:3128" }));
+
+        // Started by an older CLI: fall back to what status reports
+        let opts = restart_options(&dir, "old", Some(&json!({ "headless": false })), &LaunchOptions::default());
+        assert!(opts.headed);
+        assert!(opts.extensions.is_empty());
+    }
+}
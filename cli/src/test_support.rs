@@ -0,0 +1,60 @@
+//! Fixtures shared by the unit tests
+
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A fresh, empty directory under the system temp dir, removed with
+/// everything in it when dropped. Derefs to its path.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("z-agent-browser-test-{}-{}-{}", std::process::id(), id, name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    /// Write `content` to `name` in the directory and return its path
+    pub fn write(&self, name: &str, content: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_dir_is_removed_on_drop() {
+        let dir = TempDir::new("drop");
+        let path = dir.to_path_buf();
+        dir.write("nested.txt", "x");
+        assert!(path.join("nested.txt").is_file());
+        assert_ne!(path, TempDir::new("drop").to_path_buf());
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::connection::get_scratch_dir;
+use crate::session::format_uptime;
+
+/// How long a command took: `132ms`, `1.8s`, `2m 05s`
+pub fn format_duration(d: Duration) -> String {
+    let ms = d.as_millis();
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", d.as_secs_f64())
+    } else {
+        format_uptime(d.as_secs())
+    }
+}
+
+/// Where `--timing-log` appends for a session (next to the daemon log)
+pub fn get_timings_path(session: &str) -> PathBuf {
+    get_scratch_dir().join(format!("z-agent-browser-{}.timings", session))
+}
+
+/// Append one tab-separated line: unix time (ms), action, duration (ms),
+/// `ok` or `failed`
+pub fn append_timing(path: &Path, action: &str, duration: Duration, success: bool) -> io::Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{}\t{}\t{}\t{}",
+        now,
+        action,
+        duration.as_millis(),
+        if success { "ok" } else { "failed" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(0)), "0ms");
+        assert_eq!(format_duration(Duration::from_millis(90)), "90ms");
+        assert_eq!(format_duration(Duration::from_millis(999)), "999ms");
+        assert_eq!(format_duration(Duration::from_millis(1000)), "1.0s");
+        assert_eq!(format_duration(Duration::from_millis(1840)), "1.8s");
+        assert_eq!(format_duration(Duration::from_millis(59_900)), "59.9s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m 05s");
+    }
+
+    #[test]
+    fn test_append_timing() {
+        let dir = TempDir::new("timing");
+        let path = dir.join("s.timings");
+
+        append_timing(&path, "snapshot", Duration::from_millis(1800), true).unwrap();
+        append_timing(&path, "click", Duration::from_millis(90), false).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let rows: Vec<Vec<&str>> = content.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][1..], ["snapshot", "1800", "ok"]);
+        assert_eq!(rows[1][1..], ["click", "90", "failed"]);
+        assert!(rows[0][0].parse::<u128>().is_ok());
+
+        assert!(append_timing(&dir.join("missing/s.timings"), "click", Duration::ZERO, true).is_err());
+    }
+
+    #[test]
+    fn test_timings_path() {
+        assert!(get_timings_path("work").ends_with("z-agent-browser-work.timings"));
+    }
+}
@@ -0,0 +1,107 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Open a saved trace in the Playwright trace viewer (`npx playwright show-trace`).
+pub fn run_trace_view(path: &str) -> Result<(), String> {
+    view_trace(path, launch_show_trace)
+}
+
+/// Validate the trace path, then hand it to `launch`, which returns whether the
+/// viewer exited successfully. Split out so tests can stub the spawn.
+fn view_trace<F>(path: &str, launch: F) -> Result<(), String>
+where
+    F: FnOnce(&Path) -> io::Result<bool>,
+{
+    let trace_path = Path::new(path);
+    if !trace_path.exists() {
+        return Err(format!("Trace file not found: {}", path));
+    }
+    if !trace_path.is_file() {
+        return Err(format!("Not a trace file: {} (expected a .zip from 'trace stop')", path));
+    }
+
+    match launch(trace_path) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("Trace viewer failed to open {}", path)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(
+            "npx not found. Make sure Node.js is installed and npx is in your PATH".to_string(),
+        ),
+        Err(e) => Err(format!("Failed to run npx: {}", e)),
+    }
+}
+
+fn launch_show_trace(path: &Path) -> io::Result<bool> {
+    // On Windows npx is npx.cmd, so run it through cmd.exe (same as run_install).
+    #[cfg(windows)]
+    let status = Command::new("cmd")
+        .args(["/c", &format!("npx playwright show-trace \"{}\"", path.display())])
+        .status();
+
+    #[cfg(not(windows))]
+    let status = Command::new("npx")
+        .args(["playwright", "show-trace"])
+        .arg(path)
+        .status();
+
+    status.map(|s| s.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn temp_trace(dir: &TempDir, name: &str) -> std::path::PathBuf {
+        dir.write(name, b"PK")
+    }
+
+    #[test]
+    fn test_view_trace_missing_file() {
+        let mut called = false;
+        let result = view_trace("/nonexistent/trace.zip", |_| {
+            called = true;
+            Ok(true)
+        });
+        assert!(result.unwrap_err().contains("not found"));
+        assert!(!called);
+    }
+
+    #[test]
+    fn test_view_trace_directory() {
+        let dir = std::env::temp_dir();
+        let result = view_trace(dir.to_str().unwrap(), |_| Ok(true));
+        assert!(result.unwrap_err().contains("Not a trace file"));
+    }
+
+    #[test]
+    fn test_view_trace_launches_viewer() {
+        let dir = TempDir::new("trace");
+        let path = temp_trace(&dir, "ok.zip");
+        let mut seen = None;
+        let result = view_trace(path.to_str().unwrap(), |p| {
+            seen = Some(p.to_path_buf());
+            Ok(true)
+        });
+        assert!(result.is_ok());
+        assert_eq!(seen.unwrap(), path);
+    }
+
+    #[test]
+    fn test_view_trace_npx_missing() {
+        let dir = TempDir::new("trace");
+        let path = temp_trace(&dir, "npx.zip");
+        let result = view_trace(path.to_str().unwrap(), |_| {
+            Err(io::Error::new(io::ErrorKind::NotFound, "no npx"))
+        });
+        assert!(result.unwrap_err().contains("npx not found"));
+    }
+
+    #[test]
+    fn test_view_trace_viewer_failure() {
+        let dir = TempDir::new("trace");
+        let path = temp_trace(&dir, "fail.zip");
+        let result = view_trace(path.to_str().unwrap(), |_| Ok(false));
+        assert!(result.unwrap_err().contains("failed to open"));
+    }
+}
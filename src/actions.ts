@@ -1,5 +1,6 @@
 import type { Page, Frame } from 'playwright-core';
 import type { BrowserManager, ScreencastFrame } from './browser.js';
+import { stealthToEnv } from './browser.js';
 import type {
   Command,
   Response,
@@ -484,8 +485,9 @@ async function handleConfigure(
     proxy: command.proxy,
   };
 
-  if (command.stealth) {
-    process.env.AGENT_BROWSER_STEALTH = '1';
+  const stealthEnv = stealthToEnv(command.stealth);
+  if (stealthEnv) {
+    process.env.AGENT_BROWSER_STEALTH = stealthEnv;
   } else {
     delete process.env.AGENT_BROWSER_STEALTH;
   }
@@ -637,6 +639,23 @@ async function handleEvaluate(
 ): Promise<Response<EvaluateData>> {
   const page = browser.getPage();
 
+  if (command.await) {
+    // Run the script and settle its promise separately, so a rejection can be
+    // told apart from a synchronous throw (which propagates as usual)
+    const outcome = await page.evaluate(async (script) => {
+      const value = (0, eval)(script);
+      try {
+        return { value: await value };
+      } catch (e) {
+        return { rejected: e instanceof Error ? `${e.name}: ${e.message}` : String(e) };
+      }
+    }, command.script);
+    if ('rejected' in outcome) {
+      throw new Error(`Promise rejected: ${outcome.rejected}`);
+    }
+    return successResponse(command.id, { result: outcome.value });
+  }
+
   // Evaluate the script directly as a string expression
   const result = await page.evaluate(command.script);
 
@@ -1074,6 +1093,7 @@ async function handleRoute(command: RouteCommand, browser: BrowserManager): Prom
   await browser.addRoute(command.url, {
     response: command.response,
     abort: command.abort,
+    continue: command.continue,
   });
   return successResponse(command.id, { routed: command.url });
 }
@@ -1,5 +1,5 @@
 import { describe, it, expect, beforeAll, afterAll } from 'vitest';
-import { BrowserManager } from './browser.js';
+import { BrowserManager, stealthFromEnv, stealthToEnv } from './browser.js';
 
 describe('BrowserManager', () => {
   let browser: BrowserManager;
@@ -631,3 +631,21 @@ describe('BrowserManager', () => {
     });
   });
 });
+
+describe('stealth settings', () => {
+  it('should read AGENT_BROWSER_STEALTH values the CLI sets', () => {
+    expect(stealthFromEnv(undefined)).toBe(false);
+    expect(stealthFromEnv('1')).toEqual({ level: 'full' });
+    expect(stealthFromEnv('basic')).toEqual({ level: 'basic' });
+    expect(stealthFromEnv('navigator.webdriver, chrome.app')).toEqual({
+      evasions: ['navigator.webdriver', 'chrome.app'],
+    });
+  });
+
+  it('should round-trip through the environment value', () => {
+    expect(stealthToEnv(false)).toBeUndefined();
+    expect(stealthToEnv(true)).toBe('1');
+    expect(stealthToEnv({ level: 'basic' })).toBe('basic');
+    expect(stealthFromEnv(stealthToEnv({ evasions: ['sourceurl'] }))).toEqual({ evasions: ['sourceurl'] });
+  });
+});
@@ -19,9 +19,56 @@ import StealthPlugin from 'puppeteer-extra-plugin-stealth';
 import path from 'node:path';
 import os from 'node:os';
 import { existsSync, mkdirSync, rmSync } from 'node:fs';
-import type { LaunchCommand } from './types.js';
+import type { LaunchCommand, StealthSetting } from './types.js';
 import { type RefMap, type EnhancedSnapshot, getEnhancedSnapshot, parseRef } from './snapshot.js';
 
+// Evasions of the basic stealth preset: the ones that rarely break pages
+const BASIC_STEALTH_EVASIONS = [
+  'chrome.app',
+  'chrome.csi',
+  'chrome.loadTimes',
+  'chrome.runtime',
+  'navigator.languages',
+  'navigator.permissions',
+  'navigator.plugins',
+  'navigator.webdriver',
+  'window.outerdimensions',
+];
+
+/**
+ * Stealth setting from AGENT_BROWSER_STEALTH: `1`/`true` for the full preset,
+ * `basic`, or a comma list of evasions
+ */
+export function stealthFromEnv(value = process.env.AGENT_BROWSER_STEALTH): StealthSetting {
+  if (!value || value === '0' || value === 'false' || value === 'off') return false;
+  if (value === '1' || value === 'true' || value === 'full') return { level: 'full' };
+  if (value === 'basic') return { level: 'basic' };
+  const evasions = value
+    .split(',')
+    .map((e) => e.trim())
+    .filter(Boolean);
+  return evasions.length ? { evasions } : false;
+}
+
+/**
+ * AGENT_BROWSER_STEALTH value for a stealth setting (undefined when off)
+ */
+export function stealthToEnv(setting: StealthSetting | undefined): string | undefined {
+  if (!setting) return undefined;
+  if (setting === true) return '1';
+  if ('evasions' in setting) return setting.evasions.join(',');
+  return setting.level === 'basic' ? 'basic' : '1';
+}
+
+/**
+ * Evasions to enable for a stealth setting: all of them (undefined) or a subset
+ */
+function stealthEvasions(setting: StealthSetting): string[] | undefined {
+  if (typeof setting === 'boolean') return undefined;
+  if ('evasions' in setting) return setting.evasions;
+  return setting.level === 'basic' ? BASIC_STEALTH_EVASIONS : undefined;
+}
+
 // Screencast frame data from CDP
 export interface ScreencastFrame {
   data: string; // base64 encoded image
@@ -102,7 +149,7 @@ export class BrowserManager {
   // Current launch configuration for status reporting
   private launchConfig: {
     headless: boolean;
-    stealth: boolean;
+    stealth: StealthSetting;
     profile?: string;
     cdpPort?: number | string;
   } = { headless: true, stealth: false };
@@ -120,7 +167,7 @@ export class BrowserManager {
   getStatus(): {
     launched: boolean;
     headless: boolean;
-    stealth: boolean;
+    stealth: StealthSetting;
     profile?: string;
     cdpPort?: number | string;
   } {
@@ -339,6 +386,11 @@ export class BrowserManager {
         headers?: Record<string, string>;
       };
       abort?: boolean;
+      continue?: {
+        url?: string;
+        method?: string;
+        headers?: Record<string, string>;
+      };
     }
   ): Promise<void> {
     const page = this.getPage();
@@ -353,6 +405,13 @@ export class BrowserManager {
           contentType: options.response.contentType ?? 'text/plain',
           headers: options.response.headers,
         });
+      } else if (options.continue) {
+        const { url: overrideUrl, method, headers } = options.continue;
+        await route.continue({
+          url: overrideUrl,
+          method,
+          headers: headers ? { ...route.request().headers(), ...headers } : undefined,
+        });
       } else {
         await route.continue();
       }
@@ -780,11 +839,12 @@ export class BrowserManager {
       throw new Error('Extensions are only supported in Chromium');
     }
 
-    const useStealth =
-      process.env.AGENT_BROWSER_STEALTH === '1' || process.env.AGENT_BROWSER_STEALTH === 'true';
+    const stealth = options.stealth ?? stealthFromEnv();
+    const useStealth = stealth !== false;
 
     if (useStealth && browserType === 'chromium') {
-      stealthChromium.use(StealthPlugin());
+      const evasions = stealthEvasions(stealth);
+      stealthChromium.use(StealthPlugin(evasions ? { enabledEvasions: new Set(evasions) } : undefined));
       // Ensure args is always an array - stealth plugin assumes this
       if (!options.args) {
         options.args = [];
@@ -865,7 +925,7 @@ export class BrowserManager {
 
     this.launchConfig = {
       headless: options.headless ?? true,
-      stealth: useStealth,
+      stealth,
       profile: options.profile,
     };
   }
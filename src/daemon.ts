@@ -2,11 +2,13 @@ import * as net from 'net';
 import * as fs from 'fs';
 import * as path from 'path';
 import * as os from 'os';
+import * as crypto from 'crypto';
 import { BrowserManager } from './browser.js';
 import { parseCommand, serializeResponse, errorResponse, successResponse } from './protocol.js';
 import { executeCommand } from './actions.js';
 import { StreamServer } from './stream-server.js';
 import { PlaywrightMCPBackend } from './playwright-mcp.js';
+import type { Command, PingData, Response } from './types.js';
 
 type BackendType = 'native' | 'playwright-mcp';
 const backendType: BackendType =
@@ -149,10 +151,12 @@ function getPortForSession(session: string): number {
 
 /**
  * Get the runtime directory for socket/pid files
- * Uses ~/.z-agent-browser/run/ for cross-platform consistency
+ * Uses AGENT_BROWSER_RUNTIME_DIR (set by the CLI), else ~/.z-agent-browser/run/
+ * for cross-platform consistency
  */
 function getRuntimeDir(): string {
-  const dir = path.join(os.homedir(), '.z-agent-browser', 'run');
+  const dir =
+    process.env.AGENT_BROWSER_RUNTIME_DIR || path.join(os.homedir(), '.z-agent-browser', 'run');
   if (!fs.existsSync(dir)) {
     fs.mkdirSync(dir, { recursive: true });
   }
@@ -225,9 +229,12 @@ export function getConnectionInfo(
 export function cleanupSocket(session?: string): void {
   const pidFile = getPidFile(session);
   const streamPortFile = getStreamPortFile(session);
+  // Launch options the CLI saved next to the pid file, for `daemon restart`
+  const launchFile = path.join(getRuntimeDir(), `${session ?? currentSession}.launch`);
   try {
     if (fs.existsSync(pidFile)) fs.unlinkSync(pidFile);
     if (fs.existsSync(streamPortFile)) fs.unlinkSync(streamPortFile);
+    if (fs.existsSync(launchFile)) fs.unlinkSync(launchFile);
     if (isWindows) {
       const portFile = getPortFile(session);
       if (fs.existsSync(portFile)) fs.unlinkSync(portFile);
@@ -248,6 +255,29 @@ export function getStreamPortFile(session?: string): string {
   return path.join(getRuntimeDir(), `${sess}.stream`);
 }
 
+/**
+ * Split a `host:port` listen address (`[::1]:9300` for IPv6)
+ */
+export function parseListenAddress(addr: string): { host: string; port: number } {
+  const sep = addr.lastIndexOf(':');
+  const port = parseInt(addr.slice(sep + 1), 10);
+  if (sep < 0 || !Number.isInteger(port) || port < 0 || port > 65535) {
+    throw new Error(`Invalid listen address: ${addr} (expected host:port)`);
+  }
+  const host = addr.slice(0, sep).replace(/^\[(.*)\]$/, '$1');
+  return { host: host || '127.0.0.1', port };
+}
+
+/**
+ * Compare a client's token with the expected one in constant time
+ */
+function tokenMatches(given: string | undefined, expected: string): boolean {
+  if (given === undefined) return false;
+  const a = Buffer.from(given);
+  const b = Buffer.from(expected);
+  return a.length === b.length && crypto.timingSafeEqual(a, b);
+}
+
 /**
  * Start the daemon server
  * @param options.streamPort Port for WebSocket stream server (0 to disable)
@@ -260,6 +290,29 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
   let shuttingDown = false;
   let mcpInitialized = false;
 
+  // Idle shutdown: the daemon exits once no command has arrived for this long
+  const idleTimeoutSecs = parseInt(process.env.AGENT_BROWSER_IDLE_TIMEOUT || '0', 10);
+  const idleTimeoutMs = idleTimeoutSecs > 0 ? idleTimeoutSecs * 1000 : 0;
+  let idleTimer: ReturnType<typeof setTimeout> | null = null;
+  let idleDeadline = 0;
+
+  const resetIdleTimer = () => {
+    if (!idleTimeoutMs || shuttingDown) return;
+    if (idleTimer) clearTimeout(idleTimer);
+    idleDeadline = Date.now() + idleTimeoutMs;
+    idleTimer = setTimeout(async () => {
+      if (browser) await savePersistState(browser);
+      await shutdown();
+    }, idleTimeoutMs);
+  };
+
+  // Add the time left before idle shutdown to a status response
+  const withIdleRemaining = (action: string, response: Response): Response => {
+    if (action !== 'status' || !response.success || !idleTimeoutMs) return response;
+    const idleRemaining = Math.max(0, idleDeadline - Date.now());
+    return { ...response, data: { ...(response.data as object), idleRemaining } };
+  };
+
   // Start stream server if port is specified (native backend only)
   const streamPort =
     options?.streamPort ??
@@ -274,7 +327,10 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
     fs.writeFileSync(streamPortFile, streamPort.toString());
   }
 
-  const server = net.createServer((socket) => {
+  // Clients on the --listen address must send this token with every command
+  const remoteToken = process.env.AGENT_BROWSER_REMOTE_TOKEN || undefined;
+
+  const handleConnection = (socket: net.Socket, remote: boolean) => {
     let buffer = '';
 
     socket.on('data', async (data) => {
@@ -297,6 +353,24 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
             continue;
           }
 
+          if (remote && remoteToken && !tokenMatches(parseResult.command.token, remoteToken)) {
+            const resp = errorResponse(parseResult.command.id, 'Unauthorized: invalid or missing token');
+            socket.write(serializeResponse(resp) + '\n');
+            continue;
+          }
+
+          // Health queries don't count as activity, so status can report the time left
+          if (parseResult.command.action !== 'status' && parseResult.command.action !== 'ping') {
+            resetIdleTimer();
+          }
+
+          // Health check: answered by the daemon itself, without launching a browser
+          if (parseResult.command.action === 'ping') {
+            const data: PingData = { pid: process.pid, uptime: Math.round(process.uptime() * 1000) };
+            socket.write(serializeResponse(successResponse(parseResult.command.id, data)) + '\n');
+            continue;
+          }
+
           if (mcpBackend) {
             // playwright-mcp backend
             if (!mcpInitialized && parseResult.command.action !== 'close') {
@@ -319,7 +393,7 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
             }
 
             const response = await executeMCPCommand(parseResult.command, mcpBackend);
-            socket.write(serializeResponse(response) + '\n');
+            socket.write(serializeResponse(withIdleRemaining(parseResult.command.action, response)) + '\n');
           } else if (browser) {
             // Native Playwright backend
             if (
@@ -358,11 +432,19 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
                       .map((a) => a.trim())
                       .filter(Boolean)
                   : undefined;
+                const browserEnv = process.env.AGENT_BROWSER_BROWSER;
+                const browserType =
+                  browserEnv === 'firefox' || browserEnv === 'webkit' ? browserEnv : 'chromium';
                 await browser.launch({
                   id: 'auto',
                   action: 'launch',
                   headless: !headedEnv,
-                  executablePath: getDefaultChromePath(),
+                  browser: browserType,
+                  // The detected system Chrome is only a default for Chromium
+                  executablePath:
+                    browserType === 'chromium'
+                      ? getDefaultChromePath()
+                      : process.env.AGENT_BROWSER_EXECUTABLE_PATH,
                   extensions: extensions,
                   storageState: persistState,
                   profile: process.env.AGENT_BROWSER_PROFILE,
@@ -389,7 +471,7 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
             }
 
             const response = await executeCommand(parseResult.command, browser);
-            socket.write(serializeResponse(response) + '\n');
+            socket.write(serializeResponse(withIdleRemaining(parseResult.command.action, response)) + '\n');
           }
         } catch (err) {
           const message = err instanceof Error ? err.message : String(err);
@@ -401,7 +483,9 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
     socket.on('error', () => {
       // Client disconnected, ignore
     });
-  });
+  };
+
+  const server = net.createServer((socket) => handleConnection(socket, false));
 
   const pidFile = getPidFile();
 
@@ -430,9 +514,23 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
     process.exit(1);
   });
 
+  // --listen: also accept TCP clients, e.g. a CLI on another machine using --remote
+  let remoteServer: net.Server | null = null;
+  if (process.env.AGENT_BROWSER_LISTEN) {
+    const { host, port } = parseListenAddress(process.env.AGENT_BROWSER_LISTEN);
+    remoteServer = net.createServer((socket) => handleConnection(socket, true));
+    remoteServer.on('error', (err) => {
+      console.error('Listen error:', err);
+      cleanupSocket();
+      process.exit(1);
+    });
+    remoteServer.listen(port, host);
+  }
+
   const shutdown = async () => {
     if (shuttingDown) return;
     shuttingDown = true;
+    if (idleTimer) clearTimeout(idleTimer);
 
     if (streamServer) {
       await streamServer.stop();
@@ -448,10 +546,13 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
     if (browser) await browser.close();
     if (mcpBackend) await mcpBackend.close();
     server.close();
+    remoteServer?.close();
     cleanupSocket();
     process.exit(0);
   };
 
+  resetIdleTimer();
+
   process.on('SIGINT', shutdown);
   process.on('SIGTERM', shutdown);
   process.on('SIGHUP', shutdown);
@@ -478,8 +579,6 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
   process.stdin.resume();
 }
 
-import type { Command, Response } from './types.js';
-
 async function executeMCPCommand(
   command: Command,
   backend: PlaywrightMCPBackend
@@ -1,5 +1,5 @@
 import { describe, it, expect } from 'vitest';
-import { parseCommand } from './protocol.js';
+import { parseCommand, serializeResponse, successResponse, DAEMON_VERSION } from './protocol.js';
 
 // Helper to create command JSON string
 const cmd = (obj: object) => JSON.stringify(obj);
@@ -480,6 +480,26 @@ describe('parseCommand', () => {
       const result = parseCommand(cmd({ id: '1', action: 'launch', cdpPort: '9222' }));
       expect(result.success).toBe(true);
     });
+
+    // The stealth shapes the CLI sends for --stealth, --stealth basic and an evasion list
+    it('should accept every stealth form in launch and configure', () => {
+      const forms = [
+        true,
+        false,
+        { level: 'full' },
+        { level: 'basic' },
+        { evasions: ['navigator.webdriver', 'chrome.app'] },
+      ];
+      for (const stealth of forms) {
+        expect(parseCommand(cmd({ id: '1', action: 'launch', headless: true, stealth })).success).toBe(true);
+        expect(parseCommand(cmd({ id: '1', action: 'configure', headless: true, stealth })).success).toBe(true);
+      }
+    });
+
+    it('should reject an unknown stealth level or empty evasion list', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'configure', stealth: { level: 'max' } })).success).toBe(false);
+      expect(parseCommand(cmd({ id: '1', action: 'launch', stealth: { evasions: [] } })).success).toBe(false);
+    });
   });
 
   describe('mouse actions', () => {
@@ -1006,6 +1026,13 @@ describe('parseCommand', () => {
     });
   });
 
+  describe('ping', () => {
+    it('should parse ping command', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'ping' }));
+      expect(result.success).toBe(true);
+    });
+  });
+
   describe('invalid commands', () => {
     it('should reject unknown action', () => {
       const result = parseCommand(cmd({ id: '1', action: 'unknown' }));
@@ -1023,3 +1050,11 @@ describe('parseCommand', () => {
     });
   });
 });
+
+describe('serializeResponse', () => {
+  it('should include the daemon version', () => {
+    const parsed = JSON.parse(serializeResponse(successResponse('1', {})));
+    expect(parsed.version).toBe(DAEMON_VERSION);
+    expect(DAEMON_VERSION).not.toBe('unknown');
+  });
+});
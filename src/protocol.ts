@@ -1,3 +1,4 @@
+import * as fs from 'fs';
 import { z } from 'zod';
 import type { Command, Response } from './types.js';
 
@@ -5,12 +6,21 @@ import type { Command, Response } from './types.js';
 const baseCommandSchema = z.object({
   id: z.string(),
   action: z.string(),
+  token: z.string().optional(),
 });
 
+// Stealth: a boolean, `{ level }` preset or `{ evasions }` list
+const stealthSchema = z.union([
+  z.boolean(),
+  z.object({ level: z.enum(['basic', 'full']) }),
+  z.object({ evasions: z.array(z.string().min(1)).min(1) }),
+]);
+
 // Individual action schemas
 const launchSchema = baseCommandSchema.extend({
   action: z.literal('launch'),
   headless: z.boolean().optional(),
+  stealth: stealthSchema.optional(),
   viewport: z
     .object({
       width: z.number().positive(),
@@ -207,6 +217,13 @@ const routeSchema = baseCommandSchema.extend({
     })
     .optional(),
   abort: z.boolean().optional(),
+  continue: z
+    .object({
+      url: z.string().optional(),
+      method: z.string().optional(),
+      headers: z.record(z.string()).optional(),
+    })
+    .optional(),
 });
 
 const unrouteSchema = baseCommandSchema.extend({
@@ -670,7 +687,7 @@ const inputTouchSchema = baseCommandSchema.extend({
 const configureSchema = baseCommandSchema.extend({
   action: z.literal('configure'),
   headless: z.boolean().optional(),
-  stealth: z.boolean().optional(),
+  stealth: stealthSchema.optional(),
   profile: z.string().optional(),
   userAgent: z.string().optional(),
   args: z.string().optional(),
@@ -683,6 +700,10 @@ const statusSchema = baseCommandSchema.extend({
   action: z.literal('status'),
 });
 
+const pingSchema = baseCommandSchema.extend({
+  action: z.literal('ping'),
+});
+
 const pressSchema = baseCommandSchema.extend({
   action: z.literal('press'),
   key: z.string().min(1),
@@ -710,6 +731,7 @@ const evaluateSchema = baseCommandSchema.extend({
   action: z.literal('evaluate'),
   script: z.string().min(1),
   args: z.array(z.unknown()).optional(),
+  await: z.boolean().optional(),
 });
 
 const waitSchema = baseCommandSchema.extend({
@@ -904,6 +926,7 @@ const commandSchema = z.discriminatedUnion('action', [
   inputTouchSchema,
   configureSchema,
   statusSchema,
+  pingSchema,
 ]);
 
 // Parse result type
@@ -955,8 +978,21 @@ export function errorResponse(id: string, error: string): Response {
 }
 
 /**
- * Serialize a response to JSON string
+ * Package version of this daemon, read once from package.json
+ */
+export const DAEMON_VERSION: string = (() => {
+  try {
+    const pkg = fs.readFileSync(new URL('../package.json', import.meta.url), 'utf8');
+    return JSON.parse(pkg).version ?? 'unknown';
+  } catch {
+    return 'unknown';
+  }
+})();
+
+/**
+ * Serialize a response to JSON string. Every response carries the daemon
+ * version so the CLI can warn when it talks to a daemon from another release.
  */
 export function serializeResponse(response: Response): string {
-  return JSON.stringify(response);
+  return JSON.stringify({ ...response, version: DAEMON_VERSION });
 }
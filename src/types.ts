@@ -4,12 +4,18 @@ import type { Page, Browser, BrowserContext } from 'playwright-core';
 export interface BaseCommand {
   id: string;
   action: string;
+  // Remote token, sent by clients on the daemon's --listen address
+  token?: string;
 }
 
 // Action-specific command types
+// Stealth setting: on/off, a preset, or only the listed evasions
+export type StealthSetting = boolean | { level: 'basic' | 'full' } | { evasions: string[] };
+
 export interface LaunchCommand extends BaseCommand {
   action: 'launch';
   headless?: boolean;
+  stealth?: StealthSetting;
   viewport?: { width: number; height: number };
   browser?: 'chromium' | 'firefox' | 'webkit';
   headers?: Record<string, string>;
@@ -206,6 +212,11 @@ export interface RouteCommand extends BaseCommand {
     headers?: Record<string, string>;
   };
   abort?: boolean;
+  continue?: {
+    url?: string;
+    method?: string;
+    headers?: Record<string, string>;
+  };
 }
 
 export interface UnrouteCommand extends BaseCommand {
@@ -736,6 +747,7 @@ export interface EvaluateCommand extends BaseCommand {
   action: 'evaluate';
   script: string;
   args?: unknown[];
+  await?: boolean;
 }
 
 export interface WaitCommand extends BaseCommand {
@@ -924,12 +936,13 @@ export type Command =
   | InputKeyboardCommand
   | InputTouchCommand
   | ConfigureCommand
-  | StatusCommand;
+  | StatusCommand
+  | PingCommand;
 
 export interface ConfigureCommand extends BaseCommand {
   action: 'configure';
   headless?: boolean;
-  stealth?: boolean;
+  stealth?: StealthSetting;
   profile?: string;
   userAgent?: string;
   args?: string;
@@ -942,6 +955,15 @@ export interface StatusCommand extends BaseCommand {
   action: 'status';
 }
 
+export interface PingCommand extends BaseCommand {
+  action: 'ping';
+}
+
+export interface PingData {
+  pid: number;
+  uptime: number;
+}
+
 // Response types
 export interface SuccessResponse<T = unknown> {
   id: string;